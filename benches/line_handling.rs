@@ -0,0 +1,82 @@
+//! The handling of the cargo output by the binary, line by line: the
+//! redaction, the status line, the folded diagnostics and the `--format`
+//! template. The output of a large build (the `build.txt` fixture with
+//! thousands of `Compiling` lines in between, the way a big workspace looks) is
+//! fed through the binary with `--dry-filter`, so no cargo is run. The stderr
+//! isn't a terminal, so the status is drawn as a heartbeat only. Run with:
+//!
+//! ```sh
+//! cargo bench --bench line_handling
+//! ```
+
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// How many more crates the build compiles.
+const CRATES: usize = 20_000;
+
+/// The build output of the fixture, with `crates` more crates compiled.
+fn output(crates: usize) -> String {
+    let fixture = include_str!("../tests/fixtures/build.txt");
+    let (head, tail) = fixture
+        .split_once("warning:")
+        .expect("the fixture has a warning");
+    let mut output = head.to_owned();
+    for index in 0..crates {
+        output.push_str(&format!(
+            "\x1b[1m\x1b[32m   Compiling\x1b[0m crate-{} v0.1.{} (/tmp/workspace/crate-{})\n",
+            index, index, index
+        ));
+        if index % 100 == 0 {
+            output.push_str(
+                "\x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: unused variable: `x`\x1b[0m\n\
+                 \x1b[1m\x1b[34m --> \x1b[0msrc/lib.rs:1:5\n\n",
+            );
+        }
+    }
+    output.push_str("warning:");
+    output.push_str(tail);
+    output
+}
+
+/// Writes the output where the binary replays it from.
+fn transcript(output: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join("line-handling.txt");
+    std::fs::write(&path, output).expect("the transcript is written");
+    path
+}
+
+fn line_handling(c: &mut Criterion) {
+    let output = output(CRATES);
+    let transcript = transcript(&output);
+    let mut group = c.benchmark_group("line_handling");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(output.lines().count() as u64));
+    group.bench_function("dry_filter", |b| {
+        b.iter(|| {
+            let status = Command::new(env!("CARGO_BIN_EXE_cargo-single-line"))
+                .arg("single-line")
+                .arg("--dry-filter")
+                .arg(&transcript)
+                .args(["--compact-diagnostics", "--format", "{icon} {crate:bold}"])
+                .arg("build")
+                .env("CARGO_SINGLE_LINE_NO_UPDATE_CHECK", "1")
+                .env_remove("CARGO_SINGLE_LINE_DEPTH")
+                .env_remove("CARGO_SINGLE_LINE_PRESET")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .expect("the binary runs");
+            assert!(status.success());
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, line_handling);
+criterion_main!(benches);
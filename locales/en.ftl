@@ -0,0 +1,111 @@
+# The messages of cargo-single-line, in English.
+#
+# The catalogs are Fluent files (https://projectfluent.org/). Only a subset of
+# the syntax is supported: messages, variables, string literals and select
+# expressions on numbers (`one` is selected for 1, unless there's a variant for
+# the exact number). The status verbs like `Failed` stay in English to match
+# the cargo's own.
+
+## The end of the build
+
+finished-profile-in = `{ $profile }` profile in { $duration }
+finished-in = in { $duration }
+compiled-crates = ({ $count ->
+        [one] 1 crate
+       *[other] { $count } crates
+    })
+failed-after = after { $duration }
+failed-crates-after = { $crates } after { $duration }
+first-error = (first error at { $duration })
+cancelled-after = after { $duration }
+cancelling = cancelling…
+stalled = the build has stalled, terminating cargo
+keys-passing-through = passing the output through, press `v` to collapse it again
+keys-collapsing = collapsing the output again
+keys-paused = the status line is paused, press `p` to resume
+localized-output = cargo's output doesn't look English, so it isn't collapsed; see `--locale-prefixes` and `--force-english`
+stall-hint = no progress for a while; check your connection or try `--offline`
+notify-succeeded = cargo { $subcommand } succeeded
+notify-failed = cargo { $subcommand } failed
+watch-first-run = run #1
+watch-run = run #{ $run } ({ $succeeded } succeeded, { $failed } failed, { $total } in total)
+watch-waiting = watching { $path } for changes, Ctrl-C to stop
+folded-lines = { $count ->
+        [one] (+1 line)
+       *[other] (+{ $count } lines)
+    }
+heartbeat = still building after { $duration }: { $status }
+
+## Notes and warnings after the build
+
+duplicates = { $count ->
+        [one] 1 crate
+       *[other] { $count } crates
+    } compiled at multiple versions: { $crates }
+lock-changes = Cargo.lock changes: { $changes }
+lock-added = { $count } added
+lock-removed = { $count } removed
+lock-updated = { $count } updated
+lock-upgraded = { $count } upgraded
+lock-downgraded = { $count } downgraded
+ice-saved = the compiler has crashed, the full report is saved to { $path }
+folded-diagnostics = the diagnostics in full:
+replay = the last status lines before the failure:
+log-file-saved = the full output is saved to { $path }
+rebuilt = why the crates were rebuilt:
+nothing-rebuilt = no workspace crate was rebuilt
+slow-crates = slow crates took { $duration } ({ $percent }% of the build): { $crates }
+slowest-crates = the slowest crates of the build:
+throughput = crates started per { $bucket }: { $sparkline } (at most { $peak })
+compared-to-previous = { $duration } ({ $delta } vs previous)
+warnings-compared = { $warnings ->
+        [one] 1 warning
+       *[other] { $warnings } warnings
+    } ({ $delta })
+regression = the build took { $duration }, { $percent }% longer than the baseline of { $baseline }
+diagnostics-summary = { $errors ->
+        [one] 1 error
+       *[other] { $errors } errors
+    } and { $warnings ->
+        [one] 1 warning
+       *[other] { $warnings } warnings
+    }, by crate and lint:
+reported-times = (reported { $count } times)
+update-available = { $name } { $version } is available, run `cargo single-line self-update` to install it
+
+## Hints on the status line
+
+usually = (usually ~{ $duration })
+downloaded-so-far = { $count } { $count ->
+        [one] crate
+       *[other] crates
+    } so far…
+downloaded-size-so-far = { $count } { $count ->
+        [one] crate
+       *[other] crates
+    } ({ $size }) so far…
+downloaded-of-total = { $count }/~{ $total } { $total ->
+        [one] crate
+       *[other] crates
+    } ({ $size })…
+downloaded-in = { $count } { $count ->
+        [one] crate
+       *[other] crates
+    } in { $duration }
+lock-waiting = waiting for the file lock on { $lock } for { $duration }
+lock-holder = held by { $command } (pid { $pid }, { $path })
+tests-progress = tests: { $counts }
+tests-passed = { $count } passed
+tests-failed = { $count } failed
+tests-ignored = { $count } ignored
+tests-running = { $count } running
+fuzzing = #{ $runs }: { $speed } exec/s, corpus { $corpus } ({ $size }), coverage { $coverage }
+
+## The cargo output
+
+linker-invocation = linker invocation ({ $length } chars)
+linker-invocation-saved = linker invocation ({ $length } chars) — saved to { $path }
+fresh-crates = { $count ->
+        [one] 1 crate
+       *[other] { $count } crates
+    }
@@ -0,0 +1,58 @@
+//! Response files: an `@path` argument is replaced with the arguments listed in
+//! the file, one per line, like rustc does. Handy for the long invocations
+//! generated by build systems.
+//!
+//! Only the arguments before `--` are expanded, the rest belongs to the binary
+//! being run.
+
+use std::ffi::OsString;
+
+/// Replaces every `@path` argument with the contents of the file.
+pub fn expand(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>, String> {
+    let mut expanded = Vec::new();
+    let mut args = args.into_iter();
+    for arg in args.by_ref() {
+        if arg == "--" {
+            expanded.push(arg);
+            break;
+        }
+        match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| format!("unable to read arguments from {}: {}", path, e))?;
+                expanded.extend(split(&contents));
+            }
+            _ => expanded.push(arg),
+        }
+    }
+    expanded.extend(args);
+    Ok(expanded)
+}
+
+/// Splits the contents of a response file into the arguments, one per line.
+/// Empty lines are skipped.
+fn split(contents: &str) -> impl Iterator<Item = OsString> + '_ {
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(OsString::from)
+}
+
+#[test]
+fn splits_lines() {
+    let args: Vec<OsString> =
+        split("--target\r\naarch64-unknown-linux-gnu\n\n--features=a b\n").collect();
+    assert_eq!(
+        args,
+        ["--target", "aarch64-unknown-linux-gnu", "--features=a b"]
+    );
+}
+
+#[test]
+fn expands_before_separator() {
+    let args = ["build", "@", "--", "@args.txt"].map(OsString::from);
+    assert_eq!(expand(args.clone()).unwrap(), args);
+    assert!(expand([OsString::from("@/nonexistent/args.txt")])
+        .unwrap_err()
+        .starts_with("unable to read arguments from /nonexistent/args.txt"));
+}
@@ -0,0 +1,39 @@
+//! Files saved for the user to look at after the build, in the
+//! `single-line` directory of cargo's target directory.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{cargo_args::CargoArgs, history};
+
+/// The target directory of the build being run.
+pub fn target_dir(cargo_args: &CargoArgs) -> Option<PathBuf> {
+    cargo_args
+        .target_dir
+        .clone()
+        .or_else(|| std::env::var_os("CARGO_TARGET_DIR").map(PathBuf::from))
+        .or_else(|| history::project_root(cargo_args).map(|root| root.join("target")))
+}
+
+/// Saves the `contents` under the `name`, returning the path to the file.
+pub fn save(cargo_args: &CargoArgs, name: &str, contents: &str) -> io::Result<PathBuf> {
+    let dir = target_dir(cargo_args)
+        .ok_or_else(|| io::Error::other("unable to locate the target directory"))?
+        .join("single-line");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    std::fs::write(&path, contents)?;
+    Ok(relative(&path))
+}
+
+/// The path relative to the current directory, if it's inside, to keep the
+/// messages short.
+pub fn relative(path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path)
+        .to_path_buf()
+}
@@ -0,0 +1,128 @@
+//! Build duration baselines, used to warn about build time regressions.
+//!
+//! Baselines are kept per cargo subcommand (a `check` is much faster than a
+//! `build`) in `.cargo-single-line/baseline.json`.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::history::{self, History};
+
+/// Default allowed slowdown, in percents.
+pub const DEFAULT_THRESHOLD: f64 = 20.0;
+
+/// Baseline durations of the builds, per subcommand, in seconds.
+#[derive(Debug)]
+pub struct Baselines {
+    path: PathBuf,
+    pub durations: BTreeMap<String, f64>,
+}
+
+impl Baselines {
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let path = root.join(history::DATA_DIR).join("baseline.json");
+        let durations = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Baselines { path, durations })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&self.durations)?)
+    }
+
+    /// Checks the build duration against the baseline. Returns the slowdown in
+    /// percents if it exceeds the `threshold`.
+    pub fn regression(&self, command: &str, duration: Duration, threshold: f64) -> Option<f64> {
+        let baseline = *self.durations.get(command)?;
+        if baseline <= 0.0 {
+            return None;
+        }
+        let slowdown = (duration.as_secs_f64() - baseline) / baseline * 100.0;
+        (slowdown > threshold).then_some(slowdown)
+    }
+}
+
+/// Runs the `baseline` subcommand:
+///
+/// * `baseline set [COMMAND]` takes the latest successful build (of the
+///   `COMMAND`, if given) from the history as the baseline,
+/// * `baseline show` prints the baselines,
+/// * `baseline clear [COMMAND]` removes the baselines (for the `COMMAND`).
+pub fn run(root: &Path, args: &[&str]) -> Result<(), String> {
+    let mut baselines = Baselines::load(root).map_err(|e| e.to_string())?;
+    match args {
+        ["set", rest @ ..] if rest.len() <= 1 => {
+            let command = rest.first().copied();
+            let history = History::load(root).map_err(|e| e.to_string())?;
+            let build = history
+                .builds
+                .iter()
+                .rev()
+                .filter(|build| build.success)
+                .find(|build| command.is_none_or(|command| build.command == command))
+                .ok_or("no successful build recorded yet")?;
+            baselines
+                .durations
+                .insert(build.command.clone(), build.duration);
+            println!(
+                "{}: {}",
+                build.command,
+                crate::format::duration(Duration::from_secs_f64(build.duration))
+            );
+        }
+        ["show"] => {
+            if baselines.durations.is_empty() {
+                println!("No baselines set, use `cargo single-line baseline set`.");
+            }
+            for (command, duration) in &baselines.durations {
+                println!(
+                    "{}: {}",
+                    command,
+                    crate::format::duration(Duration::from_secs_f64(*duration))
+                );
+            }
+            return Ok(());
+        }
+        ["clear"] => baselines.durations.clear(),
+        ["clear", command] => {
+            baselines.durations.remove(*command);
+        }
+        _ => {
+            return Err(
+                "usage: cargo single-line baseline <set [COMMAND]|show|clear [COMMAND]>".into(),
+            )
+        }
+    }
+    baselines.save().map_err(|e| e.to_string())
+}
+
+#[test]
+fn detects_regressions() {
+    let baselines = Baselines {
+        path: PathBuf::new(),
+        durations: [("build".to_owned(), 10.0)].into_iter().collect(),
+    };
+    assert_eq!(
+        baselines.regression("build", Duration::from_secs(11), DEFAULT_THRESHOLD),
+        None
+    );
+    assert_eq!(
+        baselines.regression("build", Duration::from_secs(15), DEFAULT_THRESHOLD),
+        Some(50.0)
+    );
+    assert_eq!(
+        baselines.regression("check", Duration::from_secs(100), DEFAULT_THRESHOLD),
+        None
+    );
+}
@@ -0,0 +1,441 @@
+//! Bits of the cargo command line we are interested in.
+
+use std::{ffi::OsString, path::PathBuf};
+
+/// Cargo options that come before the subcommand and take a value.
+const GLOBAL_OPTIONS_WITH_VALUE: &[&str] = &["--config", "-C", "-Z"];
+
+/// Subcommands whose product is their output rather than a build.
+const OUTPUT_SUBCOMMANDS: &[&str] = &[
+    "metadata",
+    "tree",
+    "pkgid",
+    "read-manifest",
+    "config",
+    "locate-project",
+    "verify-project",
+];
+
+/// Cargo's own subcommands (and the aliases), which all accept `--color`. The
+/// third-party ones might not.
+const COLOR_SUBCOMMANDS: &[&str] = &[
+    "add",
+    "b",
+    "bench",
+    "build",
+    "c",
+    "check",
+    "clean",
+    "clippy",
+    "config",
+    "d",
+    "doc",
+    "fetch",
+    "fix",
+    "generate-lockfile",
+    "help",
+    "info",
+    "init",
+    "install",
+    "locate-project",
+    "login",
+    "logout",
+    "metadata",
+    "new",
+    "owner",
+    "package",
+    "pkgid",
+    "publish",
+    "r",
+    "read-manifest",
+    "remove",
+    "report",
+    "rm",
+    "run",
+    "rustc",
+    "rustdoc",
+    "search",
+    "t",
+    "test",
+    "tree",
+    "uninstall",
+    "update",
+    "vendor",
+    "verify-project",
+    "version",
+    "yank",
+];
+
+/// Subcommands accepting `--message-format json`, `run` aside: the program
+/// has the stdout on its own.
+const JSON_SUBCOMMANDS: &[&str] = &[
+    "build", "b", "check", "c", "clippy", "test", "t", "bench", "doc", "d", "rustc", "rustdoc",
+    "fix",
+];
+
+/// The `--message-format` given to cargo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    /// One line per diagnostic.
+    Short,
+    /// Diagnostics are printed to stdout as JSON, for the tools to consume.
+    Json,
+}
+
+impl MessageFormat {
+    /// Parses the value of `--message-format`, which might be a comma-separated
+    /// list like `json,json-diagnostic-short`.
+    fn parse(value: &str) -> Self {
+        let mut format = MessageFormat::Human;
+        for part in value.split(',') {
+            if part.starts_with("json") {
+                return MessageFormat::Json;
+            } else if part == "short" {
+                format = MessageFormat::Short;
+            }
+        }
+        format
+    }
+}
+
+/// A summary of the arguments forwarded to cargo.
+#[derive(Debug, Default)]
+pub struct CargoArgs {
+    /// The `+toolchain` selector (without the `+`).
+    pub toolchain: Option<String>,
+    /// The cargo subcommand, e.g. `build`.
+    pub subcommand: Option<String>,
+    /// Position of the subcommand among the arguments.
+    subcommand_index: Option<usize>,
+    /// The argument right after the subcommand, like the `test` of
+    /// `cargo miri test`.
+    nested_subcommand: Option<String>,
+    /// Position of the `--` separating the arguments of the binary being run.
+    separator_index: Option<usize>,
+    /// The value of `--color`, if given.
+    pub color: Option<String>,
+    pub manifest_path: Option<PathBuf>,
+    /// The value of `--target-dir`, if given.
+    pub target_dir: Option<PathBuf>,
+    /// Packages selected with `-p`/`--package`.
+    pub packages: Vec<String>,
+    /// How many times `-v` is given.
+    pub verbosity: usize,
+    /// Whether `-q`/`--quiet` is given.
+    pub quiet: bool,
+    pub message_format: MessageFormat,
+}
+
+impl CargoArgs {
+    pub fn parse(args: &[OsString]) -> Self {
+        let mut parsed = CargoArgs::default();
+        // Non-UTF-8 arguments are of no interest, but they still count when it
+        // comes to positions.
+        let mut args = args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| (index, arg.to_str().unwrap_or_default()))
+            .peekable();
+        while let Some((index, arg)) = args.next() {
+            if arg == "--" {
+                // The rest is for the binary being run.
+                parsed.separator_index = Some(index);
+                break;
+            }
+            if index == 0 {
+                if let Some(toolchain) = arg.strip_prefix('+') {
+                    parsed.toolchain = Some(toolchain.to_owned());
+                    continue;
+                }
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+                _ => match arg.strip_prefix("-p") {
+                    Some(value) if !value.is_empty() && !arg.starts_with("--") => {
+                        ("-p", Some(value))
+                    }
+                    _ => (arg, None),
+                },
+            };
+            // The separator is never an option value: `-p --` is a mistake for
+            // cargo to report, not a package to select.
+            let mut value = || {
+                value.or_else(|| {
+                    args.next_if(|(_, next)| *next != "--")
+                        .map(|(_, value)| value)
+                })
+            };
+            match name {
+                "--manifest-path" => parsed.manifest_path = value().map(PathBuf::from),
+                "--target-dir" => parsed.target_dir = value().map(PathBuf::from),
+                "-p" | "--package" => parsed.packages.extend(value().map(str::to_owned)),
+                "--color" => parsed.color = value().map(str::to_owned),
+                "--message-format" => {
+                    if let Some(value) = value() {
+                        parsed.message_format = MessageFormat::parse(value);
+                    }
+                }
+                "--verbose" => parsed.verbosity += 1,
+                "-q" | "--quiet" => parsed.quiet = true,
+                _ if is_short_verbose(name) => parsed.verbosity += name.len() - 1,
+                _ if parsed.subcommand.is_none() => {
+                    if GLOBAL_OPTIONS_WITH_VALUE.contains(&name) {
+                        let _ = value();
+                    } else if !name.is_empty() && !name.starts_with('-') {
+                        parsed.subcommand = Some(name.to_owned());
+                        parsed.subcommand_index = Some(index);
+                    }
+                }
+                _ if parsed
+                    .subcommand_index
+                    .is_some_and(|subcommand| index == subcommand + 1)
+                    && !name.starts_with('-') =>
+                {
+                    parsed.nested_subcommand = Some(name.to_owned());
+                }
+                _ => {}
+            }
+        }
+        parsed
+    }
+
+    /// Inserts the `flags` into the `args` right after the subcommand: the
+    /// toolchain selector must stay the first argument, and the flags that
+    /// come after the subcommand are understood by all of them. They never go
+    /// past the `--`, where they would reach the binary being run instead.
+    pub fn inject(&self, args: &[OsString], flags: &[&str]) -> Vec<OsString> {
+        let position = match (self.subcommand_index, &self.toolchain) {
+            (Some(index), _) => index + 1,
+            (None, Some(_)) => 1,
+            (None, None) => 0,
+        };
+        let position = match self.separator_index {
+            Some(separator) => position.min(separator),
+            None => position,
+        };
+        let mut result = args[..position].to_vec();
+        result.extend(flags.iter().map(OsString::from));
+        result.extend_from_slice(&args[position..]);
+        result
+    }
+
+    /// Whether the test harness is run: `cargo test`, or `cargo miri test`
+    /// interpreting the tests.
+    pub fn runs_tests(&self) -> bool {
+        match self.subcommand.as_deref() {
+            Some("test") => true,
+            Some("miri") => self.nested_subcommand.as_deref() == Some("test"),
+            _ => false,
+        }
+    }
+
+    /// Whether the subcommand's output is the product, like `cargo metadata`,
+    /// so it's not to be touched at all.
+    pub fn produces_output(&self) -> bool {
+        self.subcommand
+            .as_deref()
+            .is_some_and(|subcommand| OUTPUT_SUBCOMMANDS.contains(&subcommand))
+    }
+
+    /// Whether `--color` can be passed along, that is the subcommand is one of
+    /// cargo's own (or there's none, like with `cargo --version`).
+    pub fn accepts_color(&self) -> bool {
+        self.subcommand
+            .as_deref()
+            .is_none_or(|subcommand| COLOR_SUBCOMMANDS.contains(&subcommand))
+    }
+
+    /// Whether cargo can be asked for the JSON messages, see
+    /// [`crate::messages`]. The format chosen by the user is not overridden.
+    pub fn supports_json(&self) -> bool {
+        self.message_format == MessageFormat::Human
+            && self
+                .subcommand
+                .as_deref()
+                .is_some_and(|subcommand| JSON_SUBCOMMANDS.contains(&subcommand))
+    }
+
+    /// The directory of the manifest of the project being built.
+    pub fn manifest_dir(&self) -> Option<PathBuf> {
+        match self.manifest_path.as_ref().and_then(|path| path.parent()) {
+            Some(dir) if !dir.as_os_str().is_empty() => Some(dir.to_path_buf()),
+            Some(_) => Some(PathBuf::from(".")),
+            None => std::env::current_dir().ok(),
+        }
+    }
+}
+
+/// Checks for `-v`, `-vv` and so on.
+fn is_short_verbose(arg: &str) -> bool {
+    arg.strip_prefix('-')
+        .is_some_and(|vs| !vs.is_empty() && vs.chars().all(|c| c == 'v'))
+}
+
+#[cfg(test)]
+fn parse_strs(args: &[&str]) -> CargoArgs {
+    let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+    CargoArgs::parse(&args)
+}
+
+#[test]
+fn selects_packages() {
+    let args = parse_strs(&[
+        "build",
+        "-pfoo",
+        "--package",
+        "bar",
+        "--manifest-path=ws/Cargo.toml",
+        "--target-dir",
+        "/tmp/target",
+        "--",
+        "-p",
+        "baz",
+    ]);
+    assert_eq!(args.packages, ["foo", "bar"]);
+    assert_eq!(args.manifest_path, Some(PathBuf::from("ws/Cargo.toml")));
+    assert_eq!(args.manifest_dir(), Some(PathBuf::from("ws")));
+    assert_eq!(args.target_dir, Some(PathBuf::from("/tmp/target")));
+}
+
+#[test]
+fn finds_subcommand() {
+    let args = parse_strs(&[
+        "+nightly",
+        "--color",
+        "never",
+        "-Zunstable-options",
+        "check",
+    ]);
+    assert_eq!(args.subcommand.as_deref(), Some("check"));
+    let args = parse_strs(&["--locked", "test", "--", "foo"]);
+    assert_eq!(args.subcommand.as_deref(), Some("test"));
+    assert_eq!(parse_strs(&["--version"]).subcommand, None);
+}
+
+#[test]
+fn detects_test_runs() {
+    assert!(parse_strs(&["test", "--lib"]).runs_tests());
+    assert!(parse_strs(&["+nightly", "miri", "test", "-p", "foo"]).runs_tests());
+    assert!(!parse_strs(&["miri", "run"]).runs_tests());
+    assert!(!parse_strs(&["miri", "--", "test"]).runs_tests());
+    assert!(!parse_strs(&["build", "test"]).runs_tests());
+}
+
+#[test]
+fn detects_verbosity() {
+    let args = parse_strs(&["build", "-vv", "--verbose"]);
+    assert_eq!(args.verbosity, 3);
+    assert!(!args.quiet);
+    let args = parse_strs(&["-q", "check", "--", "-v"]);
+    assert_eq!(args.verbosity, 0);
+    assert!(args.quiet);
+}
+
+#[test]
+fn detects_message_format() {
+    assert_eq!(parse_strs(&["build"]).message_format, MessageFormat::Human);
+    let args = parse_strs(&["build", "--message-format", "short"]);
+    assert_eq!(args.message_format, MessageFormat::Short);
+    let args = parse_strs(&["build", "--message-format=json-diagnostic-short,json"]);
+    assert_eq!(args.message_format, MessageFormat::Json);
+}
+
+#[test]
+fn injects_after_subcommand() {
+    let strs = |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).collect() };
+
+    let args = strs(&["+nightly", "--locked", "build", "--release"]);
+    let parsed = CargoArgs::parse(&args);
+    assert_eq!(parsed.toolchain.as_deref(), Some("nightly"));
+    assert_eq!(parsed.subcommand.as_deref(), Some("build"));
+    assert_eq!(
+        parsed.inject(&args, &["--color=always"]),
+        strs(&[
+            "+nightly",
+            "--locked",
+            "build",
+            "--color=always",
+            "--release"
+        ])
+    );
+
+    let args = strs(&["+nightly", "--version"]);
+    assert_eq!(
+        CargoArgs::parse(&args).inject(&args, &["--color=always"]),
+        strs(&["+nightly", "--color=always", "--version"])
+    );
+
+    let args = strs(&["--color", "never", "check"]);
+    let parsed = CargoArgs::parse(&args);
+    assert_eq!(parsed.color.as_deref(), Some("never"));
+    assert_eq!(parsed.subcommand.as_deref(), Some("check"));
+}
+
+#[test]
+fn detects_output_subcommands() {
+    let parse =
+        |args: &[&str]| CargoArgs::parse(&args.iter().map(OsString::from).collect::<Vec<_>>());
+    assert!(parse(&["metadata", "--format-version", "1"]).produces_output());
+    assert!(parse(&["+nightly", "-Zunstable-options", "config", "get"]).produces_output());
+    assert!(!parse(&["build"]).produces_output());
+    assert!(!parse(&["--version"]).produces_output());
+}
+
+#[test]
+fn detects_color_support() {
+    let parse =
+        |args: &[&str]| CargoArgs::parse(&args.iter().map(OsString::from).collect::<Vec<_>>());
+    assert!(parse(&["+nightly", "--locked", "b"]).accepts_color());
+    assert!(parse(&["--version"]).accepts_color());
+    assert!(!parse(&["fmt", "--check"]).accepts_color());
+    assert!(!parse(&["nextest", "run"]).accepts_color());
+}
+
+#[test]
+fn detects_json_support() {
+    let parse =
+        |args: &[&str]| CargoArgs::parse(&args.iter().map(OsString::from).collect::<Vec<_>>());
+    assert!(parse(&["clippy", "--all-targets"]).supports_json());
+    assert!(!parse(&["build", "--message-format", "short"]).supports_json());
+    assert!(!parse(&["run"]).supports_json());
+}
+
+#[test]
+fn respects_separator() {
+    let strs = |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).collect() };
+
+    let args = strs(&["run", "--", "--color=never"]);
+    let parsed = CargoArgs::parse(&args);
+    assert_eq!(parsed.color, None);
+    assert_eq!(
+        parsed.inject(&args, &["--color=always"]),
+        strs(&["run", "--color=always", "--", "--color=never"])
+    );
+
+    let args = strs(&["+nightly", "--", "run", "--color", "never"]);
+    let parsed = CargoArgs::parse(&args);
+    assert_eq!(parsed.subcommand, None);
+    assert_eq!(parsed.color, None);
+    assert_eq!(
+        parsed.inject(&args, &["--color=always"]),
+        strs(&[
+            "+nightly",
+            "--color=always",
+            "--",
+            "run",
+            "--color",
+            "never"
+        ])
+    );
+
+    let args = strs(&["run", "-p", "--", "--color", "never"]);
+    let parsed = CargoArgs::parse(&args);
+    assert!(parsed.packages.is_empty());
+    assert_eq!(parsed.color, None);
+    assert_eq!(
+        parsed.inject(&args, &["--color=always"]),
+        strs(&["run", "--color=always", "-p", "--", "--color", "never"])
+    );
+}
@@ -0,0 +1,122 @@
+//! The environment of the child cargo process.
+
+use std::{ffi::OsString, process::Command};
+
+/// Variables that survive `--clean-env`: without them cargo and rustup can't
+/// find the toolchains or a place for temporary files.
+const ESSENTIAL: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "TERM",
+    "TMPDIR",
+    "LANG",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    // Windows.
+    "SystemRoot",
+    "USERPROFILE",
+    "TEMP",
+    "TMP",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PATHEXT",
+    "COMSPEC",
+];
+
+/// A change to the environment requested on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvChange {
+    Set(OsString, OsString),
+    Unset(OsString),
+}
+
+impl EnvChange {
+    /// Parses the `KEY=VALUE` argument of `--env`.
+    pub fn parse_set(arg: &str) -> Result<Self, String> {
+        match arg.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(EnvChange::Set(key.into(), value.into())),
+            _ => Err(format!("expected KEY=VALUE, got {:?}", arg)),
+        }
+    }
+
+    /// Parses the `KEY` argument of `--unset-env`.
+    pub fn parse_unset(arg: &str) -> Result<Self, String> {
+        if arg.is_empty() || arg.contains('=') {
+            Err(format!("invalid variable name {:?}", arg))
+        } else {
+            Ok(EnvChange::Unset(arg.into()))
+        }
+    }
+}
+
+/// Applies the changes to the command. With `clean`, everything but the
+/// [`ESSENTIAL`] variables is removed first.
+pub fn apply(cmd: &mut Command, clean: bool, changes: &[EnvChange]) {
+    if clean {
+        // Removing the variables one by one rather than clearing the whole
+        // environment keeps `--dry-run` informative.
+        for (name, _) in std::env::vars_os() {
+            let is_essential = name.to_str().is_some_and(|name| {
+                ESSENTIAL
+                    .iter()
+                    .any(|essential| essential.eq_ignore_ascii_case(name))
+            });
+            if !is_essential {
+                cmd.env_remove(name);
+            }
+        }
+    }
+    for change in changes {
+        match change {
+            EnvChange::Set(key, value) => cmd.env(key, value),
+            EnvChange::Unset(key) => cmd.env_remove(key),
+        };
+    }
+}
+
+#[test]
+fn parses_changes() {
+    assert_eq!(
+        EnvChange::parse_set("RUSTFLAGS=-C target-cpu=native"),
+        Ok(EnvChange::Set(
+            "RUSTFLAGS".into(),
+            "-C target-cpu=native".into()
+        ))
+    );
+    assert_eq!(
+        EnvChange::parse_set("EMPTY="),
+        Ok(EnvChange::Set("EMPTY".into(), "".into()))
+    );
+    assert!(EnvChange::parse_set("=foo").is_err());
+    assert!(EnvChange::parse_set("FOO").is_err());
+    assert_eq!(
+        EnvChange::parse_unset("RUSTC_WRAPPER"),
+        Ok(EnvChange::Unset("RUSTC_WRAPPER".into()))
+    );
+    assert!(EnvChange::parse_unset("A=B").is_err());
+}
+
+#[test]
+fn applies_changes_in_order() {
+    let mut cmd = Command::new("cargo");
+    apply(
+        &mut cmd,
+        false,
+        &[
+            EnvChange::Set("FOO".into(), "1".into()),
+            EnvChange::Unset("FOO".into()),
+            EnvChange::Set("BAR".into(), "2".into()),
+        ],
+    );
+    let mut envs: Vec<_> = cmd.get_envs().collect();
+    envs.sort();
+    assert_eq!(
+        envs,
+        [
+            (std::ffi::OsStr::new("BAR"), Some(std::ffi::OsStr::new("2"))),
+            (std::ffi::OsStr::new("FOO"), None),
+        ]
+    );
+}
@@ -0,0 +1,1845 @@
+//! The command line interface: runs cargo and compacts its output on the way.
+
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    argfile, artifacts, baseline,
+    cargo_args::{CargoArgs, MessageFormat},
+    child_env, config,
+    crate_filter::CrateFilter,
+    diagnostics::{self, Diagnostics, Level},
+    doctor, downloads, dry_run,
+    duplicates::Duplicates,
+    events::{BuildEvent, EventSink},
+    exec,
+    filter::{CaptureRule, Compaction, LineFilter},
+    folding::Folding,
+    format, handoff,
+    heartbeat::Heartbeat,
+    hints,
+    history::{self, History},
+    hooks, ice, init, keys, l10n,
+    lines::{Segment, Segments, Text},
+    linker, localized, lock_changes,
+    lock_wait::LockWait,
+    lockfile,
+    log_file::LogFile,
+    messages, notify,
+    options::Options,
+    pager, panic,
+    progress::{self, Progress},
+    pty, rebuild, recursion, redact,
+    replay::Replay,
+    report,
+    screen::Screen,
+    signals::{self, Signal},
+    slow, sparkline, spinner,
+    stall::StallDetector,
+    starts_with, stats,
+    status::StatusLine,
+    status_file::StatusFile,
+    strip_colors, summary, svg,
+    tag::Tag,
+    terminal,
+    test_progress::TestProgress,
+    timings::{self, Timings},
+    title, tools,
+    trace::{self, trace},
+    transcript::{Recorder, Transcript},
+    unit::Unit,
+    update, verbose,
+    verbosity::{Class, Verbosity},
+    watch, workspace,
+};
+
+/// How often the main loop wakes up to check timers.
+const TICK: Duration = Duration::from_millis(250);
+
+/// Exit code used when the build is terminated because it has stalled.
+/// Matches `EX_TEMPFAIL` from `sysexits.h`.
+const STALL_EXIT_CODE: i32 = 75;
+
+/// Exit code used when the build is slower than the baseline and
+/// `--fail-on-regression` is set.
+const REGRESSION_EXIT_CODE: i32 = 3;
+
+/// Exit code used when the build is cancelled from the keyboard, as if it was
+/// interrupted with Ctrl-C.
+const CANCEL_EXIT_CODE: i32 = 130;
+
+/// Set by GitHub Actions to the file collecting the job summary in Markdown.
+const GITHUB_STEP_SUMMARY: &str = "GITHUB_STEP_SUMMARY";
+
+/// Events processed by the main loop.
+enum Event {
+    /// A line read from cargo's stderr, including the trailing newline.
+    Line(Text),
+    /// A progress update read from cargo's stderr (or stdout with
+    /// `--filter-stdout`), to be redrawn in place.
+    Update(String),
+    /// A line read from cargo's stdout in the `--json` mode, while running the
+    /// tests or with `--filter-stdout`.
+    Message(Text),
+    /// Cargo's stderr or stdout is closed (or reading it has failed).
+    Eof(std::io::Result<()>),
+    /// Periodic timer tick.
+    Tick,
+    /// A key pressed by the user.
+    Key(char),
+    /// A signal received, see [`signals::watch`].
+    Signal(Signal),
+}
+
+/// Reads the lines (and the updates) from the `input` and sends them over to
+/// the main loop as the `event`s.
+fn read_lines(
+    input: impl Read,
+    console: bool,
+    sender: &mpsc::Sender<Event>,
+    event: fn(Segment) -> Event,
+) -> std::io::Result<()> {
+    let mut input = Segments::new(BufReader::new(input));
+    while let Some(mut segment) = input.read()? {
+        let (Segment::Line(text) | Segment::Update(text)) = &mut segment;
+        if console {
+            text.edit(|text| Cow::Owned(pty::clean_line(text)));
+        }
+        text.edit(redact::redact);
+        if sender.send(event(segment)).is_err() {
+            // The main loop is gone, nobody's interested anymore.
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the plugin with the arguments of the process, like the
+/// `cargo-single-line` binary does.
+pub fn run() -> std::io::Result<()> {
+    trace::init();
+    let mut args = std::env::args_os().peekable();
+    // The first argument is meant to be skipped anyhow.
+    let _ = args.next();
+    // If run as a cargo plugin, skip this argument as well.
+    if args.peek().is_some_and(|arg| arg == "single-line") {
+        let _ = args.next();
+    }
+    let args = match argfile::expand(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("cargo-single-line: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let all_args = args.clone();
+    let (mut options, args) = match parse_options(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("cargo-single-line: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if options.dry_filter.is_some() {
+        // Nothing is built, so there's nothing to learn from or to tell about.
+        options.history = false;
+        options.update_check = false;
+        options.notify = false;
+        options.keys = false;
+        options.on_success = None;
+        options.on_failure = None;
+        options.on_warning = None;
+    }
+    l10n::init(options.lang.as_deref());
+    match redact::Redactor::new(options.redact_defaults, &options.redact) {
+        Ok(redactor) => redact::init(redactor),
+        Err(e) => {
+            eprintln!("cargo-single-line: invalid --redact pattern: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let rules = options
+        .collapse_patterns
+        .iter()
+        .map(|pattern| CaptureRule::new(pattern))
+        .collect::<Result<Vec<_>, _>>();
+    let filter = match rules {
+        Ok(rules) => rules.into_iter().fold(
+            LineFilter::new().with_default_prefixes(options.default_prefixes),
+            LineFilter::with_rule,
+        ),
+        Err(e) => {
+            eprintln!("cargo-single-line: invalid --collapse-pattern: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if options.watch {
+        // The runs are the same, but without `--watch` among the options.
+        let (options_args, cargo_args) = all_args.split_at(all_args.len() - args.len());
+        let run_args: Vec<_> = options_args
+            .iter()
+            .filter(|arg| *arg != "--watch")
+            .chain(cargo_args)
+            .cloned()
+            .collect();
+        let cargo_args = CargoArgs::parse(&args);
+        let root = history::project_root(&cargo_args).unwrap_or_else(|| PathBuf::from("."));
+        let is_terminal = atty::is(atty::Stream::Stderr);
+        let virtual_terminal = if is_terminal {
+            terminal::enable_virtual_terminal()
+        } else {
+            None
+        };
+        let colored = colored(
+            configured_color(&options),
+            &cargo_args,
+            is_terminal,
+            virtual_terminal,
+        );
+        watch::run(&run_args, &root, colored);
+    }
+
+    match args.first().and_then(|arg| arg.to_str()) {
+        Some("slow") => return slow(&args[1..]),
+        Some("baseline") => baseline(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("exec") => exec(&options, filter, &args[1..]),
+        Some("doctor") => {
+            let install_alias = match &args[1..] {
+                [] => false,
+                [arg] if arg == "--install-alias" => true,
+                [arg, ..] => unexpected_argument(arg),
+            };
+            if install_alias {
+                if let Err(e) = doctor::install_alias() {
+                    eprintln!("cargo-single-line: unable to install the alias: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            doctor::run(&cargo_path());
+            return Ok(());
+        }
+        Some("self-update") => {
+            let check_only = match &args[1..] {
+                [] => false,
+                [arg] if arg == "--check-only" => true,
+                [arg, ..] => unexpected_argument(arg),
+            };
+            match update::self_update(&cargo_path(), check_only) {
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    eprintln!("cargo-single-line: unable to update: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some("init") => {
+            // Not to be confused with `cargo init [PATH]`.
+            if let Some(shell) = args
+                .get(1)
+                .and_then(|arg| arg.to_str())
+                .and_then(init::Shell::from_name)
+            {
+                return init(shell, &args[2..]);
+            }
+        }
+        _ => {}
+    }
+    run_cargo(&options, filter, &args)
+}
+
+/// Splits the arguments into the plugin's options, on top of the configuration
+/// files, and the cargo arguments.
+fn parse_options(args: Vec<OsString>) -> Result<(Options, Vec<OsString>), String> {
+    // The project's configuration depends on the cargo arguments (think
+    // `--manifest-path`), which are only known once the options are parsed.
+    let mut rest = args.iter().cloned().peekable();
+    let given = Options::parse(&mut rest)?;
+    let (options_args, cargo_args) = args.split_at(args.len() - rest.count());
+    let defaults = config::load(&CargoArgs::parse(cargo_args), &given.presets)?;
+    let options = Options::parse_with(defaults, &mut options_args.iter().cloned().peekable())?;
+    Ok((options, cargo_args.to_vec()))
+}
+
+/// The cargo executable to run.
+fn cargo_path() -> OsString {
+    std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"))
+}
+
+/// Reports an unexpected argument of a subcommand and exits.
+fn unexpected_argument(arg: &OsStr) -> ! {
+    eprintln!("cargo-single-line: unexpected argument {:?}", arg);
+    std::process::exit(1);
+}
+
+/// The root directory of the current project, exits if there's none.
+fn current_project_root() -> PathBuf {
+    match history::project_root(&CargoArgs::default()) {
+        Some(root) => root,
+        None => {
+            eprintln!("cargo-single-line: unable to locate the project directory");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the `slow` subcommand.
+fn slow(args: &[OsString]) -> std::io::Result<()> {
+    if let Some(arg) = args.first() {
+        unexpected_argument(arg);
+    }
+    slow::print(&History::load(&current_project_root())?.builds);
+    Ok(())
+}
+
+/// Runs the `baseline` subcommand.
+fn baseline(args: &[OsString]) -> ! {
+    let args: Vec<&str> = args
+        .iter()
+        .map(|arg| arg.to_str().unwrap_or_else(|| unexpected_argument(arg)))
+        .collect();
+    match baseline::run(&current_project_root(), &args) {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("cargo-single-line: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the `stats` subcommand.
+fn stats(args: &[OsString]) -> ! {
+    let args: Vec<&str> = args
+        .iter()
+        .map(|arg| arg.to_str().unwrap_or_else(|| unexpected_argument(arg)))
+        .collect();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let result = History::load(&current_project_root())
+        .map_err(|e| e.to_string())
+        .and_then(|history| stats::run(&history.builds, &args, now));
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("cargo-single-line: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the `exec` subcommand.
+fn exec(options: &Options, filter: LineFilter, args: &[OsString]) -> ! {
+    // There are no cargo arguments to tell the verbosity, only the plugin's
+    // own, and there's no overwriting the status without a terminal.
+    let compaction = if atty::is(atty::Stream::Stderr) {
+        options.verbosity.compaction(Compaction::Full)
+    } else {
+        Compaction::None
+    };
+    match exec::run(options, &filter.with_compaction(compaction), args) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("cargo-single-line: unable to run the command: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the `init` subcommand.
+fn init(shell: init::Shell, args: &[OsString]) -> std::io::Result<()> {
+    let install = match args {
+        [] => false,
+        [arg] if arg == "--install" => true,
+        [arg, ..] => unexpected_argument(arg),
+    };
+    init::run(shell, install)
+}
+
+/// The configured color is as good as the user's choice, unless it's the
+/// default "auto".
+fn configured_color(options: &Options) -> Option<&str> {
+    options.color.as_deref().filter(|color| *color != "auto")
+}
+
+/// Whether the output is colored: as asked for, or if the terminal can do it.
+fn colored(
+    configured_color: Option<&str>,
+    cargo_args: &CargoArgs,
+    is_terminal: bool,
+    virtual_terminal: Option<bool>,
+) -> bool {
+    match cargo_args.color.as_deref().or(configured_color) {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            is_terminal
+                && terminal::ColorDepth::detect() != terminal::ColorDepth::None
+                && virtual_terminal != Some(false)
+        }
+    }
+}
+
+/// Saves the linker invocation to a file, returning what to show instead.
+fn collapse_linker_invocation(cargo_args: &CargoArgs, command: &str) -> String {
+    let length = format::count(command.chars().count());
+    match artifacts::save(cargo_args, "linker-cmd.txt", &format!("{}\n", command)) {
+        Ok(path) => l10n::text(
+            "linker-invocation-saved",
+            &[("length", &length), ("path", &path.display())],
+        ),
+        Err(e) => {
+            trace!("linker", "unable to save the invocation: {}", e);
+            l10n::text("linker-invocation", &[("length", &length)])
+        }
+    }
+}
+
+/// Runs cargo with the given arguments, shrinking its output.
+fn run_cargo(options: &Options, filter: LineFilter, args: &[OsString]) -> std::io::Result<()> {
+    let cargo_args = CargoArgs::parse(args);
+    let is_terminal = atty::is(atty::Stream::Stderr);
+    // A legacy console would print the escape sequences as is.
+    let virtual_terminal = if is_terminal {
+        terminal::enable_virtual_terminal()
+    } else {
+        None
+    };
+
+    let mut filter = filter
+        .with_compaction(
+            options
+                .verbosity
+                .compaction(Compaction::for_cargo_args(&cargo_args)),
+        )
+        .with_subcommand(cargo_args.subcommand.as_deref());
+    // Might be switched to no compaction from the keyboard.
+    let mut compaction = filter.compaction();
+    let collapsed = compaction;
+    let configured_color = configured_color(options);
+    let colored = colored(configured_color, &cargo_args, is_terminal, virtual_terminal);
+    trace!(
+        "terminal",
+        "stderr is a terminal: {}, colors: {} ({}), size: {:?}, multiplexer: {:?}, virtual terminal: {:?}",
+        is_terminal,
+        colored,
+        terminal::ColorDepth::detect().description(),
+        terminal::size(),
+        terminal::multiplexer(),
+        virtual_terminal
+    );
+    trace!("args", "{:?}, compaction: {:?}", cargo_args, compaction);
+
+    let invocation = recursion::Invocation::detect(args);
+    if invocation.depth >= recursion::MAX_DEPTH {
+        eprintln!(
+            "cargo-single-line: invoked recursively {} times, giving up",
+            invocation.depth
+        );
+        std::process::exit(1);
+    }
+
+    // `$CARGO` points to the cargo binary of a specific toolchain, while the
+    // toolchain selectors are handled by the rustup's proxy.
+    let mut program = if cargo_args.toolchain.is_some() {
+        OsString::from("cargo")
+    } else {
+        cargo_path()
+    };
+    if recursion::is_self(&program) {
+        match recursion::real_cargo() {
+            Some(cargo) => {
+                eprintln!(
+                    "cargo-single-line: warning: {:?} is cargo-single-line itself, running {:?} instead",
+                    program, cargo
+                );
+                program = cargo.into_os_string();
+            }
+            None => {
+                eprintln!(
+                    "cargo-single-line: {:?} is cargo-single-line itself, and no other cargo is found",
+                    program
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if invocation.is_loop {
+        // Running the same program again would only go around once more. Some
+        // other cargo is only tried by the first instance to find the loop:
+        // the loop going on past it, it's not about the cargo.
+        match recursion::other_cargo(&program).filter(|_| invocation.depth == 1) {
+            Some(cargo) => {
+                eprintln!(
+                    "cargo-single-line: warning: invoked by itself with the same arguments \
+                     (a looping alias?), running {:?} instead",
+                    cargo
+                );
+                program = cargo.into_os_string();
+            }
+            None => {
+                eprintln!(
+                    "cargo-single-line: invoked by itself with the same arguments, giving up: \
+                     is there a cargo alias running `cargo single-line` with the same name?"
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut cmd = Command::new(program);
+    child_env::apply(&mut cmd, options.clean_env, &options.env);
+    invocation.mark(&mut cmd, args);
+    // With `--quiet` there's nothing to compact, the output of subcommands
+    // like `cargo metadata` must stay byte-identical to the plain cargo's, and
+    // when nested two instances would fight over the same terminal.
+    let transparent = options.dry_filter.is_none()
+        && (cargo_args.quiet || cargo_args.produces_output() || invocation.is_nested());
+    if options.explain_rebuild && !transparent {
+        cmd.env("CARGO_LOG", rebuild::CARGO_LOG);
+    }
+    let tool = options.tool.or_else(|| tools::Tool::detect(&cargo_args));
+    // The tools and the pseudo console mix the messages with the rest of the
+    // output.
+    let json = options.json && cargo_args.supports_json() && tool.is_none() && !options.pty;
+    if options.json && !json && !transparent {
+        eprintln!("cargo-single-line: warning: --json is not supported for this command, ignored");
+    }
+    let mut injected = Vec::new();
+    // Without compaction there's no status line to make colorful, and with
+    // JSON messages the colors would end up in the "rendered" diagnostics,
+    // which are meant for the tools. Cargo doesn't accept `--color` twice, so
+    // the user's choice wins. A legacy console can't do the colors at all.
+    let forced_color = if virtual_terminal == Some(false) {
+        "never"
+    } else {
+        "always"
+    };
+    let color = configured_color.or(is_terminal.then_some(forced_color));
+    let color_flag = color.map(|color| format!("--color={}", color));
+    if let (Some(color), Some(color_flag)) = (color, &color_flag) {
+        if !transparent
+            && cargo_args.message_format != MessageFormat::Json
+            && cargo_args.color.is_none()
+        {
+            if tool.is_some() || !cargo_args.accepts_color() {
+                // The tools and the third-party subcommands parse the
+                // arguments on their own, but the cargo they run respects the
+                // environment.
+                cmd.env("CARGO_TERM_COLOR", color);
+            } else {
+                injected.push(color_flag.as_str());
+            }
+        }
+    }
+    if options.force_english && !transparent {
+        // Whatever translates the output goes by the locale, and might drop
+        // the colors along with it unless they are asked for explicitly.
+        cmd.env("LC_ALL", "C").env("LANGUAGE", "en");
+        if let Some(color) = color {
+            cmd.env("CARGO_TERM_COLOR", color);
+        }
+    }
+    // The test harness prints to stdout, which is otherwise left alone. The
+    // JSON messages chosen by the user must reach them intact, and so must the
+    // results whoever reads a piped stdout.
+    let mut tests = (options.test_progress
+        && atty::is(atty::Stream::Stdout)
+        && cargo_args.runs_tests()
+        && cargo_args.message_format == MessageFormat::Human
+        && tool.is_none_or(|tool| tool == tools::Tool::Miri)
+        && !options.pty
+        && !transparent
+        && compaction != Compaction::None)
+        .then(TestProgress::new);
+    if json {
+        injected.push(messages::message_format(colored));
+    }
+    // The lines that go to stdout unchanged are still printed by the main
+    // loop only, so they don't interleave with the status line and keep their
+    // order. The captured ones would be missed by whoever reads a piped stdout.
+    let filter_stdout =
+        options.filter_stdout && !transparent && !options.pty && compaction != Compaction::None;
+    let capture_stdout = filter_stdout && atty::is(atty::Stream::Stdout);
+    if json || tests.is_some() || filter_stdout {
+        cmd.stdout(Stdio::piped());
+    }
+    cmd.args(cargo_args.inject(args, &injected));
+
+    if options.dry_run {
+        dry_run::print(&cmd);
+        return Ok(());
+    }
+    if transparent {
+        trace!("child", "running transparently, nested: {:?}", invocation);
+        // Don't stand in the way.
+        return run_transparent(cmd);
+    }
+
+    // Only bother humans with the notification.
+    let new_version =
+        (options.update_check && is_terminal).then(|| update::check_in_background(cargo_path()));
+    // The members only matter for the status line, and the highlight for
+    // the colored one.
+    let mut members = (tool.is_none()
+        && compaction != Compaction::None
+        && ((options.workspace_highlight && colored) || options.echo_workspace_members))
+        .then(|| cargo_args.manifest_dir())
+        .flatten()
+        .map(|dir| {
+            workspace::Members::look_up(
+                cmd.get_program().to_owned(),
+                cargo_args.manifest_path.clone(),
+                &dir,
+            )
+        });
+
+    let mut progress = Progress::new(if options.progress {
+        progress::estimate_total(&cargo_args)
+    } else {
+        None
+    });
+    let hints = match (options.history, &cargo_args.subcommand) {
+        (true, Some(command)) => history::project_root(&cargo_args)
+            .and_then(|root| History::load(&root).ok())
+            .map(|history| hints::Hints::from_builds(&history.builds, command))
+            .unwrap_or_default(),
+        _ => hints::Hints::default(),
+    };
+    let started = Instant::now();
+    let mut timings = Timings::new(started);
+    let mut duplicates = Duplicates::new();
+    let mut diagnostics = Diagnostics::new();
+    // With the diagnostics summed up in the end, there's nothing to fold.
+    let mut folding = (options.compact_diagnostics
+        && !(options.summary && options.verbosity.holds_back()))
+    .then(|| Folding::new(colored));
+    let shown_level = |level| options.verbosity.prints(Class::of("", Some(level)));
+    let mut first_error = None;
+    // Cargo's `Finished` line, which tells the profile.
+    let mut cargo_finished = None;
+    let mut downloads = match init::cargo_home() {
+        Some(home) => downloads::Downloads::with_registry(
+            cargo_args
+                .manifest_dir()
+                .and_then(|dir| lockfile::find(&dir)),
+            &home,
+        ),
+        None => downloads::Downloads::new(),
+    };
+    let mut lock_changes = lock_changes::LockChanges::new();
+    let mut ice = ice::IceDetector::new();
+    let mut linker = linker::LinkerErrors::new();
+    let mut fresh = verbose::FreshLines::new();
+    let crate_filter = CrateFilter::new(&options.only, &options.hide);
+    // The latest line of the crates shown, to keep on the status line while
+    // the others are compiled.
+    let mut shown: Option<String> = None;
+    let mut invocations = options.hide_invocations.then(verbose::Invocations::new);
+    let prefixes = match &options.locale_prefixes {
+        Some(path) => match localized::Prefixes::load(path) {
+            Ok(prefixes) => Some(prefixes),
+            Err(e) => {
+                eprintln!(
+                    "cargo-single-line: unable to read the prefixes {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut log_file = match &options.log_file {
+        Some(path) => match LogFile::create(path, options.log_colors) {
+            Ok(log_file) => Some(log_file),
+            Err(e) => {
+                eprintln!(
+                    "cargo-single-line: unable to create the log file {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut recorder = match &options.record {
+        Some(path) => match Recorder::create(path, started) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                eprintln!(
+                    "cargo-single-line: unable to create the transcript {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let transcript = match &options.dry_filter {
+        Some(path) => match Transcript::load(path) {
+            Ok(transcript) => Some(transcript),
+            Err(e) => {
+                eprintln!(
+                    "cargo-single-line: unable to read the transcript {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut events = match &options.emit_events {
+        Some(target) => match EventSink::open(target) {
+            Ok(events) => Some(events),
+            Err(e) => {
+                eprintln!(
+                    "cargo-single-line: unable to open {} for the events: {}",
+                    target, e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    // The channel is unbounded, so that cargo never waits for the terminal.
+    let (sender, receiver) = mpsc::channel();
+    // Whatever might fail is done above: the keys leave the terminal without
+    // echo until they're dropped, which an early exit doesn't do.
+    // `cargo run` passes the stdin over to the program.
+    let keys = if options.keys && is_terminal && cargo_args.subcommand.as_deref() != Some("run") {
+        let sender = sender.clone();
+        keys::Keys::start(move |key| sender.send(Event::Key(key)).is_ok())
+    } else {
+        None
+    };
+    // The whole output is only kept around for the report and the pager.
+    let mut log = (options.report_html.is_some() || keys.is_some()).then(Vec::new);
+    let mut rebuilds = options.explain_rebuild.then(rebuild::RebuildExplainer::new);
+    let mut localized = (compaction != Compaction::None).then(localized::Detector::new);
+
+    if let Some(keys) = &keys {
+        keys.isolate(&mut cmd);
+    }
+    if options.pty && !pty::SUPPORTED {
+        eprintln!("cargo-single-line: warning: --pty is only supported on Windows, ignored");
+    }
+    panic::install();
+    signals::install();
+    let (mut child, child_output) = match transcript {
+        Some(transcript) => pty::Child::replay(transcript, options.realtime),
+        None => pty::Child::spawn(&mut cmd, options.pty)?,
+    };
+    panic::watch_child(child.id());
+    trace!(
+        "child",
+        "spawned {:?} with {:?} (pid {}, console: {}, keys: {})",
+        cmd.get_program(),
+        cmd.get_args().collect::<Vec<_>>(),
+        child.id(),
+        child.is_console(),
+        keys.is_some()
+    );
+    signals::forward_to(child.id(), keys.is_some());
+    {
+        let sender = sender.clone();
+        signals::watch(move |signal| sender.send(Event::Signal(signal)).is_ok());
+    }
+
+    {
+        let sender = sender.clone();
+        let console = child.is_console();
+        std::thread::spawn(move || {
+            let result = read_lines(child_output, console, &sender, |segment| match segment {
+                Segment::Line(line) => Event::Line(line),
+                Segment::Update(update) => Event::Update(update.text),
+            });
+            let _ = sender.send(Event::Eof(result));
+        });
+    }
+    // The loop is over once all the outputs are closed.
+    let mut open_outputs = 1;
+    if let Some(stdout) = child.take_stdout() {
+        open_outputs += 1;
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            // The program's own updates are printed as they come, unless they
+            // are captured.
+            let event: fn(Segment) -> Event = if capture_stdout {
+                |segment| match segment {
+                    Segment::Line(line) => Event::Message(line),
+                    Segment::Update(update) => Event::Update(update.text),
+                }
+            } else {
+                |segment| match segment {
+                    Segment::Line(line) => Event::Message(line),
+                    Segment::Update(mut update) => {
+                        update.push_str("\r");
+                        Event::Message(update)
+                    }
+                }
+            };
+            let result = read_lines(stdout, false, &sender, event);
+            let _ = sender.send(Event::Eof(result));
+        });
+    }
+    // Without a terminal, there's no overwriting the status line in place.
+    let interactive = is_terminal && !options.ci;
+    let spinner = options.spinner && interactive;
+    let mut title = (options.set_title && interactive)
+        .then(title::Title::start)
+        .flatten();
+    let mut heartbeat = (!interactive)
+        .then(|| Heartbeat::new(options.heartbeat, options.heartbeat_crates, started));
+    if options.stall_timeout.is_some() || spinner || heartbeat.is_some() {
+        let tick = if spinner { spinner::INTERVAL } else { TICK };
+        std::thread::spawn(move || {
+            // Stops as soon as the main loop is over.
+            while sender.send(Event::Tick).is_ok() {
+                std::thread::sleep(tick);
+            }
+        });
+    }
+
+    // The window is redrawn with the cursor movements, which a console
+    // without the virtual terminal processing would print as is.
+    let mut status = if !interactive {
+        StatusLine::detached()
+    } else if virtual_terminal != Some(false) && options.tag.is_none() {
+        StatusLine::with_window(options.window)
+    } else {
+        // The other instances' lines would get in the way of the window.
+        StatusLine::new()
+    }
+    .with_screen(if virtual_terminal == Some(false) {
+        Screen::Plain
+    } else {
+        Screen::Ansi
+    })
+    .with_spinner(spinner)
+    .with_tag(options.tag.as_deref().map(|name| Tag::new(name, colored)));
+    let mut stall = StallDetector::new(options.stall_timeout);
+    let mut lock_wait = LockWait::new(artifacts::target_dir(&cargo_args));
+    let mut replay = Replay::new(options.replay);
+    let mut cancelled = false;
+    let mut stalled = false;
+    let mut handoff = (cargo_args.subcommand.as_deref() == Some("run"))
+        .then(|| handoff::Handoff::new(options.run_status, colored));
+    let mut result = Ok(());
+    let mut status_file = options.status_file.clone().map(StatusFile::new);
+    loop {
+        // The output is processed as fast as it comes, while the status line
+        // catches up at its own pace.
+        let event = match receiver.try_recv() {
+            Ok(event) => event,
+            // Caught up with cargo, time to show what's been processed.
+            Err(_) => {
+                status.write_out();
+                if let Some(file) = &mut status_file {
+                    if let Err(e) = file.update(status.current()) {
+                        status.print(&format!(
+                            "cargo-single-line: unable to write the status file {}: {}\n",
+                            file.path().display(),
+                            e
+                        ));
+                        status_file = None;
+                    }
+                }
+                match status.redraw_in() {
+                    Some(wait) => match receiver.recv_timeout(wait) {
+                        Ok(event) => event,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            status.flush();
+                            continue;
+                        }
+                        Err(mpsc::RecvTimeoutError::Disconnected) => {
+                            panic!("stderr handling thread always reports EOF")
+                        }
+                    },
+                    None => receiver
+                        .recv()
+                        .expect("stderr handling thread always reports EOF"),
+                }
+            }
+        };
+        let recorded = recorder.as_mut().map(|recorder| match &event {
+            Event::Line(line) => recorder.stderr(&line.text),
+            Event::Update(update) => recorder.stderr(&format!("{}\r", update)),
+            Event::Message(line) => recorder.stdout(&line.text),
+            _ => Ok(()),
+        });
+        if let Some(Err(e)) = recorded {
+            status.print(&format!(
+                "cargo-single-line: unable to record the transcript: {}\n",
+                e
+            ));
+            recorder = None;
+        }
+        match event {
+            Event::Line(mut line) => {
+                if let Some(log_file) = &mut log_file {
+                    log_file.write(&line.text);
+                }
+                if let Some(prefixes) = &prefixes {
+                    line.edit(|text| prefixes.translate(text));
+                }
+                let Text { text: line, raw } = line;
+                if localized
+                    .as_mut()
+                    .is_some_and(|localized| localized.observe(&line))
+                {
+                    trace!("line", "localized {:?}", line);
+                    status.print(&format!("note: {}\n", l10n::text("localized-output", &[])));
+                }
+                if let Some(rebuilds) = &mut rebuilds {
+                    if rebuilds.observe(&line) {
+                        trace!("line", "fingerprint log {:?}", line);
+                        continue;
+                    }
+                }
+                let now = Instant::now();
+                stall.observe(&line, now);
+                let acquired = lock_wait.observe(&line, now);
+                if let (Some(events), Some(acquired)) = (&mut events, &acquired) {
+                    events.emit(&BuildEvent::LockAcquired {
+                        lock: &acquired.lock,
+                        waited: acquired.waited.as_secs_f64(),
+                    });
+                }
+                timings.observe(&line, now);
+                progress.observe(&line);
+                if let (Some(title), Some(unit)) = (&mut title, Unit::parse(&line)) {
+                    title.set(&title::text(&unit, progress.done(), progress.total()));
+                }
+                if let (Some(events), Some(unit)) = (&mut events, Unit::parse(&line)) {
+                    events.emit(&BuildEvent::Compiling {
+                        name: &unit.name,
+                        version: &unit.version,
+                        verb: &unit.verb,
+                    });
+                }
+                duplicates.observe(&line);
+                let starts = diagnostics.observe(&line);
+                if starts == Some(Level::Error) && first_error.is_none() {
+                    first_error = Some(now - started);
+                }
+                let burst = downloads.observe(&line, now);
+                let fresh_burst = fresh.observe(&line);
+                lock_changes.observe(&line);
+                if let Some(log) = &mut log {
+                    log.push(strip_colors(line.trim_end()).into_owned());
+                }
+                if ice.observe(&line) {
+                    // The compiler has crashed, every word of it matters.
+                    trace!("line", "crash report {:?}", line);
+                    status.print(&line);
+                    continue;
+                }
+                if let Some(folding) = folding.as_mut().filter(|_| compaction != Compaction::None) {
+                    let folded = folding.observe(&line, starts, diagnostics.continues());
+                    if let Some((_, one_liner)) =
+                        folding.finished().filter(|(level, _)| shown_level(*level))
+                    {
+                        status.print(&one_liner);
+                    }
+                    if folded {
+                        trace!("line", "folded {:?}", line);
+                        continue;
+                    }
+                }
+                let invocation = linker.observe(&line);
+                if let Some(invocation) = invocation.filter(|_| compaction != Compaction::None) {
+                    trace!("line", "linker invocation {:?}", line);
+                    status.print(&format!(
+                        "{}{}\n",
+                        invocation.prefix,
+                        collapse_linker_invocation(&cargo_args, &invocation.command)
+                    ));
+                    continue;
+                }
+                if let Some(invocations) = invocations
+                    .as_mut()
+                    .filter(|_| compaction != Compaction::None)
+                {
+                    if invocations.hold(&line) {
+                        trace!("line", "rustc invocation {:?}", line);
+                        continue;
+                    }
+                    if let Some(invocation) =
+                        diagnostics::failed_crate(&line).and_then(|name| invocations.take(&name))
+                    {
+                        status.print(&invocation);
+                    }
+                }
+                match fresh_burst.filter(|_| compaction != Compaction::None) {
+                    Some(downloads::Burst::Progress(text)) => {
+                        trace!("line", "fresh {:?}", line);
+                        status.update(&progress.decorate(&text));
+                        continue;
+                    }
+                    Some(downloads::Burst::Over { summary, .. }) => {
+                        status.finish_with(&summary);
+                    }
+                    None => {}
+                }
+                // With `-vv` nothing is compacted, the downloads included.
+                let handled = match burst.filter(|_| compaction != Compaction::None) {
+                    Some(downloads::Burst::Progress(text)) => {
+                        status.update(&progress.decorate(&text));
+                        true
+                    }
+                    Some(downloads::Burst::Over { summary, consumed }) => {
+                        status.finish_with(&summary);
+                        consumed
+                    }
+                    None => false,
+                };
+                if handled {
+                    trace!("line", "download {:?}", line);
+                } else if let Some(display) =
+                    handoff.as_mut().and_then(|handoff| handoff.display(&line))
+                {
+                    trace!("line", "handoff {:?}: {:?}", line, display);
+                    match display {
+                        handoff::Display::Status(line) => status.update(&line),
+                        handoff::Display::Final(line) => status.finish_with(&line),
+                        handoff::Display::Clear => status.clear(),
+                    }
+                } else if let Some(text) = tests.as_mut().and_then(|tests| {
+                    tests
+                        .observe_cargo(&line)
+                        .then(|| tests.status().unwrap_or_else(|| line.clone()))
+                }) {
+                    trace!("line", "test binary {:?}", line);
+                    status.update(&text);
+                } else if let Some(text) = tool
+                    .filter(|_| compaction != Compaction::None)
+                    .and_then(|tool| tool.status(&line))
+                {
+                    trace!("line", "{:?} progress {:?}", tool, line);
+                    replay.record(&line);
+                    status.update(&progress.decorate(&text));
+                } else if filter.is_status(&line) {
+                    trace!("line", "status {:?}", line);
+                    replay.record(&line);
+                    if let Some(text) = lock_wait.status(now) {
+                        status.push(&progress.decorate(&blocking_status(&text, colored)));
+                        continue;
+                    }
+                    let unit = Unit::parse(&line);
+                    if let Some(filter) = &crate_filter {
+                        match &unit {
+                            Some(unit) if !filter.shows(&unit.name) => {
+                                trace!("line", "filtered out {:?}", line);
+                                // Only the progress indicator moves on.
+                                if let Some(shown) = &shown {
+                                    status.update(&progress.decorate(shown));
+                                }
+                                continue;
+                            }
+                            _ => shown = None,
+                        }
+                    }
+                    let member = unit
+                        .as_ref()
+                        .zip(members.as_mut())
+                        .is_some_and(|(unit, members)| members.contains(&unit.name));
+                    if member && options.echo_workspace_members {
+                        trace!("line", "workspace member {:?}", line);
+                        status.print(&line);
+                        continue;
+                    }
+                    let line = match (&options.format, &unit) {
+                        (Some(format), Some(unit)) => {
+                            format.render(unit, now - started, options.icons, colored)
+                        }
+                        (None, Some(unit)) if member && colored => {
+                            workspace::highlight(&line, &unit.name)
+                        }
+                        _ => line,
+                    };
+                    let line = diagnostics
+                        .decorate(&hints.annotate(&line), colored)
+                        .into_owned();
+                    status.push(&progress.decorate(&line));
+                    if crate_filter.is_some() && unit.is_some() {
+                        shown = Some(line);
+                    }
+                } else if options.summary
+                    && options.verbosity.holds_back()
+                    && diagnostics.is_within()
+                {
+                    trace!("line", "held back {:?}", line);
+                } else {
+                    let finished = starts_with(&line, &["Finished"]);
+                    if finished {
+                        cargo_finished = Some(line.clone());
+                    }
+                    if finished && compaction != Compaction::None {
+                        trace!("line", "finished {:?}", line);
+                        status.print(&finished_line(
+                            &line,
+                            now - started,
+                            progress.done(),
+                            colored,
+                        ));
+                    } else if !options
+                        .verbosity
+                        .prints(Class::of(&line, diagnostics.within()))
+                    {
+                        trace!("line", "hidden {:?}", line);
+                    } else {
+                        trace!("line", "print {:?}", line);
+                        status.print_bytes(raw.as_deref().unwrap_or(line.as_bytes()));
+                    }
+                    if finished {
+                        // The build is over, the status must not get in the
+                        // way of the test output and the like.
+                        status.clear();
+                    }
+                }
+            }
+            Event::Update(update) => {
+                if strip_colors(&update).trim().is_empty() {
+                    // The previous update is being erased, the status line
+                    // takes care of that.
+                    trace!("line", "erase {:?}", update);
+                } else {
+                    // Whatever the compaction, there's no better place for a
+                    // line meant to be overwritten.
+                    trace!("line", "update {:?}", update);
+                    stall.observe(&update, Instant::now());
+                    status.update(&update);
+                }
+            }
+            Event::Message(Text { text: line, raw }) => match messages::parse(&line)
+                .filter(|_| json)
+            {
+                Some(messages::Message::Compiler {
+                    diagnostic,
+                    rendered,
+                }) => {
+                    trace!("line", "compiler message {:?}", line);
+                    if let Some(log_file) = &mut log_file {
+                        log_file.write(&rendered);
+                    }
+                    if let Some(log) = &mut log {
+                        log.extend(rendered.lines().map(|line| strip_colors(line).into_owned()));
+                    }
+                    let level = diagnostic.as_ref().map(|diagnostic| diagnostic.level);
+                    let held_back =
+                        (options.summary && options.verbosity.holds_back() && level.is_some())
+                            || !options.verbosity.prints(Class::of(&rendered, level));
+                    if let Some(diagnostic) = diagnostic {
+                        if diagnostics.record(diagnostic) == Level::Error && first_error.is_none() {
+                            first_error = Some(started.elapsed());
+                        }
+                    }
+                    match (&mut folding, level) {
+                        _ if held_back => {}
+                        (Some(folding), Some(level)) if compaction != Compaction::None => {
+                            status.print(&folding.fold(&rendered, level));
+                        }
+                        _ => status.print(&rendered),
+                    }
+                }
+                Some(messages::Message::Other) => trace!("line", "message {:?}", line),
+                None => {
+                    if let Some(log_file) = log_file
+                        .as_mut()
+                        .filter(|_| tests.is_some() || filter_stdout)
+                    {
+                        log_file.write(&line);
+                    }
+                    match tests
+                        .as_mut()
+                        .and_then(|tests| tests.observe(&line).then(|| tests.status()))
+                    {
+                        Some(text) => {
+                            trace!("line", "test {:?}", line);
+                            if let Some(text) = text {
+                                status.update(&text);
+                            }
+                        }
+                        None if capture_stdout && filter.is_status(&line) => {
+                            trace!("line", "stdout status {:?}", line);
+                            replay.record(&line);
+                            status.push(&progress.decorate(&line));
+                        }
+                        None => {
+                            trace!("line", "stdout {:?}", line);
+                            status.print_stdout(raw.as_deref().unwrap_or(line.as_bytes()));
+                        }
+                    }
+                }
+            },
+            Event::Tick => {
+                status.tick();
+                if let Some(text) = lock_wait
+                    .status(Instant::now())
+                    .filter(|_| compaction != Compaction::None)
+                {
+                    status.update(&progress.decorate(&blocking_status(&text, colored)));
+                }
+                if stall.check(Instant::now()) {
+                    let line = format!("{} ({})", status.current(), l10n::text("stall-hint", &[]));
+                    status.update(&line);
+                    if options.exit_on_stall {
+                        status.print(&format!(
+                            "cargo-single-line: {}\n",
+                            l10n::text("stalled", &[])
+                        ));
+                        trace!("child", "killed after a stall");
+                        let _ = child.kill();
+                        stalled = true;
+                        // The compilers cargo has started might still hold the
+                        // output open, there's no waiting for them.
+                        break;
+                    }
+                }
+            }
+            Event::Key('o') => {
+                if let (Some(keys), Some(log)) = (&keys, &log) {
+                    let current = status.current().to_owned();
+                    status.update("");
+                    status.flush();
+                    keys.pause();
+                    if let Err(e) = pager::show(&log.join("\n")) {
+                        status.print(&format!(
+                            "cargo-single-line: unable to run the pager: {}\n",
+                            e
+                        ));
+                    }
+                    keys.resume();
+                    status.update(&current);
+                }
+            }
+            Event::Key('v') if keys.is_some() && collapsed != Compaction::None => {
+                let (next, note) = if compaction == Compaction::None {
+                    (collapsed, "keys-collapsing")
+                } else {
+                    (Compaction::None, "keys-passing-through")
+                };
+                trace!("line", "compaction switched to {:?}", next);
+                compaction = next;
+                filter = filter.with_compaction(compaction);
+                status.print(&format!("note: {}\n", l10n::text(note, &[])));
+            }
+            Event::Key('d') if keys.is_some() => {
+                if let Some(full) = folding
+                    .as_mut()
+                    .and_then(|folding| folding.replay(shown_level))
+                {
+                    status.print(&format!(
+                        "note: {}\n{}",
+                        l10n::text("folded-diagnostics", &[]),
+                        full
+                    ));
+                }
+            }
+            Event::Key('p') if keys.is_some() => {
+                let paused = !status.is_paused();
+                if paused {
+                    status.print(&format!("note: {}\n", l10n::text("keys-paused", &[])));
+                }
+                status.set_paused(paused);
+            }
+            Event::Key('c' | 'q') if !cancelled => {
+                if let Some(keys) = &keys {
+                    trace!("child", "interrupted from the keyboard");
+                    keys.interrupt(child.id());
+                    cancelled = true;
+                    show_cancelling(&mut status);
+                }
+            }
+            Event::Key(_) => {}
+            Event::Signal(Signal::Interrupt) => {
+                trace!("child", "interrupted by a signal");
+                show_cancelling(&mut status);
+            }
+            Event::Signal(Signal::Resize) => status.resized(),
+            Event::Eof(output_result) => {
+                open_outputs -= 1;
+                result = result.and(output_result);
+                if open_outputs == 0 {
+                    break;
+                }
+            }
+        }
+        if let Some(events) = &mut events {
+            events.counts(
+                diagnostics.count(Level::Error),
+                diagnostics.count(Level::Warning),
+            );
+        }
+        if let Some(heartbeat) = &mut heartbeat {
+            if !status.current().is_empty() && heartbeat.is_due(Instant::now(), progress.done()) {
+                let line = l10n::text(
+                    "heartbeat",
+                    &[
+                        ("status", &status.current().trim_start()),
+                        ("duration", &format::duration(started.elapsed())),
+                    ],
+                );
+                trace!("redraw", "heartbeat {:?}", line);
+                status.print(&format!("{}\n", line));
+            }
+        }
+    }
+    drop(keys);
+    // The build is over, and so is its status.
+    drop(title);
+    if let Some(line) = tests.and_then(|tests| tests.status()) {
+        status.finish_with(&line);
+    }
+    let exit_status = child.wait()?;
+    panic::forget_child();
+    trace!("child", "exited: {}, output: {:?}", exit_status, result);
+    if let Some(Err(e)) = recorder.map(|recorder| recorder.finish(exit_status.code())) {
+        eprintln!("cargo-single-line: unable to record the transcript: {}", e);
+    }
+    let finished = Instant::now();
+    if let Some(events) = &mut events {
+        events.emit(&BuildEvent::BuildFinished {
+            success: exit_status.success(),
+            duration: (finished - started).as_secs_f64(),
+            crates: progress.done(),
+            first_error: first_error.map(|first_error| first_error.as_secs_f64()),
+        });
+    }
+    if let Some(mut folding) = folding {
+        if let Some((_, one_liner)) = folding.end().filter(|(level, _)| shown_level(*level)) {
+            status.print(&one_liner);
+        }
+        if let Some(full) = folding.replay(shown_level) {
+            status.print(&format!(
+                "note: {}\n{}",
+                l10n::text("folded-diagnostics", &[]),
+                full
+            ));
+        }
+    }
+    let diagnostics = diagnostics.finish();
+    if let Some(summary) = summary::render(&diagnostics).filter(|_| options.summary) {
+        status.print(&summary);
+    }
+    // A program started by `cargo run` might handle Ctrl-C on its own.
+    let cancelled = cancelled || (signals::interrupted() && !exit_status.success());
+    // A cancelled (or stalled) build is a failed one to the hooks, the
+    // history and the reports, whatever cargo has exited with.
+    let success = exit_status.success() && !cancelled && !stalled;
+    let code = if cancelled {
+        CANCEL_EXIT_CODE
+    } else if stalled {
+        STALL_EXIT_CODE
+    } else {
+        exit_code(exit_status)
+    };
+    let failed_crates = diagnostics::failed_crates(&diagnostics);
+    let failure = |colored| failure_line(&failed_crates, finished - started, first_error, colored);
+    if cancelled {
+        let cancelled = |colored| {
+            format!(
+                "{} {}",
+                styled_verb("Cancelled", "33", colored),
+                l10n::text(
+                    "cancelled-after",
+                    &[("duration", &format::duration(finished - started))]
+                )
+            )
+        };
+        status.finish_with(&cancelled(colored));
+        finish_status_file(status_file, &cancelled(false));
+    } else if success {
+        status.finish();
+        let outcome = finished_line(
+            cargo_finished.as_deref().unwrap_or_default(),
+            finished - started,
+            progress.done(),
+            false,
+        );
+        finish_status_file(status_file, outcome.trim_end());
+    } else {
+        if !replay.is_empty() {
+            status.print(&format!("note: {}\n", l10n::text("replay", &[])));
+            for line in replay.lines() {
+                status.print(&line);
+            }
+        }
+        status.finish_with(&failure(colored));
+        finish_status_file(status_file, &failure(false));
+    }
+    if options.notify && notify::available() {
+        let subcommand = cargo_args.subcommand.as_deref().unwrap_or_default();
+        let duration = format::duration(finished - started);
+        let (title, body) = if success {
+            ("notify-succeeded", "finished-in")
+        } else {
+            ("notify-failed", "failed-after")
+        };
+        let title = l10n::text(title, &[("subcommand", &subcommand)]);
+        let body = l10n::text(body, &[("duration", &duration)]);
+        if let Err(e) = notify::send(&title, &body, !success) {
+            trace!("child", "unable to send the notification: {}", e);
+        }
+    }
+    let build = hooks::Build {
+        subcommand: cargo_args.subcommand.as_deref().unwrap_or_default(),
+        exit_code: code,
+        duration: finished - started,
+        errors: count_level(&diagnostics, Level::Error),
+        warnings: count_level(&diagnostics, Level::Warning),
+        failed_crates: &failed_crates,
+    };
+    for (option, command) in hooks::due(
+        &build,
+        options.on_success.as_deref(),
+        options.on_failure.as_deref(),
+        options.on_warning.as_deref(),
+    ) {
+        match hooks::run(command, &build) {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!(
+                "cargo-single-line: the {} command exited with {}",
+                option, status
+            ),
+            Err(e) => eprintln!(
+                "cargo-single-line: unable to run the {} command: {}",
+                option, e
+            ),
+        }
+    }
+    if let (Some(log_file), Some(path)) = (log_file, &options.log_file) {
+        match log_file.finish() {
+            Ok(()) if !success => eprintln!(
+                "note: {}",
+                l10n::text("log-file-saved", &[("path", &path.display())])
+            ),
+            Ok(()) => {}
+            Err(e) => eprintln!(
+                "cargo-single-line: unable to write the log file {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+    // The notes are no errors, `-q` leaves them out.
+    let notes = options.verbosity > Verbosity::Quiet;
+    if let Some(report) = duplicates.report().filter(|_| notes) {
+        eprintln!("note: {}", report);
+    }
+    if let Some(report) = lock_changes.report().filter(|_| notes) {
+        eprintln!("note: {}", report);
+    }
+    if let Some(report) = ice.report() {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let name = format!("ice-{}.txt", timestamp);
+        match artifacts::save(&cargo_args, &name, &report) {
+            Ok(path) => eprintln!(
+                "note: {}",
+                l10n::text("ice-saved", &[("path", &path.display())])
+            ),
+            Err(e) => eprintln!(
+                "cargo-single-line: unable to save the compiler crash report: {}",
+                e
+            ),
+        }
+    }
+    match rebuilds.as_ref().map(rebuild::RebuildExplainer::summary) {
+        Some(Some(summary)) => eprintln!("note: {}\n{}", l10n::text("rebuilt", &[]), summary),
+        Some(None) => eprintln!("note: {}", l10n::text("nothing-rebuilt", &[])),
+        None => {}
+    }
+
+    let timings = timings.finish(finished);
+    if options.slow_crates_summary {
+        if let Some(summary) = hints.slow_summary(&timings, finished - started) {
+            eprintln!("note: {}", summary);
+        }
+    }
+    if options.throughput {
+        if let Some(summary) = sparkline::summary(&timings, finished - started) {
+            eprintln!("note: {}", summary);
+        }
+    }
+    if options.timings_lite {
+        if let Some(table) = timings::table(&timings, finished - started) {
+            eprint!("note: {}\n{}", l10n::text("slowest-crates", &[]), table);
+        }
+    }
+    if let Some(path) = &options.timeline_svg {
+        if let Err(e) = std::fs::write(path, svg::timeline(&timings, finished - started)) {
+            eprintln!(
+                "cargo-single-line: unable to write the timeline to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    let summary_md = options
+        .summary_md
+        .clone()
+        .or_else(|| std::env::var_os(GITHUB_STEP_SUMMARY).map(PathBuf::from));
+    if options.report_html.is_some() || summary_md.is_some() {
+        let command = std::iter::once(Cow::Borrowed("cargo"))
+            .chain(args.iter().map(|arg| dry_run::quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let command = redact::redact(&command).into_owned();
+        let report = report::Report {
+            command,
+            success,
+            duration: finished - started,
+            first_error,
+            timings: &timings,
+            diagnostics: &diagnostics,
+            downloads: &downloads,
+            log: log.as_deref().unwrap_or_default(),
+        };
+        if let Some(path) = &options.report_html {
+            if let Err(e) = std::fs::write(path, report.html()) {
+                eprintln!(
+                    "cargo-single-line: unable to write the report to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        if let Some(path) = &summary_md {
+            if let Err(e) = append(path, &report.markdown()) {
+                eprintln!(
+                    "cargo-single-line: unable to write the summary to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+    if options.history {
+        let mut build = history::Build::new(
+            cargo_args.subcommand.clone().unwrap_or_default(),
+            finished - started,
+            success,
+            &timings,
+        );
+        build.first_error = first_error.map(|first_error| first_error.as_secs_f64());
+        build.compiled = Some(progress.done());
+        build.warnings = Some(count_level(&diagnostics, Level::Warning));
+        match record_build(&cargo_args, &build) {
+            Ok(Some(previous)) if notes => {
+                eprintln!("note: {}", stats::compare(&build, &previous));
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!(
+                "cargo-single-line: unable to update the builds history: {}",
+                e
+            ),
+        }
+    }
+    let regressed = success
+        && options.dry_filter.is_none()
+        && check_regression(options, &cargo_args, finished - started);
+    if let Some(latest) = new_version
+        .filter(|_| notes)
+        .and_then(|receiver| receiver.try_recv().ok())
+    {
+        let notice = update::notice(&latest);
+        if colored {
+            eprintln!("\x1b[2m{}\x1b[0m", notice);
+        } else {
+            eprintln!("{}", notice);
+        }
+    }
+    if let Err(e) = result {
+        eprintln!("Unable to capture cargo's stderr: {:#}\n", e);
+        std::process::exit(1);
+    }
+    if !success {
+        std::process::exit(code);
+    }
+    if regressed && options.fail_on_regression {
+        std::process::exit(REGRESSION_EXIT_CODE);
+    }
+    Ok(())
+}
+
+/// The code to exit with after cargo has exited with the `status`. When cargo
+/// is killed by a signal, it's `128 + signal` like in the shells.
+pub(crate) fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Right-aligns the verb like cargo does and, if `colored`, makes it bold in
+/// the given color.
+fn styled_verb(verb: &str, color: &str, colored: bool) -> String {
+    if colored {
+        format!("\x1b[1;{}m{:>12}\x1b[0m", color, verb)
+    } else {
+        format!("{:>12}", verb)
+    }
+}
+
+/// Tells on the status line that the build is being cancelled.
+fn show_cancelling(status: &mut StatusLine) {
+    let cancelling = l10n::text("cancelling", &[]);
+    let line = match status.current() {
+        "" => cancelling,
+        current => format!("{} ({})", current, cancelling),
+    };
+    status.update(&line);
+}
+
+/// Leaves the outcome of the build in the status file.
+fn finish_status_file(status_file: Option<StatusFile>, outcome: &str) {
+    if let Some(mut file) = status_file {
+        if let Err(e) = file.update(outcome) {
+            eprintln!(
+                "cargo-single-line: unable to write the status file {}: {}",
+                file.path().display(),
+                e
+            );
+        }
+    }
+}
+
+/// How many of the diagnostics are of the `level`.
+fn count_level(diagnostics: &[diagnostics::Diagnostic], level: Level) -> usize {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level == level)
+        .count()
+}
+
+/// The status line while cargo waits for another cargo to release a lock.
+fn blocking_status(text: &str, colored: bool) -> String {
+    format!("{} {}", styled_verb("Blocking", "36", colored), text)
+}
+
+/// The line replacing cargo's own `Finished` one, with the whole build
+/// duration (the downloads included) and how many crates were compiled.
+fn finished_line(cargo_line: &str, duration: Duration, crates: usize, colored: bool) -> String {
+    let verb = styled_verb("✔ Finished", "32", colored);
+    let duration = format::duration(duration);
+    let plain = strip_colors(cargo_line);
+    // Like `Finished `dev` profile [...]` or, before cargo 1.77, `Finished dev
+    // [...]`.
+    let profile = plain
+        .split_whitespace()
+        .nth(1)
+        .map(|profile| profile.trim_matches('`'))
+        .filter(|profile| !profile.is_empty() && !profile.starts_with('[') && *profile != "in");
+    let mut line = match profile {
+        Some(profile) => format!(
+            "{} {}",
+            verb,
+            l10n::text(
+                "finished-profile-in",
+                &[("profile", &profile), ("duration", &duration)]
+            )
+        ),
+        None => format!(
+            "{} {}",
+            verb,
+            l10n::text("finished-in", &[("duration", &duration)])
+        ),
+    };
+    if crates > 0 {
+        line.push(' ');
+        line.push_str(&l10n::text("compiled-crates", &[("count", &crates)]));
+    }
+    line.push('\n');
+    line
+}
+
+/// The line that replaces the status line when the build fails, styled after
+/// cargo's own `Finished` line.
+fn failure_line(
+    failed_crates: &[&str],
+    duration: Duration,
+    first_error: Option<Duration>,
+    colored: bool,
+) -> String {
+    let verb = styled_verb("✘ Failed", "31", colored);
+    let crates: Vec<_> = failed_crates
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect();
+    let duration = format::duration(duration);
+    let mut line = if crates.is_empty() {
+        let after = l10n::text("failed-after", &[("duration", &duration)]);
+        format!("{} {}", verb, after)
+    } else {
+        let crates = crates.join(", ");
+        let after = l10n::text(
+            "failed-crates-after",
+            &[("crates", &crates), ("duration", &duration)],
+        );
+        format!("{} {}", verb, after)
+    };
+    if let Some(first_error) = first_error {
+        let first_error = format::duration(first_error);
+        line.push(' ');
+        line.push_str(&l10n::text("first-error", &[("duration", &first_error)]));
+    }
+    line
+}
+
+/// Appends the `text` to the file, creating it if needed.
+fn append(path: &Path, text: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(text.as_bytes())
+}
+
+/// Compares the build duration with the baseline, if there's one, and warns
+/// about a regression. Returns `true` if the build has regressed.
+fn check_regression(options: &Options, cargo_args: &CargoArgs, duration: Duration) -> bool {
+    let command = match &cargo_args.subcommand {
+        Some(command) => command,
+        None => return false,
+    };
+    let baselines =
+        match history::project_root(cargo_args).map(|root| baseline::Baselines::load(&root)) {
+            Some(Ok(baselines)) => baselines,
+            _ => return false,
+        };
+    match baselines.regression(command, duration, options.regression_threshold) {
+        Some(slowdown) => {
+            let baseline = Duration::from_secs_f64(baselines.durations[command]);
+            eprintln!(
+                "warning: {}",
+                l10n::text(
+                    "regression",
+                    &[
+                        ("duration", &format::duration(duration)),
+                        ("percent", &format!("{:.0}", slowdown)),
+                        ("baseline", &format::duration(baseline)),
+                    ]
+                )
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+/// Runs the command with the output going straight to the terminal.
+fn run_transparent(mut cmd: Command) -> std::io::Result<()> {
+    let status = cmd
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()?;
+    if !status.success() {
+        std::process::exit(exit_code(status));
+    }
+    Ok(())
+}
+
+/// Appends the build to the project's history, returns the previous build of
+/// the same command, if any.
+fn record_build(
+    cargo_args: &CargoArgs,
+    build: &history::Build,
+) -> std::io::Result<Option<history::Build>> {
+    let root = match history::project_root(cargo_args) {
+        Some(root) => root,
+        None => return Ok(None),
+    };
+    let mut history = History::load(&root)?;
+    let previous = history.previous(&build.command).cloned();
+    history.push(build.clone());
+    history.save()?;
+    Ok(previous)
+}
+
+#[test]
+fn formats_failure_line() {
+    assert_eq!(
+        failure_line(
+            &["foo", "bar"],
+            Duration::from_secs(3),
+            Some(Duration::from_secs(1)),
+            false
+        ),
+        "    ✘ Failed `foo`, `bar` after 3.0s (first error at 1.0s)"
+    );
+    assert_eq!(
+        failure_line(&[], Duration::from_secs(3), None, true),
+        "\x1b[1;31m    ✘ Failed\x1b[0m after 3.0s"
+    );
+}
+
+#[test]
+fn formats_finished_line() {
+    let duration = Duration::from_millis(12_400);
+    assert_eq!(
+        finished_line(
+            "\x1b[1m\x1b[32m    Finished\x1b[0m `dev` profile [unoptimized + debuginfo] target(s) in 11.90s\n",
+            duration,
+            180,
+            false
+        ),
+        "  ✔ Finished `dev` profile in 12.4s (180 crates)\n"
+    );
+    assert_eq!(
+        finished_line(
+            "    Finished release [optimized] target(s) in 0.01s\n",
+            duration,
+            1,
+            false
+        ),
+        "  ✔ Finished `release` profile in 12.4s (1 crate)\n"
+    );
+    assert_eq!(
+        finished_line("    Finished in 0.01s\n", duration, 0, false),
+        "  ✔ Finished in 12.4s\n"
+    );
+}
+
+#[test]
+fn propagates_exit_code() {
+    let status = Command::new(cargo_path())
+        .arg("--no-such-option")
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(exit_code(status), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn maps_signals_to_exit_codes() {
+    let status = Command::new("sh").args(["-c", "exit 3"]).status().unwrap();
+    assert_eq!(exit_code(status), 3);
+    let status = Command::new("sh")
+        .args(["-c", "kill -TERM $$"])
+        .status()
+        .unwrap();
+    assert_eq!(exit_code(status), 128 + libc::SIGTERM);
+}
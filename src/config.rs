@@ -0,0 +1,214 @@
+//! Configuration files: the user's one (`~/.config/cargo-single-line/config.toml`
+//! on Linux) and the project's `.cargo-single-line.toml` next to `Cargo.lock`.
+//!
+//! The keys are the plugin's options without the leading dashes, like
+//! `stall-timeout = 10` or `redact = ["secret-[0-9]+"]`, and the flags are
+//! booleans named after what they turn on: `throughput = true`, `progress =
+//! false`. The project's file takes precedence over the user's one, and the
+//! command line over both. The [presets](crate::preset) are defined and
+//! selected here as well.
+//!
+//! Only a (very) small subset of TOML is understood: top-level `key = value`
+//! pairs, where the value is a string, a number, a boolean or an array of
+//! strings on a single line.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cargo_args::CargoArgs,
+    options::Options,
+    preset::{self, Presets},
+};
+
+/// Name of the project's configuration file.
+pub const PROJECT_FILE: &str = ".cargo-single-line.toml";
+
+/// A value of a setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    /// A string or a number, as written.
+    String(String),
+    Array(Vec<String>),
+}
+
+/// Where the user's configuration file is.
+pub fn user_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+        })
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+    Some(config_dir.join("cargo-single-line").join("config.toml"))
+}
+
+/// The options set by the configuration files over the `presets` (given on
+/// the command line, or else selected by the environment or the files), the
+/// defaults for the rest.
+pub fn load(cargo_args: &CargoArgs, presets: &[String]) -> Result<Options, String> {
+    let project_path = crate::history::project_root(cargo_args).map(|root| root.join(PROJECT_FILE));
+    let mut definitions = Presets::default();
+    let mut selected = Vec::new();
+    let mut settings = Vec::new();
+    for path in user_path().into_iter().chain(project_path) {
+        let context = |e: String| format!("{}: {}", path.display(), e);
+        for (key, value) in read_file(&path).map_err(context)? {
+            match (key.as_str(), &value) {
+                ("preset", Value::String(list)) => {
+                    selected = preset::names(list).map(str::to_owned).collect();
+                }
+                ("preset", _) => return Err(context("preset expects a name".into())),
+                _ if definitions.define(&key, &value).map_err(context)? => {}
+                _ => settings.push((path.clone(), key, value)),
+            }
+        }
+    }
+    let from_env = std::env::var(preset::ENV).unwrap_or_default();
+    let selected: Vec<&str> = if !presets.is_empty() {
+        presets.iter().map(String::as_str).collect()
+    } else if !from_env.trim().is_empty() {
+        preset::names(&from_env).collect()
+    } else {
+        selected.iter().map(String::as_str).collect()
+    };
+    let mut options = Options::default();
+    for name in selected {
+        definitions.apply(&mut options, name)?;
+    }
+    for (path, key, value) in settings {
+        options
+            .apply(&key, value)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(options)
+}
+
+/// Reads the settings from the file, if it exists.
+fn read_file(path: &Path) -> Result<Vec<(String, Value)>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses the settings, in the order they are given.
+pub fn parse(contents: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut settings = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("line {}: unsupported setting {:?}", index + 1, line);
+        let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+        let key = key.trim().trim_matches('"');
+        let value = parse_value(value.trim()).ok_or_else(invalid)?;
+        settings.push((key.to_owned(), value));
+    }
+    Ok(settings)
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(items) = text.strip_prefix('[') {
+        let mut rest = items.trim_start();
+        let mut values = Vec::new();
+        loop {
+            if let Some(tail) = rest.strip_prefix(']') {
+                return is_comment(tail).then_some(Value::Array(values));
+            }
+            let (value, tail) = parse_string(rest)?;
+            values.push(value);
+            let tail = tail.trim_start();
+            rest = match tail.strip_prefix(',') {
+                Some(tail) => tail.trim_start(),
+                None => tail,
+            };
+        }
+    }
+    if text.starts_with(['"', '\'']) {
+        let (value, tail) = parse_string(text)?;
+        return is_comment(tail).then_some(Value::String(value));
+    }
+    let text = match text.split_once('#') {
+        Some((text, _comment)) => text.trim_end(),
+        None => text,
+    };
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ if text.parse::<f64>().is_ok() => Some(Value::String(text.replace('_', ""))),
+        _ => None,
+    }
+}
+
+/// Parses a basic (`"..."`) or a literal (`'...'`) string at the beginning of
+/// the text, returning it along with the rest of the text.
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    if let Some(literal) = text.strip_prefix('\'') {
+        let (value, rest) = literal.split_once('\'')?;
+        return Some((value.to_owned(), rest));
+    }
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[index + 2..])),
+            '\\' => value.push(match chars.next()?.1 {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Whether nothing but a comment is left.
+fn is_comment(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+#[test]
+fn parses_settings() {
+    let contents = r#"
+# A comment.
+stall-timeout = 10
+throughput = true # Another one.
+progress = false
+lang = "en"
+redact = ["token-[0-9]+", 'C:\secret' ]
+env = []
+"#;
+    assert_eq!(
+        parse(contents).unwrap(),
+        [
+            ("stall-timeout".into(), Value::String("10".into())),
+            ("throughput".into(), Value::Bool(true)),
+            ("progress".into(), Value::Bool(false)),
+            ("lang".into(), Value::String("en".into())),
+            (
+                "redact".into(),
+                Value::Array(vec!["token-[0-9]+".into(), r"C:\secret".into()])
+            ),
+            ("env".into(), Value::Array(Vec::new())),
+        ]
+    );
+    assert!(parse("[section]").is_err());
+    assert!(parse("lang = en").is_err());
+    assert!(parse(r#"lang = "en"#).is_err());
+}
@@ -0,0 +1,96 @@
+//! `--only GLOB` and `--hide GLOB`: which crates get on the status line. In a
+//! big workspace the interesting crates flash by among the hundreds of
+//! dependencies, so with `--only 'my-org-*'` the status line sticks with the
+//! crates of the organization, while the rest are only counted by the progress
+//! indicator.
+//!
+//! The globs know `*` (any number of characters) and `?` (a single one), and
+//! don't tell a dash from an underscore, like cargo itself when it comes to the
+//! crate names.
+
+/// Picks the crates to show.
+#[derive(Debug, Clone)]
+pub struct CrateFilter {
+    only: Vec<String>,
+    hide: Vec<String>,
+}
+
+impl CrateFilter {
+    /// The filter with the globs given, if any.
+    pub fn new(only: &[String], hide: &[String]) -> Option<Self> {
+        if only.is_empty() && hide.is_empty() {
+            return None;
+        }
+        Some(CrateFilter {
+            only: only.to_vec(),
+            hide: hide.to_vec(),
+        })
+    }
+
+    /// Whether the crate gets on the status line: it matches one of the
+    /// `--only` globs (if there are any) and none of the `--hide` ones.
+    pub fn shows(&self, name: &str) -> bool {
+        let matches = |glob: &String| matches(glob, name);
+        (self.only.is_empty() || self.only.iter().any(matches)) && !self.hide.iter().any(matches)
+    }
+}
+
+/// Whether the crate `name` matches the `glob`.
+fn matches(glob: &str, name: &str) -> bool {
+    let normalize = |c: char| if c == '_' { '-' } else { c };
+    let glob: Vec<char> = glob.chars().map(normalize).collect();
+    let name: Vec<char> = name.chars().map(normalize).collect();
+    let (mut g, mut n) = (0, 0);
+    // Where to go back to if the rest doesn't match: the glob past the latest
+    // star, and the name past the characters the star has taken so far.
+    let mut star = None;
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g + 1, n));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after_star, taken)) => {
+                    star = Some((after_star, taken + 1));
+                    g = after_star;
+                    n = taken + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn matches_globs() {
+    assert!(matches("serde", "serde"));
+    assert!(!matches("serde", "serde_json"));
+    assert!(matches("serde*", "serde_json"));
+    assert!(matches("*json", "serde_json"));
+    assert!(matches("my-org-*", "my_org_core"));
+    assert!(matches("*-sys", "openssl-sys"));
+    assert!(matches("a*b*c", "aXXbYYbZc"));
+    assert!(!matches("a*b*c", "aXXbYYbZ"));
+    assert!(matches("tok?o", "tokio"));
+    assert!(!matches("tok?o", "toko"));
+    assert!(matches("*", ""));
+    assert!(!matches("?", ""));
+}
+
+#[test]
+fn filters_crates() {
+    assert!(CrateFilter::new(&[], &[]).is_none());
+    let filter = CrateFilter::new(&["my-org-*".into()], &["*-macros".into()]).unwrap();
+    assert!(filter.shows("my-org-core"));
+    assert!(!filter.shows("my-org-macros"));
+    assert!(!filter.shows("serde"));
+    let filter = CrateFilter::new(&[], &["*-sys".into()]).unwrap();
+    assert!(filter.shows("serde"));
+    assert!(!filter.shows("openssl-sys"));
+}
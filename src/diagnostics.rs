@@ -0,0 +1,360 @@
+//! Collection of the compiler diagnostics (warnings and errors) from the
+//! human-readable cargo output (or from the [JSON messages](crate::messages)).
+//!
+//! A diagnostic starts with a `warning:`/`error:` line and lasts until an empty
+//! line. Cargo follows the diagnostics of a crate with a summary like
+//! ``warning: `foo` (lib) generated 2 warnings`` or ``error: could not compile
+//! `foo` ``, which is how the diagnostics are attributed to the crates.
+
+use std::borrow::Cow;
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    }
+}
+
+/// A single warning or error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// The lint or error code, like `unused_variables` or `E0308`, if known.
+    pub code: Option<String>,
+    /// The first line of the diagnostic, without the level.
+    pub message: String,
+    /// The whole diagnostic, color codes excluded.
+    pub lines: Vec<String>,
+    /// The crate the diagnostic belongs to, if known.
+    pub crate_name: Option<String>,
+}
+
+/// Parses the first line of a diagnostic: `error[E0308]: mismatched types`.
+fn parse_header(line: &str) -> Option<(Level, Option<String>, String)> {
+    let (level, rest) = if let Some(rest) = line.strip_prefix("warning") {
+        (Level::Warning, rest)
+    } else if let Some(rest) = line.strip_prefix("error") {
+        (Level::Error, rest)
+    } else {
+        return None;
+    };
+    let (code, rest) = match rest.strip_prefix('[') {
+        Some(rest) => {
+            let (code, rest) = rest.split_once(']')?;
+            (Some(code.to_owned()), rest)
+        }
+        None => (None, rest),
+    };
+    let message = rest.strip_prefix(':')?.trim();
+    Some((level, code, message.to_owned()))
+}
+
+/// Extracts the crate name from the cargo's per-crate summary lines.
+fn summary_crate(message: &str) -> Option<&str> {
+    let rest = message
+        .strip_prefix("could not compile `")
+        .or_else(|| message.strip_prefix('`'))?;
+    let (name, rest) = rest.split_once('`')?;
+    (message.starts_with("could not compile") || rest.contains(" generated ")).then_some(name)
+}
+
+/// The crate cargo reports failing to compile on the line, like `foo` from
+/// ``error: could not compile `foo` (lib) due to 2 previous errors``.
+pub fn failed_crate(line: &str) -> Option<String> {
+    let (level, _, message) = parse_header(crate::strip_colors(line).trim_end())?;
+    let name = summary_crate(&message).filter(|_| level == Level::Error)?;
+    message
+        .starts_with("could not compile")
+        .then(|| name.to_owned())
+}
+
+/// Messages cargo prints on its own that are not diagnostics.
+fn is_cargo_chatter(message: &str) -> bool {
+    message.starts_with("build failed, waiting for other jobs")
+        || message.starts_with("unused manifest key")
+}
+
+/// Collects the diagnostics from the cargo output.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    finished: Vec<Diagnostic>,
+    current: Option<Diagnostic>,
+    /// Diagnostics starting from this index are not attributed to a crate yet.
+    unattributed: usize,
+    /// The level of the diagnostic the latest line is a part of, see
+    /// [`Diagnostics::within`].
+    within: Option<Level>,
+    /// Whether the latest line continues a diagnostic, see
+    /// [`Diagnostics::continues`].
+    continues: bool,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo. Returns the level of the
+    /// diagnostic if the line starts a new one.
+    pub fn observe(&mut self, line: &str) -> Option<Level> {
+        let line = crate::strip_colors(line);
+        let line = line.trim_end();
+        self.continues = false;
+        if let Some((level, code, message)) = parse_header(line) {
+            self.finish_current();
+            if let Some(name) = summary_crate(&message) {
+                for diagnostic in &mut self.finished[self.unattributed..] {
+                    diagnostic.crate_name = Some(name.to_owned());
+                }
+                self.unattributed = self.finished.len();
+                self.within = Some(level);
+                return None;
+            }
+            if is_cargo_chatter(&message) {
+                self.within = None;
+                return None;
+            }
+            self.current = Some(Diagnostic {
+                level,
+                code,
+                message,
+                lines: vec![line.to_owned()],
+                crate_name: None,
+            });
+            self.within = Some(level);
+            return Some(level);
+        } else if line.is_empty() {
+            // The empty line ending a diagnostic is a part of it.
+            self.within = self.current.as_ref().map(|current| current.level);
+            self.continues = self.current.is_some();
+            self.finish_current();
+        } else if crate::starts_with(line, crate::unit::UNIT_PREFIXES) {
+            self.within = None;
+            self.finish_current();
+        } else if let Some(current) = &mut self.current {
+            current.lines.push(line.to_owned());
+            self.within = Some(current.level);
+            self.continues = true;
+        } else {
+            // Only the errors have the explanations.
+            self.within = line
+                .starts_with("For more information about")
+                .then_some(Level::Error);
+        }
+        None
+    }
+
+    /// Whether the latest observed line is a part of a diagnostic, or of the
+    /// cargo's summary of them.
+    pub fn is_within(&self) -> bool {
+        self.within.is_some()
+    }
+
+    /// The level of the diagnostic (or of the cargo's summary) the latest
+    /// observed line is a part of.
+    pub fn within(&self) -> Option<Level> {
+        self.within
+    }
+
+    /// Whether the latest observed line is a part of a diagnostic other than
+    /// its first line, the empty line ending it included.
+    pub fn continues(&self) -> bool {
+        self.continues
+    }
+
+    /// Takes a note of a diagnostic reported in a structured way, like in a
+    /// JSON message. Returns its level.
+    pub fn record(&mut self, diagnostic: Diagnostic) -> Level {
+        self.finish_current();
+        let level = diagnostic.level;
+        self.finished.push(diagnostic);
+        level
+    }
+
+    fn finish_current(&mut self) {
+        if let Some(mut diagnostic) = self.current.take() {
+            // Lint names are only mentioned in the notes.
+            if diagnostic.code.is_none() {
+                diagnostic.code = diagnostic.lines.iter().find_map(|line| lint_name(line));
+            }
+            self.finished.push(diagnostic);
+        }
+    }
+
+    /// How many diagnostics of the `level` have been seen so far.
+    pub fn count(&self, level: Level) -> usize {
+        self.finished
+            .iter()
+            .chain(&self.current)
+            .filter(|diagnostic| diagnostic.level == level)
+            .count()
+    }
+
+    /// Prefixes the status line with the errors and warnings counters, once
+    /// there's anything to count. With `colored`, errors are red and warnings
+    /// are yellow, in bold when there are any.
+    pub fn decorate<'a>(&self, line: &'a str, colored: bool) -> Cow<'a, str> {
+        let errors = self.count(Level::Error);
+        let warnings = self.count(Level::Warning);
+        if errors == 0 && warnings == 0 {
+            return Cow::Borrowed(line);
+        }
+        let counter = |label: &str, count: usize, color: &str| {
+            if !colored {
+                format!("{}:{}", label, count)
+            } else if count == 0 {
+                format!("\x1b[{}m{}:{}\x1b[0m", color, label, count)
+            } else {
+                format!("\x1b[1;{}m{}:{}\x1b[0m", color, label, count)
+            }
+        };
+        Cow::Owned(format!(
+            "{} {} {}",
+            counter("E", errors, "31"),
+            counter("W", warnings, "33"),
+            line
+        ))
+    }
+
+    /// All the diagnostics collected so far.
+    pub fn finish(mut self) -> Vec<Diagnostic> {
+        self.finish_current();
+        self.finished
+    }
+}
+
+/// The crates that have failed to compile, judging by the `diagnostics`.
+pub fn failed_crates(diagnostics: &[Diagnostic]) -> Vec<&str> {
+    let mut names: Vec<&str> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level == Level::Error)
+        .filter_map(|diagnostic| diagnostic.crate_name.as_deref())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Extracts the lint name from a note like
+/// `= note: `#[warn(unused_variables)]` on by default`.
+fn lint_name(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once("#[")?;
+    let (_, rest) = rest.split_once('(')?;
+    let (name, _) = rest.split_once(')')?;
+    Some(name.to_owned())
+}
+
+#[cfg(test)]
+const SAMPLE: &str = "   Compiling foo v0.1.0 (/tmp/foo)
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 5;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0308]: mismatched types
+ --> src/main.rs:3:18
+  |
+3 |     let y: u8 = \"a\";
+  |            --   ^^^ expected `u8`, found `&str`
+
+For more information about this error, try `rustc --explain E0308`.
+warning: `foo` (bin \"foo\") generated 1 warning
+error: could not compile `foo` (bin \"foo\") due to 1 previous error; 1 warning emitted
+";
+
+#[test]
+fn collects_diagnostics() {
+    let mut diagnostics = Diagnostics::new();
+    let levels: Vec<_> = SAMPLE
+        .lines()
+        .filter_map(|line| diagnostics.observe(line))
+        .collect();
+    assert_eq!(levels, [Level::Warning, Level::Error]);
+    let diagnostics = diagnostics.finish();
+    assert_eq!(diagnostics.len(), 2);
+
+    assert_eq!(diagnostics[0].level, Level::Warning);
+    assert_eq!(diagnostics[0].message, "unused variable: `x`");
+    assert_eq!(diagnostics[0].code.as_deref(), Some("unused_variables"));
+    assert_eq!(diagnostics[0].lines.len(), 7);
+    assert_eq!(diagnostics[0].crate_name.as_deref(), Some("foo"));
+
+    assert_eq!(diagnostics[1].level, Level::Error);
+    assert_eq!(diagnostics[1].code.as_deref(), Some("E0308"));
+    assert_eq!(diagnostics[1].message, "mismatched types");
+    assert_eq!(diagnostics[1].crate_name.as_deref(), Some("foo"));
+    assert_eq!(failed_crates(&diagnostics), ["foo"]);
+}
+
+#[test]
+fn tells_diagnostic_lines() {
+    let mut diagnostics = Diagnostics::new();
+    let within: Vec<bool> = SAMPLE
+        .lines()
+        .map(|line| {
+            let _ = diagnostics.observe(line);
+            diagnostics.is_within()
+        })
+        .collect();
+    assert!(!within[0]);
+    assert!(within[1..].iter().all(|within| *within));
+
+    let mut diagnostics = Diagnostics::new();
+    let levels: Vec<_> = SAMPLE
+        .lines()
+        .map(|line| {
+            let _ = diagnostics.observe(line);
+            diagnostics.within()
+        })
+        .collect();
+    assert!(levels[1..9]
+        .iter()
+        .all(|level| *level == Some(Level::Warning)));
+    assert!(levels[9..16]
+        .iter()
+        .all(|level| *level == Some(Level::Error)));
+    assert_eq!(levels[16..], [Some(Level::Warning), Some(Level::Error)]);
+}
+
+#[test]
+fn decorates_status() {
+    let mut diagnostics = Diagnostics::new();
+    assert_eq!(diagnostics.decorate("Compiling foo", true), "Compiling foo");
+    let _ = diagnostics.observe("warning: unused variable: `x`\n");
+    assert_eq!(
+        diagnostics.decorate("Compiling foo", false),
+        "E:0 W:1 Compiling foo"
+    );
+    assert_eq!(
+        diagnostics.decorate("Compiling foo", true),
+        "\x1b[31mE:0\x1b[0m \x1b[1;33mW:1\x1b[0m Compiling foo"
+    );
+}
+
+#[test]
+fn finds_failed_crate() {
+    assert_eq!(
+        failed_crate(
+            "\x1b[1m\x1b[31merror\x1b[0m\x1b[1m:\x1b[0m could not compile `my-crate` (lib) due to 2 previous errors\n"
+        )
+        .as_deref(),
+        Some("my-crate")
+    );
+    assert_eq!(
+        failed_crate("warning: `my-crate` (lib) generated 1 warning\n"),
+        None
+    );
+    assert_eq!(failed_crate("error[E0425]: cannot find value `x`\n"), None);
+}
@@ -0,0 +1,238 @@
+//! The `doctor` subcommand: what the plugin has detected about the
+//! environment, to make "it looks weird in my terminal" reports actionable.
+//! The report is followed by the findings, what's likely to go wrong and how
+//! to fix it.
+
+use std::{ffi::OsStr, io, path::PathBuf};
+
+use crate::{
+    init, recursion,
+    terminal::{self, ColorDepth},
+};
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// How the output is going to be rendered.
+pub fn rendering_strategy(stderr_is_terminal: bool) -> &'static str {
+    if stderr_is_terminal {
+        "status line overwritten in place, colors forced with `--color=always`"
+    } else {
+        "status printed as a heartbeat line every so often, colors left up to cargo (stderr is not a terminal)"
+    }
+}
+
+/// The cargo aliases running the plugin, defined in the `config` file either
+/// in the `[alias]` table or as `alias.NAME = ...`.
+fn plugin_aliases(config: &str) -> Vec<&str> {
+    let mut in_alias_table = false;
+    let mut aliases = Vec::new();
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_alias_table = line == "[alias]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let name = match key.strip_prefix("alias.") {
+            Some(name) if !in_alias_table => name,
+            _ if in_alias_table => key,
+            _ => continue,
+        };
+        if value.contains("single-line") {
+            aliases.push(name.trim_matches('"'));
+        }
+    }
+    aliases
+}
+
+/// The cargo configuration files that apply in the current directory: the
+/// `.cargo/config.toml` (or the legacy `.cargo/config`) files up the tree,
+/// then the one in cargo's home.
+fn config_files() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::current_dir()
+        .map(|dir| dir.ancestors().map(|dir| dir.join(".cargo")).collect())
+        .unwrap_or_default();
+    if let Some(home) = init::cargo_home() {
+        if !dirs.contains(&home) {
+            dirs.push(home);
+        }
+    }
+    dirs.iter()
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Prints the report.
+pub fn run(cargo: &OsStr) {
+    let stderr_is_terminal = atty::is(atty::Stream::Stderr);
+    let color_depth = ColorDepth::detect();
+    let virtual_terminal = terminal::virtual_terminal_enabled();
+    let cargo_env = std::env::var_os("CARGO");
+    let cargo_is_self = recursion::is_self(cargo);
+    let depth = std::env::var(recursion::DEPTH_ENV).ok();
+    let aliases: Vec<(PathBuf, String)> = config_files()
+        .into_iter()
+        .filter_map(|path| {
+            let config = std::fs::read_to_string(&path).ok()?;
+            let aliases = plugin_aliases(&config);
+            (!aliases.is_empty()).then(|| (path, aliases.join(", ")))
+        })
+        .collect();
+
+    let mut report: Vec<(&str, String)> = vec![
+        (
+            "stdin is a terminal",
+            yes_no(atty::is(atty::Stream::Stdin)).into(),
+        ),
+        (
+            "stdout is a terminal",
+            yes_no(atty::is(atty::Stream::Stdout)).into(),
+        ),
+        ("stderr is a terminal", yes_no(stderr_is_terminal).into()),
+        (
+            "terminal size",
+            match terminal::size() {
+                Some((columns, rows)) => format!("{}x{}", columns, rows),
+                None => "unknown".into(),
+            },
+        ),
+        ("color depth", color_depth.description().into()),
+        (
+            "multiplexer",
+            terminal::multiplexer().unwrap_or("none").into(),
+        ),
+    ];
+    if let Some(enabled) = virtual_terminal {
+        report.push(("virtual terminal processing", yes_no(enabled).into()));
+    }
+    report.push((
+        "cargo",
+        match &cargo_env {
+            Some(_) => format!("{} (from CARGO)", cargo.to_string_lossy()),
+            None => format!("{} (from the PATH)", cargo.to_string_lossy()),
+        },
+    ));
+    report.push((
+        "nested instance",
+        match &depth {
+            Some(depth) => format!("yes, {}={}", recursion::DEPTH_ENV, depth),
+            None => "no".into(),
+        },
+    ));
+    report.push((
+        "cargo aliases",
+        if aliases.is_empty() {
+            "none".into()
+        } else {
+            aliases
+                .iter()
+                .map(|(path, aliases)| format!("{} ({})", aliases, path.display()))
+                .collect::<Vec<_>>()
+                .join("; ")
+        },
+    ));
+    report.push(("rendering", rendering_strategy(stderr_is_terminal).into()));
+
+    let width = report.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in report {
+        println!("{:>width$}: {}", name, value, width = width);
+    }
+
+    let mut findings = Vec::new();
+    if !stderr_is_terminal {
+        findings.push(
+            "stderr is not a terminal (the output is piped, or it's a CI), so the status is \
+             printed as a heartbeat line instead of being drawn in place"
+                .to_owned(),
+        );
+    }
+    if virtual_terminal == Some(false) {
+        findings.push(
+            "the console doesn't process the ANSI sequences, the status line is erased with \
+             spaces and cargo prints no colors: try Windows Terminal"
+                .to_owned(),
+        );
+    }
+    if stderr_is_terminal && matches!(color_depth, ColorDepth::None) {
+        findings.push(
+            "the colors are off (NO_COLOR is set or TERM is `dumb`): unset NO_COLOR, or set \
+             TERM to your terminal, like `xterm-256color`"
+                .to_owned(),
+        );
+    }
+    if cargo_is_self {
+        findings.push(
+            "CARGO points to the plugin itself, the real cargo is looked up in the PATH \
+             instead: point CARGO to cargo, or unset it"
+                .to_owned(),
+        );
+    } else if cargo_env.is_some() && recursion::real_cargo().is_none() {
+        findings.push(format!(
+            "CARGO is set to {}, while there's no cargo in the PATH",
+            cargo.to_string_lossy()
+        ));
+    }
+    if let Some(depth) = &depth {
+        findings.push(format!(
+            "{}={} is set, so the plugin takes itself for a nested instance and leaves the \
+             output as is: unset it if it has leaked into your shell",
+            recursion::DEPTH_ENV,
+            depth
+        ));
+    }
+    if aliases.is_empty() {
+        findings.push(
+            "no cargo alias runs the plugin: `cargo single-line doctor --install-alias` adds \
+             `cargo b`, `cargo c`, `cargo t` and `cargo r`"
+                .to_owned(),
+        );
+    }
+
+    println!();
+    if findings.is_empty() {
+        println!("Everything looks fine.");
+    }
+    for finding in findings {
+        println!("* {}", finding);
+    }
+}
+
+/// Installs the cargo aliases to the `config.toml` in cargo's home, with
+/// `--install-alias`.
+pub fn install_alias() -> io::Result<()> {
+    let cargo_home = init::cargo_home().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "unable to locate the cargo home")
+    })?;
+    init::install_cargo_aliases(&cargo_home.join("config.toml"))
+}
+
+#[test]
+fn finds_aliases() {
+    let config = r#"
+[build]
+jobs = 4
+
+[alias]
+b = "single-line build"
+xtask = "run --package xtask --"
+"c" = ["single-line", "check"]
+
+[target.x86_64-unknown-linux-gnu]
+linker = "clang"
+"#;
+    assert_eq!(plugin_aliases(config), ["b", "c"]);
+    assert_eq!(
+        plugin_aliases("alias.t = \"single-line test\"\nalias.r = \"run\"\n"),
+        ["t"]
+    );
+    assert!(plugin_aliases("[build]\nb = \"single-line build\"\n").is_empty());
+}
@@ -0,0 +1,279 @@
+//! Downloads of the crates.
+//!
+//! Cargo prints a `Downloaded foo v1.2.3` line per crate, which would only
+//! flash through the status line. Instead, a burst of downloads is displayed as
+//! a counter, and a single summary line is kept once it's over: cargo's own
+//! `Downloaded 142 crates (36.8 MB) in 4.2s`, or ours if cargo hasn't printed
+//! one.
+//!
+//! The counter is as detailed as the cargo's home allows: the crates of the
+//! `Cargo.lock` not found in the registry cache give an estimate of how many
+//! crates are to be downloaded, and the sizes of the freshly cached `.crate`
+//! files add up to `Downloaded 143/~210 crates (37.2 MiB)…`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{format, lockfile};
+
+/// How a line should be displayed with respect to the downloads.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Burst {
+    /// The line is a part of a burst, the text is to be shown on the status
+    /// line instead.
+    Progress(String),
+    /// The burst is over, the summary is to be displayed for good. Unless the
+    /// line was the summary itself, it's to be displayed as usual afterwards.
+    Over { summary: String, consumed: bool },
+}
+
+/// Collects the downloaded crates from the cargo output.
+#[derive(Debug, Default)]
+pub struct Downloads {
+    /// `name vX.Y.Z` of every downloaded crate.
+    pub crates: Vec<String>,
+    /// Cargo's own summary, like `Downloaded 5 crates (1.2 MB) in 0.89s`.
+    pub summary: Option<String>,
+    /// When the current burst has started and how many crates it has
+    /// downloaded so far.
+    burst: Option<(Instant, usize)>,
+    /// The lock file of the project, to estimate how many crates are missing.
+    lock_file: Option<PathBuf>,
+    /// The registries' directories of the downloaded `.crate` files.
+    caches: Vec<PathBuf>,
+    /// How many crates the current burst is expected to download.
+    expected: Option<usize>,
+    /// The size of the crates downloaded by the current burst.
+    bytes: u64,
+}
+
+impl Downloads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up the downloaded crates in the registry cache of the
+    /// `cargo_home`, and estimates the crates to download from the
+    /// `lock_file`, if any.
+    pub fn with_registry(lock_file: Option<PathBuf>, cargo_home: &Path) -> Self {
+        let caches = std::fs::read_dir(cargo_home.join("registry").join("cache"))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        Downloads {
+            lock_file,
+            caches,
+            ..Self::default()
+        }
+    }
+
+    /// The cached `.crate` file of the crate, like `serde v1.0.197`.
+    fn cached(&self, name_version: &str) -> Option<PathBuf> {
+        let mut words = name_version.split_whitespace();
+        let name = words.next()?;
+        let version = words.next()?.strip_prefix('v')?;
+        let file = format!("{}-{}.crate", name, version);
+        self.caches
+            .iter()
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+    }
+
+    /// How many crates of the lock file are not downloaded yet. Git
+    /// dependencies are counted too, hence it's only an estimate.
+    fn estimate(&self) -> Option<usize> {
+        if self.caches.is_empty() {
+            return None;
+        }
+        let contents = std::fs::read_to_string(self.lock_file.as_ref()?).ok()?;
+        let missing = lockfile::packages(&contents)
+            .iter()
+            .filter(|package| package.has_source)
+            .filter(|package| {
+                self.cached(&format!("{} v{}", package.name, package.version))
+                    .is_none()
+            })
+            .count();
+        Some(missing).filter(|&missing| missing > 0)
+    }
+
+    /// The counter of the current burst.
+    fn progress(&self, count: usize) -> String {
+        let size = format::size(self.bytes);
+        let text = match self.expected.filter(|&total| count <= total) {
+            Some(total) => crate::l10n::text(
+                "downloaded-of-total",
+                &[("count", &count), ("total", &total), ("size", &size)],
+            ),
+            None if self.bytes > 0 => crate::l10n::text(
+                "downloaded-size-so-far",
+                &[("count", &count), ("size", &size)],
+            ),
+            None => crate::l10n::text("downloaded-so-far", &[("count", &count)]),
+        };
+        format!("{:>12} {}", "Downloaded", text)
+    }
+
+    /// Takes a note of a line printed by cargo at the moment `now`, telling how
+    /// to display it if it matters for the downloads.
+    pub fn observe(&mut self, line: &str, now: Instant) -> Option<Burst> {
+        let plain = crate::strip_colors(line);
+        let rest = match plain.trim().strip_prefix("Downloaded ") {
+            Some(rest) => rest,
+            None => {
+                if crate::starts_with(line, &["Downloading"]) {
+                    // `Downloading crates ...` comes right before the burst.
+                    if self.burst.is_none() && self.expected.is_none() {
+                        self.expected = self.estimate();
+                    }
+                    return None;
+                }
+                let (started, count) = self.burst.take()?;
+                self.expected = None;
+                self.bytes = 0;
+                let duration = format::duration(now.saturating_duration_since(started));
+                return Some(Burst::Over {
+                    summary: format!(
+                        "{:>12} {}",
+                        "Downloaded",
+                        crate::l10n::text(
+                            "downloaded-in",
+                            &[("count", &count), ("duration", &duration)]
+                        )
+                    ),
+                    consumed: false,
+                });
+            }
+        };
+        if rest.contains(" crate") && rest.contains(" in ") {
+            self.summary = Some(format!("Downloaded {}", rest));
+            self.burst = None;
+            self.expected = None;
+            self.bytes = 0;
+            return Some(Burst::Over {
+                summary: line.trim_end().to_owned(),
+                consumed: true,
+            });
+        }
+        if let Some(metadata) = self
+            .cached(rest)
+            .and_then(|path| std::fs::metadata(path).ok())
+        {
+            self.bytes += metadata.len();
+        }
+        self.crates.push(rest.to_owned());
+        let (_, count) = self.burst.get_or_insert((now, 0));
+        *count += 1;
+        let count = *count;
+        Some(Burst::Progress(self.progress(count)))
+    }
+}
+
+#[test]
+fn collects_downloads() {
+    let now = Instant::now();
+    let mut downloads = Downloads::new();
+    assert_eq!(
+        downloads.observe("  Downloaded serde v1.0.197", now),
+        Some(Burst::Progress("  Downloaded 1 crate so far…".into()))
+    );
+    assert_eq!(
+        downloads.observe("\x1b[1m\x1b[32m  Downloaded\x1b[0m syn v2.0.48", now),
+        Some(Burst::Progress("  Downloaded 2 crates so far…".into()))
+    );
+    assert_eq!(
+        downloads.observe("  Downloaded 2 crates (1.2 MB) in 0.89s\n", now),
+        Some(Burst::Over {
+            summary: "  Downloaded 2 crates (1.2 MB) in 0.89s".into(),
+            consumed: true
+        })
+    );
+    assert_eq!(downloads.observe("   Compiling syn v2.0.48", now), None);
+    assert_eq!(downloads.crates, ["serde v1.0.197", "syn v2.0.48"]);
+    assert_eq!(
+        downloads.summary.as_deref(),
+        Some("Downloaded 2 crates (1.2 MB) in 0.89s")
+    );
+}
+
+#[test]
+fn summarizes_bursts_without_cargo_summary() {
+    let now = Instant::now();
+    let mut downloads = Downloads::new();
+    let _ = downloads.observe("  Downloaded serde v1.0.197", now);
+    assert_eq!(
+        downloads.observe(
+            "   Compiling serde v1.0.197",
+            now + std::time::Duration::from_secs(2)
+        ),
+        Some(Burst::Over {
+            summary: "  Downloaded 1 crate in 2.0s".into(),
+            consumed: false
+        })
+    );
+}
+
+#[test]
+fn estimates_downloads() {
+    let home = std::env::temp_dir().join(format!("cargo-single-line-home-{}", std::process::id()));
+    let cache = home
+        .join("registry")
+        .join("cache")
+        .join("index.crates.io-0123");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(cache.join("serde-1.0.197.crate"), [0; 2048]).unwrap();
+    let lock_file = home.join("Cargo.lock");
+    std::fs::write(
+        &lock_file,
+        r#"
+[[package]]
+name = "foo"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.197"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.48"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "quote"
+version = "1.0.35"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+    )
+    .unwrap();
+
+    let now = Instant::now();
+    let mut downloads = Downloads::with_registry(Some(lock_file), &home);
+    assert_eq!(downloads.observe(" Downloading crates ...", now), None);
+    std::fs::write(cache.join("syn-2.0.48.crate"), [0; 1024 * 1024]).unwrap();
+    assert_eq!(
+        downloads.observe("  Downloaded syn v2.0.48", now),
+        Some(Burst::Progress(
+            "  Downloaded 1/~2 crates (1.0 MiB)…".into()
+        ))
+    );
+    std::fs::write(cache.join("quote-1.0.35.crate"), [0; 512 * 1024]).unwrap();
+    assert_eq!(
+        downloads.observe("  Downloaded quote v1.0.35", now),
+        Some(Burst::Progress(
+            "  Downloaded 2/~2 crates (1.5 MiB)…".into()
+        ))
+    );
+    assert_eq!(
+        downloads.observe("  Downloaded cc v1.0.90", now),
+        Some(Burst::Progress(
+            "  Downloaded 3 crates (1.5 MiB) so far…".into()
+        ))
+    );
+    std::fs::remove_dir_all(&home).unwrap();
+}
@@ -0,0 +1,80 @@
+//! Detection of crates compiled at several versions at once.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::unit::Unit;
+
+/// Collects the versions of every compiled crate.
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    versions: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Duplicates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo.
+    pub fn observe(&mut self, line: &str) {
+        if let Some(unit) = Unit::parse(line) {
+            self.versions
+                .entry(unit.name)
+                .or_default()
+                .insert(unit.version);
+        }
+    }
+
+    /// Crates that have been compiled at more than one version, along with
+    /// the versions.
+    pub fn found(&self) -> impl Iterator<Item = (&str, &BTreeSet<String>)> {
+        self.versions
+            .iter()
+            .filter(|(_, versions)| versions.len() > 1)
+            .map(|(name, versions)| (name.as_str(), versions))
+    }
+
+    /// A one-line report on the duplicates, if any.
+    pub fn report(&self) -> Option<String> {
+        let found: Vec<String> = self
+            .found()
+            .map(|(name, versions)| {
+                let versions: Vec<&str> = versions.iter().map(String::as_str).collect();
+                format!("{} ({})", name, versions.join(", "))
+            })
+            .collect();
+        if found.is_empty() {
+            return None;
+        }
+        Some(crate::l10n::text(
+            "duplicates",
+            &[("count", &found.len()), ("crates", &found.join("; "))],
+        ))
+    }
+}
+
+#[test]
+fn reports_duplicates() {
+    let mut duplicates = Duplicates::new();
+    assert_eq!(duplicates.report(), None);
+
+    duplicates.observe("   Compiling syn v1.0.109\n");
+    duplicates.observe("   Compiling serde v1.0.200\n");
+    duplicates.observe("   Compiling syn v2.0.48\n");
+    // Same version compiled for the host and for the target.
+    duplicates.observe("   Compiling serde v1.0.200\n");
+    assert_eq!(
+        duplicates.report().as_deref(),
+        Some("1 crate compiled at multiple versions: syn (1.0.109, 2.0.48)")
+    );
+
+    duplicates.observe("    Checking bitflags v1.3.2\n");
+    duplicates.observe("    Checking bitflags v2.4.0\n");
+    assert_eq!(
+        duplicates.report().as_deref(),
+        Some(
+            "2 crates compiled at multiple versions: \
+             bitflags (1.3.2, 2.4.0); syn (1.0.109, 2.0.48)"
+        )
+    );
+}
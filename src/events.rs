@@ -0,0 +1,207 @@
+//! Machine-readable build progress with `--emit-events`, for the editors and
+//! the like: one JSON object per line (NDJSON), while the terminal output stays
+//! the same.
+//!
+//! ```text
+//! {"event":"compiling","crate":"serde","version":"1.0.200","verb":"Compiling","ts":1718000000123}
+//! {"event":"warning_count","errors":0,"warnings":1,"ts":1718000001456}
+//! {"event":"lock_acquired","lock":"build directory","waited":8.2,"ts":1718000009700}
+//! {"event":"build_finished","success":true,"duration":12.4,"crates":180,"ts":1718000012523}
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+/// What's happened, see the module documentation.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BuildEvent<'a> {
+    /// A crate has started compiling (or checking).
+    Compiling {
+        #[serde(rename = "crate")]
+        name: &'a str,
+        version: &'a str,
+        verb: &'a str,
+    },
+    /// Cargo has got the lock it's been waiting for (another cargo held it),
+    /// the `waited` is in seconds.
+    LockAcquired { lock: &'a str, waited: f64 },
+    /// The amount of the errors or the warnings has changed.
+    WarningCount { errors: usize, warnings: usize },
+    /// Cargo has exited, the `duration` and the `first_error` (when the first
+    /// error was reported, if any) are in seconds.
+    BuildFinished {
+        success: bool,
+        duration: f64,
+        crates: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first_error: Option<f64>,
+    },
+}
+
+/// An event along with the time it's emitted at, in milliseconds since the
+/// Unix epoch.
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    event: &'a BuildEvent<'a>,
+    ts: u128,
+}
+
+/// Writes the events to a file or an inherited file descriptor.
+#[derive(Debug)]
+pub struct EventSink {
+    /// `None` once writing has failed, the reader must be gone.
+    output: Option<File>,
+    /// The latest counts of the errors and the warnings.
+    counts: (usize, usize),
+}
+
+impl EventSink {
+    /// Opens the `target`: `fd:N` for a file descriptor inherited from the
+    /// parent (on Unix), anything else is a path to a file, which is created
+    /// or truncated.
+    pub fn open(target: &str) -> io::Result<Self> {
+        let Some(fd) = target.strip_prefix("fd:") else {
+            return Ok(Self::new(File::create(target)?));
+        };
+        let fd = fd.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a file descriptor: {}", fd),
+            )
+        })?;
+        Ok(Self::new(duplicate(fd)?))
+    }
+
+    fn new(output: File) -> Self {
+        EventSink {
+            output: Some(output),
+            counts: (0, 0),
+        }
+    }
+
+    /// Writes the event right away, so the reader gets it while the build
+    /// runs.
+    pub fn emit(&mut self, event: &BuildEvent) {
+        let Some(output) = &mut self.output else {
+            return;
+        };
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis());
+        let mut line = serde_json::to_vec(&Record { event, ts }).expect("events are serializable");
+        line.push(b'\n');
+        if output.write_all(&line).is_err() {
+            self.output = None;
+        }
+    }
+
+    /// Emits a [`BuildEvent::WarningCount`] if the counts have changed.
+    pub fn counts(&mut self, errors: usize, warnings: usize) {
+        if self.counts != (errors, warnings) {
+            self.counts = (errors, warnings);
+            self.emit(&BuildEvent::WarningCount { errors, warnings });
+        }
+    }
+}
+
+/// A file writing to a copy of the descriptor `fd`, so the descriptor itself
+/// stays open once the file is closed: it might be our stdout.
+#[cfg(unix)]
+fn duplicate(fd: std::os::unix::io::RawFd) -> io::Result<File> {
+    use std::os::unix::io::FromRawFd;
+
+    // SAFETY: `fcntl` fails on a descriptor that isn't open, and the copy is
+    // a new descriptor nothing else owns.
+    let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if copy == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(copy) })
+}
+
+#[cfg(not(unix))]
+fn duplicate(_fd: i32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file descriptors are only supported on Unix",
+    ))
+}
+
+#[test]
+fn serializes_events() {
+    let event = BuildEvent::Compiling {
+        name: "serde",
+        version: "1.0.200",
+        verb: "Compiling",
+    };
+    assert_eq!(
+        serde_json::to_string(&Record {
+            event: &event,
+            ts: 12
+        })
+        .unwrap(),
+        r#"{"event":"compiling","crate":"serde","version":"1.0.200","verb":"Compiling","ts":12}"#
+    );
+    let event = BuildEvent::BuildFinished {
+        success: false,
+        duration: 1.5,
+        crates: 3,
+        first_error: Some(0.75),
+    };
+    assert_eq!(
+        serde_json::to_string(&Record {
+            event: &event,
+            ts: 12
+        })
+        .unwrap(),
+        r#"{"event":"build_finished","success":false,"duration":1.5,"crates":3,"first_error":0.75,"ts":12}"#
+    );
+    let event = BuildEvent::BuildFinished {
+        success: true,
+        duration: 1.5,
+        crates: 3,
+        first_error: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&Record {
+            event: &event,
+            ts: 12
+        })
+        .unwrap(),
+        r#"{"event":"build_finished","success":true,"duration":1.5,"crates":3,"ts":12}"#
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn opens_targets() {
+    use std::os::unix::io::AsRawFd;
+
+    let dir = std::env::temp_dir().join(format!("cargo-single-line-events-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // The sink writes to a copy of the descriptor, which stays open.
+    let mut file = File::create(dir.join("events")).unwrap();
+    let mut sink = EventSink::open(&format!("fd:{}", file.as_raw_fd())).unwrap();
+    sink.counts(1, 2);
+    drop(sink);
+    file.write_all(b"still open\n").unwrap();
+    // Only the `fd:` prefix makes a descriptor, digits are a file name.
+    let mut sink = EventSink::open(dir.join("3").to_str().unwrap()).unwrap();
+    sink.counts(0, 1);
+    drop(sink);
+    let events = std::fs::read_to_string(dir.join("events")).unwrap();
+    let named = std::fs::read_to_string(dir.join("3")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(events.starts_with(r#"{"event":"warning_count","errors":1,"warnings":2,"#));
+    assert!(events.ends_with("}\nstill open\n"));
+    assert!(named.starts_with(r#"{"event":"warning_count","errors":0,"warnings":1,"#));
+    assert!(EventSink::open("fd:three").is_err());
+    assert!(EventSink::open("fd:987654").is_err());
+}
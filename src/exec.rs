@@ -0,0 +1,62 @@
+//! The `exec` subcommand: the status line for any command, like `ninja` or
+//! `make`, instead of cargo. None of the cargo arguments handling applies, the
+//! lines matching the `--collapse-pattern`s (and cargo's own, unless
+//! `--no-default-prefixes` is given) go to the status line.
+//!
+//! The stdout of the command is only shown on stderr along with the rest when
+//! it's a terminal, otherwise it's left alone: `exec tool > out` gets the
+//! output of the tool into the file.
+//!
+//! Like with cargo, the command learns it's run by the plugin, so that a
+//! nested `cargo single-line` (or `exec`) stays out of the way, and if this
+//! one is nested itself, the command is run as is.
+
+use std::{
+    ffi::OsString,
+    io::{self, BufReader},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    child_env,
+    cli::exit_code,
+    filter::LineFilter,
+    options::Options,
+    recursion::{Invocation, MAX_DEPTH},
+    signals,
+};
+
+/// Runs the command, its stderr (and the stdout, if it's a terminal) shown on
+/// stderr through the `filter`. Returns the exit code of the command.
+pub fn run(options: &Options, filter: &LineFilter, args: &[OsString]) -> io::Result<i32> {
+    let invocation = Invocation::detect(args);
+    if invocation.depth >= MAX_DEPTH {
+        return Err(io::Error::other(format!(
+            "invoked recursively {} times, giving up",
+            invocation.depth
+        )));
+    }
+    let (program, command_args) = args
+        .split_first()
+        .ok_or_else(|| io::Error::other("a command to run is expected"))?;
+    let mut cmd = Command::new(program);
+    cmd.args(command_args);
+    child_env::apply(&mut cmd, options.clean_env, &options.env);
+    invocation.mark(&mut cmd, args);
+    if invocation.is_nested() {
+        return Ok(exit_code(cmd.status()?));
+    }
+    // A single pipe keeps the order of the lines written to both streams.
+    let (reader, writer) = io::pipe()?;
+    if atty::is(atty::Stream::Stdout) {
+        cmd.stdout(writer.try_clone()?);
+    }
+    cmd.stdin(Stdio::inherit()).stderr(writer);
+    signals::install();
+    let mut child = cmd.spawn()?;
+    signals::forward_to(child.id(), false);
+    // Otherwise the pipe would stay open after the child exits.
+    drop(cmd);
+    filter.process_stream(BufReader::new(reader), io::stderr().lock())?;
+    Ok(exit_code(child.wait()?))
+}
@@ -0,0 +1,430 @@
+//! Which lines of cargo's output go to the status line, and the plain
+//! rendering of them for the library users.
+//!
+//! The plugin itself draws the status line with all the bells and whistles
+//! (the progress, the window, the spinner), while [`LineFilter::process_stream`]
+//! only overwrites a single line in place, which is enough for whatever runs
+//! cargo on its own.
+
+use std::io::{self, BufRead, Write};
+
+use regex::Regex;
+
+use crate::{
+    cargo_args::CargoArgs,
+    lines::{Segment, Segments},
+    starts_with, unit,
+};
+
+/// The cargo's own lines put on the status line besides the compilation units.
+const PREFIXES: &[&str] = &[
+    "Updating",
+    "Downloading",
+    "Downloaded",
+    "Blocking",
+    "Adding",
+    "Removing",
+    "Upgrading",
+    "Downgrading",
+];
+
+/// The lines particular to a subcommand, put on the status line on top of the
+/// cargo's usual ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SubcommandRules {
+    /// The lines like the compilation units, only collapsed with the full
+    /// compaction.
+    units: &'static [&'static str],
+    /// The rest, collapsed along with the download and the lock file chatter.
+    others: &'static [&'static str],
+}
+
+impl SubcommandRules {
+    fn for_subcommand(subcommand: &str) -> Self {
+        match subcommand {
+            "doc" | "d" | "rustdoc" => SubcommandRules {
+                units: &["Documenting", "Scraping"],
+                others: &[],
+            },
+            // `cargo clippy --fix` and `cargo fix`, the remaining warnings are
+            // what matters.
+            "clippy" | "fix" => SubcommandRules {
+                units: &[],
+                others: &["Fixed", "Migrating"],
+            },
+            // `cargo fmt -- -v`, the diffs of `--check` are printed as is. Rustfmt
+            // prints to stdout, so it only applies with `--filter-stdout`.
+            "fmt" => SubcommandRules {
+                units: &[],
+                others: &["Formatting", "Spent"],
+            },
+            // Criterion's progress, the results are printed as is. Like the
+            // rest of the benchmark output it goes to stdout, so it only
+            // applies with `--filter-stdout`.
+            "bench" => SubcommandRules {
+                units: &[],
+                others: &["Benchmarking"],
+            },
+            _ => SubcommandRules::default(),
+        }
+    }
+}
+
+/// How much of the cargo output is compacted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compaction {
+    /// Everything we know of is put on the status line.
+    Full,
+    /// Only the index/download/lock file chatter is put on the status line,
+    /// while the compilation progress is displayed as is. With `-v` cargo
+    /// prints `Running rustc ...` after each `Compiling ...`, which doesn't make
+    /// much sense without the latter.
+    NonUnits,
+    /// Nothing is compacted.
+    None,
+}
+
+impl Compaction {
+    /// Picks the compaction level matching cargo's own verbosity.
+    pub(crate) fn for_cargo_args(cargo_args: &CargoArgs) -> Self {
+        if cargo_args.quiet {
+            // Cargo only prints warnings and errors, nothing to compact.
+            Compaction::None
+        } else {
+            match cargo_args.verbosity {
+                0 => Compaction::Full,
+                1 => Compaction::NonUnits,
+                _ => Compaction::None,
+            }
+        }
+    }
+}
+
+/// A user-defined kind of lines to put on the status line, like the chatter of
+/// a custom build script.
+#[derive(Debug, Clone)]
+pub struct CaptureRule {
+    pattern: Regex,
+}
+
+impl CaptureRule {
+    /// Captures the lines matching the regular expression. The lines are
+    /// matched without the color codes and the trailing newline.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(CaptureRule {
+            pattern: Regex::new(pattern)?,
+        })
+    }
+
+    /// Whether the line is to be captured.
+    pub fn matches(&self, line: &str) -> bool {
+        let line = crate::strip_colors(line);
+        self.pattern.is_match(line.trim_end())
+    }
+}
+
+/// Tells the lines to put on the status line from the ones to print as is.
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+    compaction: Compaction,
+    /// Whether the cargo's lines (`Compiling`, `Updating` and the like) are
+    /// captured.
+    default_prefixes: bool,
+    /// The cargo's lines of the subcommand being run.
+    subcommand: SubcommandRules,
+    rules: Vec<CaptureRule>,
+}
+
+impl LineFilter {
+    /// Captures the cargo's own lines, fully compacted.
+    pub fn new() -> Self {
+        LineFilter {
+            compaction: Compaction::Full,
+            default_prefixes: true,
+            subcommand: SubcommandRules::default(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Sets how much of the output is compacted, which is supposed to match
+    /// cargo's verbosity.
+    pub fn with_compaction(mut self, compaction: Compaction) -> Self {
+        self.compaction = compaction;
+        self
+    }
+
+    /// Turns the capturing of the cargo's own lines on or off.
+    pub fn with_default_prefixes(mut self, default_prefixes: bool) -> Self {
+        self.default_prefixes = default_prefixes;
+        self
+    }
+
+    /// Captures the lines particular to the cargo subcommand as well, like
+    /// `Documenting` of `cargo doc`. They are a part of the cargo's own lines,
+    /// see [`with_default_prefixes`](Self::with_default_prefixes).
+    pub fn with_subcommand(mut self, subcommand: Option<&str>) -> Self {
+        self.subcommand =
+            subcommand.map_or_else(SubcommandRules::default, SubcommandRules::for_subcommand);
+        self
+    }
+
+    /// Captures the lines matching the rule as well.
+    pub fn with_rule(mut self, rule: CaptureRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn compaction(&self) -> Compaction {
+        self.compaction
+    }
+
+    /// Whether the line goes to the status line.
+    pub fn is_status(&self, line: &str) -> bool {
+        let matches_rules = || self.rules.iter().any(|rule| rule.matches(line));
+        match self.compaction {
+            Compaction::Full => {
+                (self.default_prefixes
+                    && (starts_with(line, unit::UNIT_PREFIXES)
+                        || starts_with(line, self.subcommand.units)
+                        || starts_with(line, PREFIXES)
+                        || starts_with(line, self.subcommand.others)))
+                    || matches_rules()
+            }
+            Compaction::NonUnits => {
+                (self.default_prefixes
+                    && (starts_with(line, PREFIXES) || starts_with(line, self.subcommand.others)))
+                    || matches_rules()
+            }
+            Compaction::None => false,
+        }
+    }
+
+    /// Copies cargo's output from the `reader` to the `writer`, the status
+    /// lines (and the progress bars redrawn with a carriage return)
+    /// overwriting each other and the rest printed as is, with the status
+    /// drawn again below. What's left of the previous status is erased with
+    /// the `ESC [K` sequence. The status is erased once the build is `Finished`,
+    /// and is otherwise left in place at the end.
+    pub fn process_stream(&self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+        let mut status = String::new();
+        let mut reader = Segments::new(reader);
+        while let Some(segment) = reader.read()? {
+            let (line, update) = match segment {
+                Segment::Line(line) => (line, false),
+                // An erased update is replaced by whatever comes next anyway.
+                Segment::Update(update) if crate::strip_colors(&update.text).trim().is_empty() => {
+                    continue
+                }
+                Segment::Update(update) => (update, true),
+            };
+            if update || self.is_status(&line.text) {
+                status.clear();
+                status.push_str(line.text.trim_end());
+                // Erases what's left of the previous status, like the plugin
+                // does.
+                write!(writer, "{}\x1b[K\r", status)?;
+            } else {
+                if !status.is_empty() {
+                    writer.write_all(b"\x1b[K")?;
+                }
+                // Whatever the encoding, the line is left as it is.
+                writer.write_all(line.bytes())?;
+                if !line.text.ends_with('\n') {
+                    writeln!(writer)?;
+                }
+                if starts_with(&line.text, &["Finished"]) {
+                    status.clear();
+                } else if !status.is_empty() {
+                    write!(writer, "{}\r", status)?;
+                }
+            }
+            writer.flush()?;
+        }
+        if !status.is_empty() {
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+impl Default for LineFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copies cargo's output from the `reader` to the `writer` with the default
+/// [`LineFilter`].
+pub fn process_stream(reader: impl BufRead, writer: impl Write) -> io::Result<()> {
+    LineFilter::new().process_stream(reader, writer)
+}
+
+/// What a terminal would display after the output, the overwritten (and the
+/// erased) parts dropped.
+#[cfg(test)]
+fn screen(output: &str) -> Vec<String> {
+    output
+        .split_terminator('\n')
+        .map(|row| {
+            let mut cells: Vec<char> = Vec::new();
+            for segment in row.split('\r') {
+                let mut column = 0;
+                for (index, part) in segment.split("\x1b[K").enumerate() {
+                    if index > 0 {
+                        cells.truncate(column);
+                    }
+                    for c in part.chars() {
+                        match cells.get_mut(column) {
+                            Some(cell) => *cell = c,
+                            None => cells.push(c),
+                        }
+                        column += 1;
+                    }
+                }
+            }
+            cells.into_iter().collect::<String>().trim_end().to_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn compaction_follows_verbosity() {
+    let line = "   Compiling foo v0.1.0\n";
+    let download = "  Downloaded foo v0.1.0\n";
+    let filter = LineFilter::new();
+    assert!(filter.is_status(line));
+    assert!(filter.is_status(download));
+    let filter = LineFilter::new().with_compaction(Compaction::NonUnits);
+    assert!(!filter.is_status(line));
+    assert!(filter.is_status(download));
+    let filter = LineFilter::new().with_compaction(Compaction::None);
+    assert!(!filter.is_status(download));
+}
+
+#[test]
+fn captures_user_patterns() {
+    let line = "   Compiling foo v0.1.0\n";
+    let generating = "  Generating bindings.rs\n";
+    let filter = LineFilter::new()
+        .with_default_prefixes(false)
+        .with_rule(CaptureRule::new("^Generating ").unwrap());
+    assert!(!filter.is_status(line));
+    assert!(!filter.is_status(generating));
+    let filter = LineFilter::new()
+        .with_default_prefixes(false)
+        .with_rule(CaptureRule::new(r"^\s*Generating ").unwrap());
+    assert!(filter.is_status(generating));
+    assert!(filter
+        .clone()
+        .with_compaction(Compaction::NonUnits)
+        .is_status(generating));
+    assert!(!filter
+        .with_compaction(Compaction::None)
+        .is_status(generating));
+    assert!(CaptureRule::new(r"^\s*Generating \S+$")
+        .unwrap()
+        .matches("\x1b[1m  Generating\x1b[0m bindings.rs\n"));
+}
+
+#[test]
+fn processes_recorded_build() {
+    let input = include_str!("../tests/fixtures/build.txt");
+    let mut output = Vec::new();
+    process_stream(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("    Updating crates.io index\x1b[K\r"));
+    assert!(output.contains("\r   Compiling foo v0.1.0 (/tmp/foo)\x1b[K\r"));
+    let kept: Vec<&str> = input.lines().skip(7).collect();
+    assert_eq!(screen(&output), kept);
+}
+
+/// Processes a recorded output of the subcommand, returning the screen.
+#[cfg(test)]
+fn process_recorded(subcommand: &str, input: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    LineFilter::new()
+        .with_subcommand(Some(subcommand))
+        .process_stream(input.as_bytes(), &mut output)
+        .unwrap();
+    screen(&String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn processes_recorded_subcommands() {
+    let input = include_str!("../tests/fixtures/doc.txt");
+    let kept: Vec<&str> = input.lines().skip(4).collect();
+    assert_eq!(process_recorded("doc", input), kept);
+
+    let input = include_str!("../tests/fixtures/clippy.txt");
+    let kept: Vec<&str> = input.lines().skip(3).collect();
+    assert_eq!(process_recorded("clippy", input), kept);
+
+    // Rustfmt and Criterion print to stdout, these are what `--filter-stdout`
+    // reads.
+    let input = include_str!("../tests/fixtures/fmt.txt");
+    let lines: Vec<&str> = input.lines().collect();
+    assert_eq!(
+        process_recorded("fmt", input),
+        [&lines[4..], &lines[3..4]].concat()
+    );
+
+    let input = include_str!("../tests/fixtures/bench.txt");
+    let lines: Vec<&str> = input.lines().collect();
+    assert_eq!(
+        process_recorded("bench", input),
+        [&lines[..2], &lines[6..], &lines[5..6]].concat()
+    );
+    // The other subcommands leave them alone.
+    assert!(!LineFilter::new().is_status(lines[2]));
+}
+
+#[test]
+fn passes_invalid_utf8_through() {
+    let input = include_bytes!("../tests/fixtures/invalid-utf8.txt");
+    let mut output = Vec::new();
+    process_stream(&input[..], &mut output).unwrap();
+    // The lines printed as is are the very same bytes.
+    let lines: Vec<&[u8]> = input.split_inclusive(|&b| b == b'\n').collect();
+    for line in &lines[3..] {
+        assert!(output.windows(line.len()).any(|window| window == *line));
+    }
+    assert_eq!(
+        screen(&String::from_utf8_lossy(&output))[..2],
+        [
+            "warning: foo@0.1.0: Zeichens\u{fffd}tze f\u{fffd}r die Konsole",
+            "error: linking with `link.exe` failed: exit code: 1181",
+        ]
+    );
+}
+
+#[test]
+fn leaves_status_at_the_end() {
+    let input = "   Compiling foo v0.1.0\nerror: could not compile `foo`\n";
+    let mut output = Vec::new();
+    process_stream(input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+        screen(&String::from_utf8(output).unwrap()),
+        ["error: could not compile `foo`", "   Compiling foo v0.1.0"]
+    );
+}
+
+#[test]
+fn redraws_progress_bars() {
+    let input = "   Building [=> ] 1/2: foo\r   Building [==>] 2/2: bar\rwarning: x\n";
+    let mut output = Vec::new();
+    process_stream(input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+        screen(&String::from_utf8(output).unwrap()),
+        ["warning: x", "   Building [==>] 2/2: bar"]
+    );
+}
+
+#[test]
+fn erases_longer_status() {
+    let input = "   Compiling serde_derive v1.0.200\n   Compiling foo v0.1.0\nwarning: x\n";
+    let mut output = Vec::new();
+    process_stream(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(!output.contains("  \r"));
+    assert_eq!(screen(&output), ["warning: x", "   Compiling foo v0.1.0"]);
+}
@@ -0,0 +1,208 @@
+//! `--compact-diagnostics`: a warning or an error is printed as a one-liner
+//! while building, its first line along with the location and the count of the
+//! lines left out:
+//!
+//! ```text
+//! warning: unused variable: `x` --> src/main.rs:2:9 (+6 lines)
+//! ```
+//!
+//! The diagnostics are printed in full after the build, or right away with the
+//! `d` key.
+
+use crate::{diagnostics::Level, l10n};
+
+/// A diagnostic, as printed by the compiler.
+#[derive(Debug)]
+struct Block {
+    level: Level,
+    lines: Vec<String>,
+}
+
+impl Block {
+    /// The first line of the block, followed by the location and the count of
+    /// the other lines (the empty one ending the block aside).
+    fn one_liner(&self, colored: bool) -> String {
+        let dim = |text: &str| {
+            if colored {
+                format!("\x1b[2m{}\x1b[0m", text)
+            } else {
+                text.to_owned()
+            }
+        };
+        let header = self.lines[0].trim_end();
+        let rest = &self.lines[1..];
+        let hidden = rest
+            .iter()
+            .filter(|line| !crate::strip_colors(line).trim().is_empty())
+            .count();
+        if hidden == 0 {
+            return format!("{}\n", header);
+        }
+        let location = rest.iter().find_map(|line| {
+            let line = crate::strip_colors(line);
+            let line = line.trim();
+            line.starts_with("--> ").then(|| line.to_owned())
+        });
+        let mut one_liner = header.to_owned();
+        if let Some(location) = location {
+            one_liner.push(' ');
+            one_liner.push_str(&dim(&location));
+        }
+        one_liner.push(' ');
+        one_liner.push_str(&dim(&l10n::text("folded-lines", &[("count", &hidden)])));
+        one_liner.push('\n');
+        one_liner
+    }
+
+    fn full(&self) -> String {
+        let mut full = String::new();
+        for line in &self.lines {
+            full.push_str(line);
+            if !line.ends_with('\n') {
+                full.push('\n');
+            }
+        }
+        full
+    }
+}
+
+/// Folds the diagnostics and keeps them around to print in full later.
+#[derive(Debug)]
+pub struct Folding {
+    colored: bool,
+    /// The diagnostic being printed at the moment.
+    current: Option<Block>,
+    /// The one-liner of the diagnostic finished by the latest line.
+    finished: Option<(Level, String)>,
+    /// The folded diagnostics not printed in full yet.
+    folded: Vec<Block>,
+}
+
+impl Folding {
+    pub fn new(colored: bool) -> Self {
+        Folding {
+            colored,
+            current: None,
+            finished: None,
+            folded: Vec::new(),
+        }
+    }
+
+    /// Takes a line already [observed](crate::diagnostics::Diagnostics::observe),
+    /// given the level of the diagnostic it starts, if any, and whether it
+    /// [continues](crate::diagnostics::Diagnostics::continues) one. Returns
+    /// `true` if the line is folded, and is not to be printed.
+    pub fn observe(&mut self, line: &str, starts: Option<Level>, continues: bool) -> bool {
+        if let Some(level) = starts {
+            self.finish();
+            self.current = Some(Block {
+                level,
+                lines: vec![line.to_owned()],
+            });
+            return true;
+        }
+        let Some(current) = self.current.as_mut().filter(|_| continues) else {
+            self.finish();
+            return false;
+        };
+        current.lines.push(line.to_owned());
+        if crate::strip_colors(line).trim().is_empty() {
+            self.finish();
+        }
+        true
+    }
+
+    /// Folds a diagnostic rendered as a whole, like the one of a JSON message.
+    pub fn fold(&mut self, rendered: &str, level: Level) -> String {
+        self.finish();
+        let block = Block {
+            level,
+            lines: rendered.split_inclusive('\n').map(str::to_owned).collect(),
+        };
+        let one_liner = block.one_liner(self.colored);
+        self.folded.push(block);
+        one_liner
+    }
+
+    /// The one-liner of the diagnostic the latest line has finished, along
+    /// with its level.
+    pub fn finished(&mut self) -> Option<(Level, String)> {
+        self.finished.take()
+    }
+
+    /// Finishes the diagnostic that is still being printed, once the output is
+    /// over. Returns its one-liner.
+    pub fn end(&mut self) -> Option<(Level, String)> {
+        self.finish();
+        self.finished()
+    }
+
+    fn finish(&mut self) {
+        if let Some(block) = self.current.take() {
+            self.finished = Some((block.level, block.one_liner(self.colored)));
+            self.folded.push(block);
+        }
+    }
+
+    /// The folded diagnostics of the `shown` levels in full, the ones not
+    /// printed this way yet.
+    pub fn replay(&mut self, shown: impl Fn(Level) -> bool) -> Option<String> {
+        let full: String = self
+            .folded
+            .drain(..)
+            .filter(|block| shown(block.level))
+            .map(|block| block.full())
+            .collect();
+        (!full.is_empty()).then_some(full)
+    }
+}
+
+#[test]
+fn folds_diagnostics() {
+    let output = "\
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 5;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error: aborting due to 1 previous error
+warning: `foo` (bin \"foo\") generated 1 warning
+";
+    let mut diagnostics = crate::diagnostics::Diagnostics::new();
+    let mut folding = Folding::new(false);
+    let mut printed = String::new();
+    for line in output.split_inclusive('\n') {
+        let starts = diagnostics.observe(line);
+        let folded = folding.observe(line, starts, diagnostics.continues());
+        if let Some((_, one_liner)) = folding.finished() {
+            printed.push_str(&one_liner);
+        }
+        if !folded {
+            printed.push_str(line);
+        }
+    }
+    assert_eq!(folding.end(), None);
+    assert_eq!(
+        printed,
+        "warning: unused variable: `x` --> src/main.rs:2:9 (+6 lines)
+error: aborting due to 1 previous error
+warning: `foo` (bin \"foo\") generated 1 warning
+"
+    );
+    assert_eq!(
+        folding.replay(|level| level == Level::Warning).unwrap(),
+        output.split("error:").next().unwrap()
+    );
+    assert_eq!(folding.replay(|_| true), None);
+
+    assert_eq!(
+        folding.fold(
+            "\x1b[33mwarning\x1b[0m: unused import\n --> src/lib.rs:1:5\n  |\n",
+            Level::Warning
+        ),
+        "\x1b[33mwarning\x1b[0m: unused import --> src/lib.rs:1:5 (+2 lines)\n"
+    );
+}
@@ -0,0 +1,79 @@
+//! Human-readable formatting helpers.
+
+use std::time::Duration;
+
+/// Formats a duration like `8.2s` or `1m 12s`.
+pub fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        let seconds = duration.as_secs();
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// Formats a duration roughly, like `22s` or `1m 12s`.
+pub fn approximate(duration: Duration) -> String {
+    if duration.as_secs() < 60 {
+        format!("{}s", duration.as_secs_f64().round())
+    } else {
+        self::duration(duration)
+    }
+}
+
+/// Formats a number with the thousands separated, like `4,812`.
+pub fn count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats a size in bytes, like `37.2 MiB`.
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[test]
+fn formats_durations() {
+    assert_eq!(duration(Duration::from_millis(8240)), "8.2s");
+    assert_eq!(duration(Duration::from_secs(72)), "1m 12s");
+    assert_eq!(duration(Duration::from_secs(3605)), "60m 05s");
+}
+
+#[test]
+fn formats_approximate_durations() {
+    assert_eq!(approximate(Duration::from_millis(21_600)), "22s");
+    assert_eq!(approximate(Duration::from_secs(72)), "1m 12s");
+}
+
+#[test]
+fn formats_counts() {
+    assert_eq!(count(0), "0");
+    assert_eq!(count(812), "812");
+    assert_eq!(count(4812), "4,812");
+    assert_eq!(count(1_234_567), "1,234,567");
+}
+
+#[test]
+fn formats_sizes() {
+    assert_eq!(size(512), "512 B");
+    assert_eq!(size(1536), "1.5 KiB");
+    assert_eq!(size(39_007_027), "37.2 MiB");
+}
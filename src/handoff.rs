@@ -0,0 +1,109 @@
+//! What's left of the build output once `cargo run` hands the terminal over to
+//! the program.
+
+/// What to do with the `Finished`/`Running` lines of `cargo run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    /// Print them as cargo does.
+    Keep,
+    /// Erase them, so the program starts on a clean line.
+    Clear,
+    /// Collapse them into a single dim line.
+    Collapse,
+}
+
+impl RunStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keep" => Some(RunStatus::Keep),
+            "clear" => Some(RunStatus::Clear),
+            "collapse" => Some(RunStatus::Collapse),
+            _ => None,
+        }
+    }
+}
+
+/// How a line should be displayed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Display {
+    /// On the status line, to be replaced later.
+    Status(String),
+    /// As the final status line.
+    Final(String),
+    /// Not at all, and the status line is to be erased.
+    Clear,
+}
+
+/// Tracks the `Finished`/`Running` lines of `cargo run`.
+#[derive(Debug)]
+pub struct Handoff {
+    mode: RunStatus,
+    colored: bool,
+    /// The `Finished` line, held until the program starts.
+    finished: Option<String>,
+}
+
+impl Handoff {
+    pub fn new(mode: RunStatus, colored: bool) -> Self {
+        Handoff {
+            mode,
+            colored,
+            finished: None,
+        }
+    }
+
+    /// Decides how to display the line, `None` meaning "as usual".
+    pub fn display(&mut self, line: &str) -> Option<Display> {
+        if self.mode == RunStatus::Keep {
+            return None;
+        }
+        let plain = crate::strip_colors(line);
+        let plain = plain.trim();
+        if plain.starts_with("Finished") {
+            self.finished = Some(plain.to_owned());
+            return Some(Display::Status(line.to_owned()));
+        }
+        let program = plain.strip_prefix("Running ")?;
+        let finished = self.finished.take()?;
+        match self.mode {
+            RunStatus::Keep => None,
+            RunStatus::Clear => Some(Display::Clear),
+            RunStatus::Collapse => {
+                let line = format!("{}, running {}", finished, program);
+                Some(Display::Final(if self.colored {
+                    format!("\x1b[2m{}\x1b[0m", line)
+                } else {
+                    line
+                }))
+            }
+        }
+    }
+}
+
+#[test]
+fn collapses_handoff() {
+    let mut handoff = Handoff::new(RunStatus::Collapse, false);
+    assert_eq!(handoff.display("   Compiling foo v0.1.0\n"), None);
+    assert_eq!(
+        handoff.display("    Finished `dev` profile in 0.5s\n"),
+        Some(Display::Status(
+            "    Finished `dev` profile in 0.5s\n".into()
+        ))
+    );
+    assert_eq!(
+        handoff.display("     Running `target/debug/foo`\n"),
+        Some(Display::Final(
+            "Finished `dev` profile in 0.5s, running `target/debug/foo`".into()
+        ))
+    );
+
+    let mut handoff = Handoff::new(RunStatus::Clear, false);
+    let _ = handoff.display("    Finished `dev` profile in 0.5s\n");
+    assert_eq!(
+        handoff.display("     Running `target/debug/foo`\n"),
+        Some(Display::Clear)
+    );
+
+    let mut handoff = Handoff::new(RunStatus::Keep, false);
+    assert_eq!(handoff.display("    Finished `dev` profile\n"), None);
+}
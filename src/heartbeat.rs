@@ -0,0 +1,58 @@
+//! The heartbeat of the non-interactive mode: when stderr is not a terminal
+//! (a CI log, a file), the status line can't be overwritten in place, so the
+//! status is printed as a line of its own every so often instead. That also
+//! keeps the CIs that kill the silent jobs happy.
+
+use std::time::{Duration, Instant};
+
+/// Default interval between the heartbeats.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default amount of the crates compiled between the heartbeats.
+pub const DEFAULT_CRATES: usize = 25;
+
+/// Decides when the status is to be printed.
+#[derive(Debug)]
+pub struct Heartbeat {
+    interval: Duration,
+    crates: usize,
+    /// When the previous heartbeat was printed (or the build started).
+    beaten_at: Instant,
+    /// How many crates were compiled by then.
+    done: usize,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration, crates: usize, now: Instant) -> Self {
+        Heartbeat {
+            interval,
+            crates,
+            beaten_at: now,
+            done: 0,
+        }
+    }
+
+    /// Whether it's time for a heartbeat, `done` crates being compiled so
+    /// far. If it is, the next one is due in another interval or another
+    /// batch of crates.
+    pub fn is_due(&mut self, now: Instant, done: usize) -> bool {
+        let due = now.saturating_duration_since(self.beaten_at) >= self.interval
+            || done >= self.done + self.crates;
+        if due {
+            self.beaten_at = now;
+            self.done = done;
+        }
+        due
+    }
+}
+
+#[test]
+fn beats_on_time_and_crates() {
+    let now = Instant::now();
+    let mut heartbeat = Heartbeat::new(Duration::from_secs(30), 10, now);
+    assert!(!heartbeat.is_due(now + Duration::from_secs(10), 9));
+    assert!(heartbeat.is_due(now + Duration::from_secs(11), 10));
+    assert!(!heartbeat.is_due(now + Duration::from_secs(40), 19));
+    assert!(heartbeat.is_due(now + Duration::from_secs(41), 19));
+    assert!(!heartbeat.is_due(now + Duration::from_secs(42), 28));
+}
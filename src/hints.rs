@@ -0,0 +1,215 @@
+//! Hints about the crate compilation times: how long a crate typically takes
+//! to compile according to the builds history, and whether it's known to be
+//! slow.
+
+use std::{borrow::Cow, collections::HashMap, time::Duration};
+
+use crate::{format, history::Build, timings::CrateTiming, unit::Unit};
+
+/// How many of the latest compilations of a crate are taken into account.
+const RECENT: usize = 5;
+
+/// Crates compiling faster than this don't get a hint, it would only be noise.
+const MIN_DURATION: Duration = Duration::from_secs(2);
+
+/// Crates that are notoriously slow to compile: heavy proc-macros, huge API
+/// bindings and the `-sys` crates building C/C++ code.
+const KNOWN_SLOW: &[&str] = &[
+    "syn",
+    "serde_derive",
+    "tokio",
+    "openssl-sys",
+    "ring",
+    "aws-lc-sys",
+    "libsqlite3-sys",
+    "librocksdb-sys",
+    "zstd-sys",
+    "bindgen",
+    "windows",
+    "web-sys",
+    "regex-automata",
+    "cranelift-codegen",
+    "diesel",
+];
+
+/// Crates that typically take at least this long are considered slow too.
+const SLOW_DURATION: Duration = Duration::from_secs(10);
+
+/// Marks the slow crates on the status line.
+const SLOW_MARK: &str = "🐢";
+
+/// Typical compilation times of the crates.
+#[derive(Debug, Default)]
+pub struct Hints {
+    typical: HashMap<String, Duration>,
+}
+
+impl Hints {
+    /// Collects the typical compilation times from the `builds` of the same
+    /// `command`, as `check` is much faster than `build`.
+    pub fn from_builds(builds: &[Build], command: &str) -> Self {
+        let mut durations: HashMap<&str, Vec<f64>> = HashMap::new();
+        for build in builds.iter().filter(|build| build.command == command) {
+            for record in &build.crates {
+                durations
+                    .entry(&record.name)
+                    .or_default()
+                    .push(record.duration);
+            }
+        }
+        let typical = durations
+            .into_iter()
+            .map(|(name, durations)| {
+                let mut recent = durations[durations.len().saturating_sub(RECENT)..].to_vec();
+                recent.sort_by(f64::total_cmp);
+                let median = recent[recent.len() / 2];
+                (name.to_owned(), Duration::from_secs_f64(median))
+            })
+            .collect();
+        Hints { typical }
+    }
+
+    /// Whether the crate is known to be slow to compile, either from the
+    /// built-in list or from the history.
+    pub fn is_slow(&self, name: &str) -> bool {
+        KNOWN_SLOW.contains(&name)
+            || self
+                .typical
+                .get(name)
+                .is_some_and(|typical| *typical >= SLOW_DURATION)
+    }
+
+    /// Marks a `Compiling ...` line of a slow crate and appends the typical
+    /// compilation time to it.
+    pub fn annotate<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let unit = match Unit::parse(line) {
+            Some(unit) => unit,
+            None => return Cow::Borrowed(line),
+        };
+        let typical = self
+            .typical
+            .get(&unit.name)
+            .filter(|typical| **typical >= MIN_DURATION);
+        if typical.is_none() && !self.is_slow(&unit.name) {
+            return Cow::Borrowed(line);
+        }
+        let mut annotated = if self.is_slow(&unit.name) {
+            // The mark is two columns wide, so it takes the place of cargo's
+            // padding to keep the verbs aligned.
+            let line = line.trim_end();
+            if line.contains("   ") {
+                line.replacen("   ", &format!("{} ", SLOW_MARK), 1)
+            } else {
+                format!("{} {}", SLOW_MARK, line)
+            }
+        } else {
+            line.trim_end().to_owned()
+        };
+        if let Some(typical) = typical {
+            let typical = format::approximate(*typical);
+            annotated.push(' ');
+            annotated.push_str(&crate::l10n::text("usually", &[("duration", &typical)]));
+        }
+        Cow::Owned(annotated)
+    }
+
+    /// Sums up how much of the build the slow crates took, if any.
+    pub fn slow_summary(&self, timings: &[CrateTiming], total: Duration) -> Option<String> {
+        let mut names: Vec<&str> = Vec::new();
+        let mut spent = Duration::ZERO;
+        for timing in timings.iter().filter(|timing| self.is_slow(&timing.name)) {
+            if !names.contains(&timing.name.as_str()) {
+                names.push(&timing.name);
+            }
+            spent += timing.duration;
+        }
+        if names.is_empty() {
+            return None;
+        }
+        let percent = spent.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON) * 100.0;
+        Some(crate::l10n::text(
+            "slow-crates",
+            &[
+                ("duration", &format::duration(spent)),
+                ("percent", &format!("{:.0}", percent)),
+                ("crates", &names.join(", ")),
+            ],
+        ))
+    }
+}
+
+#[test]
+fn annotates_slow_crates() {
+    use crate::history::CrateRecord;
+
+    let build = |command: &str, crates: &[(&str, f64)]| Build {
+        timestamp: 0,
+        command: command.into(),
+        duration: 0.0,
+        success: true,
+        crates: crates
+            .iter()
+            .map(|&(name, duration)| CrateRecord {
+                name: name.into(),
+                version: "1.0.0".into(),
+                duration,
+            })
+            .collect(),
+        first_error: None,
+        compiled: None,
+        warnings: None,
+    };
+    let builds = [
+        build("build", &[("tokio", 20.0), ("itoa", 0.3), ("app", 4.0)]),
+        build("build", &[("tokio", 40.0), ("app", 3.0)]),
+        build("build", &[("tokio", 22.0), ("app", 5.0)]),
+        build("check", &[("tokio", 5.0), ("serde", 3.0)]),
+    ];
+    let hints = Hints::from_builds(&builds, "build");
+    assert_eq!(
+        hints.annotate("   Compiling tokio v1.38.0\n"),
+        "🐢 Compiling tokio v1.38.0 (usually ~22s)"
+    );
+    assert_eq!(
+        hints.annotate("   Compiling syn v2.0.48\n"),
+        "🐢 Compiling syn v2.0.48"
+    );
+    assert_eq!(
+        hints.annotate("\x1b[1m\x1b[32m    Checking\x1b[0m syn v2.0.48\n"),
+        "\x1b[1m\x1b[32m🐢  Checking\x1b[0m syn v2.0.48"
+    );
+    assert_eq!(
+        hints.annotate("   Compiling app v0.1.0 (/tmp/app)\n"),
+        "   Compiling app v0.1.0 (/tmp/app) (usually ~4s)"
+    );
+    assert_eq!(
+        hints.annotate("   Compiling itoa v1.0.0\n"),
+        "   Compiling itoa v1.0.0\n"
+    );
+    assert_eq!(
+        hints.annotate("   Compiling serde v1.0.0\n"),
+        "   Compiling serde v1.0.0\n"
+    );
+}
+
+#[test]
+fn summarizes_slow_crates() {
+    let timing = |name: &str, seconds| CrateTiming {
+        name: name.into(),
+        version: "1.0.0".into(),
+        start: Duration::ZERO,
+        duration: Duration::from_secs(seconds),
+    };
+    let hints = Hints::default();
+    assert_eq!(
+        hints.slow_summary(
+            &[timing("syn", 3), timing("app", 2), timing("tokio", 5)],
+            Duration::from_secs(10)
+        ),
+        Some("slow crates took 8.0s (80% of the build): syn, tokio".into())
+    );
+    assert_eq!(
+        hints.slow_summary(&[timing("app", 2)], Duration::from_secs(2)),
+        None
+    );
+}
@@ -0,0 +1,157 @@
+//! Persistent history of the builds, stored in the project directory under
+//! `.cargo-single-line/history.json`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cargo_args::CargoArgs, lockfile, timings::CrateTiming};
+
+/// Directory (relative to the project root) where the plugin keeps its data.
+pub const DATA_DIR: &str = ".cargo-single-line";
+
+/// How many builds are kept in the history; older ones are dropped.
+const MAX_BUILDS: usize = 200;
+
+/// A single build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Build {
+    /// When the build has finished, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The cargo subcommand, e.g. `build`.
+    pub command: String,
+    /// Duration of the build, in seconds.
+    pub duration: f64,
+    pub success: bool,
+    #[serde(default)]
+    pub crates: Vec<CrateRecord>,
+    /// When the first error was reported, in seconds since the build start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<f64>,
+    /// How many crates were compiled (or checked), if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled: Option<usize>,
+    /// How many warnings were reported, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<usize>,
+}
+
+/// How long a crate took to compile during a build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateRecord {
+    pub name: String,
+    pub version: String,
+    /// In seconds.
+    pub duration: f64,
+}
+
+impl From<&CrateTiming> for CrateRecord {
+    fn from(timing: &CrateTiming) -> Self {
+        CrateRecord {
+            name: timing.name.clone(),
+            version: timing.version.clone(),
+            duration: timing.duration.as_secs_f64(),
+        }
+    }
+}
+
+impl Build {
+    pub fn new(command: String, duration: Duration, success: bool, crates: &[CrateTiming]) -> Self {
+        Build {
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_secs()),
+            command,
+            duration: duration.as_secs_f64(),
+            success,
+            crates: crates.iter().map(CrateRecord::from).collect(),
+            first_error: None,
+            compiled: None,
+            warnings: None,
+        }
+    }
+}
+
+/// The builds history of a project, oldest builds first.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    pub builds: Vec<Build>,
+}
+
+impl History {
+    /// Loads the history stored in the `root` directory of a project. A
+    /// missing history is not an error.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let path = root.join(DATA_DIR).join("history.json");
+        let builds = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(History { path, builds })
+    }
+
+    /// The latest build of the `command`.
+    pub fn previous(&self, command: &str) -> Option<&Build> {
+        self.builds
+            .iter()
+            .rev()
+            .find(|build| build.command == command)
+    }
+
+    /// Appends the build, forgetting the oldest builds if necessary.
+    pub fn push(&mut self, build: Build) {
+        self.builds.push(build);
+        if self.builds.len() > MAX_BUILDS {
+            let excess = self.builds.len() - MAX_BUILDS;
+            self.builds.drain(..excess);
+        }
+    }
+
+    /// Writes the history to the disk.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_vec(&self.builds)?;
+        // Write to a temporary file first so a concurrent build never sees a
+        // half-written history.
+        let temporary = self.path.with_extension("json.tmp");
+        std::fs::write(&temporary, contents)?;
+        std::fs::rename(&temporary, &self.path)
+    }
+}
+
+/// Finds the root directory of the project being built: the directory of the
+/// `Cargo.lock`, or the directory of the manifest if there's no lock file yet.
+pub fn project_root(cargo_args: &CargoArgs) -> Option<PathBuf> {
+    let manifest_dir = cargo_args.manifest_dir()?;
+    match lockfile::find(&manifest_dir) {
+        Some(lockfile) => lockfile.parent().map(Path::to_path_buf),
+        None => Some(manifest_dir),
+    }
+}
+
+#[test]
+fn keeps_limited_history() {
+    let mut history = History {
+        path: PathBuf::from("history.json"),
+        builds: Vec::new(),
+    };
+    for i in 0..MAX_BUILDS + 10 {
+        history.push(Build::new(
+            format!("build{}", i),
+            Duration::from_secs(1),
+            true,
+            &[],
+        ));
+    }
+    assert_eq!(history.builds.len(), MAX_BUILDS);
+    assert_eq!(history.builds[0].command, "build10");
+}
@@ -0,0 +1,131 @@
+//! The commands run once cargo exits: `--on-success`, `--on-failure` and
+//! `--on-warning`, like playing a sound when the build fails or deploying once
+//! it succeeds.
+//!
+//! The commands are run with the shell (`sh -c`, or `cmd /C` on Windows), the
+//! build being described by the environment variables:
+//!
+//! * `CARGO_SINGLE_LINE_SUBCOMMAND`: like `build`;
+//! * `CARGO_SINGLE_LINE_EXIT_CODE`: cargo's exit code;
+//! * `CARGO_SINGLE_LINE_DURATION`: in seconds, like `12.4`;
+//! * `CARGO_SINGLE_LINE_ERRORS` and `CARGO_SINGLE_LINE_WARNINGS`: the counts;
+//! * `CARGO_SINGLE_LINE_FAILED_CRATES`: the crates that didn't compile,
+//!   separated by spaces.
+
+use std::{
+    io,
+    process::{Command, ExitStatus, Stdio},
+    time::Duration,
+};
+
+/// The build that is over.
+#[derive(Debug)]
+pub struct Build<'a> {
+    pub subcommand: &'a str,
+    pub exit_code: i32,
+    pub duration: Duration,
+    pub errors: usize,
+    pub warnings: usize,
+    pub failed_crates: &'a [&'a str],
+}
+
+impl Build<'_> {
+    /// The environment variables describing the build.
+    fn env(&self) -> [(&'static str, String); 6] {
+        [
+            ("CARGO_SINGLE_LINE_SUBCOMMAND", self.subcommand.to_owned()),
+            ("CARGO_SINGLE_LINE_EXIT_CODE", self.exit_code.to_string()),
+            (
+                "CARGO_SINGLE_LINE_DURATION",
+                format!("{:.1}", self.duration.as_secs_f64()),
+            ),
+            ("CARGO_SINGLE_LINE_ERRORS", self.errors.to_string()),
+            ("CARGO_SINGLE_LINE_WARNINGS", self.warnings.to_string()),
+            (
+                "CARGO_SINGLE_LINE_FAILED_CRATES",
+                self.failed_crates.join(" "),
+            ),
+        ]
+    }
+}
+
+/// The hooks to run after the build, the options they're given with along with
+/// the commands.
+pub fn due<'a>(
+    build: &Build,
+    on_success: Option<&'a str>,
+    on_failure: Option<&'a str>,
+    on_warning: Option<&'a str>,
+) -> Vec<(&'static str, &'a str)> {
+    let outcome = if build.exit_code == 0 {
+        ("--on-success", on_success)
+    } else {
+        ("--on-failure", on_failure)
+    };
+    let warning = ("--on-warning", on_warning.filter(|_| build.warnings > 0));
+    [outcome, warning]
+        .into_iter()
+        .filter_map(|(option, command)| Some((option, command?)))
+        .collect()
+}
+
+/// Runs the command with the shell, waiting for it to finish. The output goes
+/// to the terminal as is.
+pub fn run(command: &str, build: &Build) -> io::Result<ExitStatus> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command)
+        .envs(build.env())
+        .stdin(Stdio::null())
+        .status()
+}
+
+#[test]
+fn picks_hooks() {
+    let mut build = Build {
+        subcommand: "build",
+        exit_code: 0,
+        duration: Duration::from_millis(12_400),
+        errors: 0,
+        warnings: 2,
+        failed_crates: &[],
+    };
+    let hooks = |build: &Build| due(build, Some("deploy"), Some("beep"), Some("lint"));
+    assert_eq!(
+        hooks(&build),
+        [("--on-success", "deploy"), ("--on-warning", "lint")]
+    );
+    build.exit_code = 101;
+    build.warnings = 0;
+    assert_eq!(hooks(&build), [("--on-failure", "beep")]);
+    assert_eq!(due(&build, Some("deploy"), None, Some("lint")), []);
+}
+
+#[cfg(unix)]
+#[test]
+fn describes_build() {
+    let build = Build {
+        subcommand: "check",
+        exit_code: 101,
+        duration: Duration::from_millis(12_400),
+        errors: 3,
+        warnings: 1,
+        failed_crates: &["foo", "bar"],
+    };
+    let status = run(
+        "test \"$CARGO_SINGLE_LINE_SUBCOMMAND $CARGO_SINGLE_LINE_EXIT_CODE \
+         $CARGO_SINGLE_LINE_DURATION $CARGO_SINGLE_LINE_ERRORS \
+         $CARGO_SINGLE_LINE_WARNINGS $CARGO_SINGLE_LINE_FAILED_CRATES\" = \
+         'check 101 12.4 3 1 foo bar'",
+        &build,
+    )
+    .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,61 @@
+//! Internal compiler errors.
+//!
+//! When rustc crashes, everything it prints matters for the bug report, so
+//! from the first sign of the crash on nothing is compacted anymore, and the
+//! whole report is kept to be saved to a file after the build.
+
+/// What the report of a crashed compiler starts with.
+const MARKERS: &[&str] = &[
+    "internal compiler error",
+    "query stack during panic",
+    "the compiler unexpectedly panicked",
+    "thread 'rustc' panicked",
+];
+
+/// Collects the report once the compiler has crashed.
+#[derive(Debug, Default)]
+pub struct IceDetector {
+    /// The lines since the crash, if it has happened.
+    report: Option<Vec<String>>,
+}
+
+impl IceDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo, telling whether it's a part of
+    /// the crash report, so it's to be printed as is.
+    pub fn observe(&mut self, line: &str) -> bool {
+        if self.report.is_none() {
+            let plain = crate::strip_colors(line);
+            if !MARKERS.iter().any(|marker| plain.contains(marker)) {
+                return false;
+            }
+        }
+        let plain = crate::strip_colors(line.trim_end()).into_owned();
+        self.report.get_or_insert_with(Vec::new).push(plain);
+        true
+    }
+
+    /// The whole report, if the compiler has crashed.
+    pub fn report(&self) -> Option<String> {
+        self.report.as_ref().map(|lines| lines.join("\n") + "\n")
+    }
+}
+
+#[test]
+fn collects_report() {
+    let mut detector = IceDetector::new();
+    assert!(!detector.observe("   Compiling foo v0.1.0\n"));
+    assert_eq!(detector.report(), None);
+    assert!(detector
+        .observe("\x1b[1m\x1b[31merror\x1b[0m: internal compiler error: unexpected panic\n"));
+    assert!(detector.observe("query stack during panic:\n"));
+    assert!(detector.observe("   Compiling bar v0.1.0\n"));
+    assert_eq!(
+        detector.report().unwrap(),
+        "error: internal compiler error: unexpected panic\n\
+         query stack during panic:\n   Compiling bar v0.1.0\n"
+    );
+}
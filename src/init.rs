@@ -0,0 +1,279 @@
+//! The `init` subcommand: shell aliases and cargo aliases for the plugin.
+//!
+//! Since `cargo init` is a thing, the subcommand is only recognized when
+//! followed by one of the supported shells, see [`Shell::from_name`].
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A line that marks the snippets we've installed.
+const MARKER: &str = "# Added by `cargo single-line init`.";
+
+/// Cargo aliases. They expand to the full subcommand names, so running the
+/// alias never loops back to itself: the plugin runs `$CARGO build`, not
+/// `$CARGO b`.
+const CARGO_ALIASES: &[(&str, &str)] = &[
+    ("b", "single-line build"),
+    ("c", "single-line check"),
+    ("t", "single-line test"),
+    ("r", "single-line run"),
+];
+
+/// Shell aliases, `(alias, subcommand)`.
+const SHELL_ALIASES: &[(&str, &str)] = &[
+    ("csl", ""),
+    ("cb", "build"),
+    ("ck", "check"),
+    ("ct", "test"),
+    ("cr", "run"),
+];
+
+/// Supported shells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" => Some(Shell::Pwsh),
+            _ => None,
+        }
+    }
+
+    /// The shell's startup file, relative to the home directory.
+    fn rc_file(self) -> &'static str {
+        match self {
+            Shell::Bash => ".bashrc",
+            Shell::Zsh => ".zshrc",
+            Shell::Fish => ".config/fish/config.fish",
+            Shell::Pwsh => ".config/powershell/Microsoft.PowerShell_profile.ps1",
+        }
+    }
+
+    /// Shell functions that invoke the plugin.
+    ///
+    /// `command cargo` (and its equivalents) bypasses any shell function or
+    /// alias called `cargo`, so the snippet is safe even if someone wraps
+    /// `cargo` itself.
+    pub fn snippet(self) -> String {
+        let mut snippet = format!("{}\n", MARKER);
+        for (alias, subcommand) in SHELL_ALIASES {
+            let subcommand = if subcommand.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", subcommand)
+            };
+            let line = match self {
+                Shell::Bash | Shell::Zsh => format!(
+                    "{}() {{ command cargo single-line{} \"$@\"; }}",
+                    alias, subcommand
+                ),
+                Shell::Fish => format!(
+                    "function {}; command cargo single-line{} $argv; end",
+                    alias, subcommand
+                ),
+                Shell::Pwsh => format!(
+                    "function {} {{ & (Get-Command cargo -CommandType Application) single-line{} @args }}",
+                    alias, subcommand
+                ),
+            };
+            snippet.push_str(&line);
+            snippet.push('\n');
+        }
+        snippet
+    }
+}
+
+/// The `[alias]` snippet for `.cargo/config.toml`.
+pub fn cargo_snippet() -> String {
+    let mut snippet = format!("{}\n[alias]\n", MARKER);
+    for (alias, command) in CARGO_ALIASES {
+        snippet.push_str(&format!("{} = \"{}\"\n", alias, command));
+    }
+    snippet
+}
+
+/// Runs the subcommand: prints the snippets, or installs them when `install`
+/// is set.
+pub fn run(shell: Shell, install: bool) -> io::Result<()> {
+    if !install {
+        println!("# Shell functions, add them to ~/{}:", shell.rc_file());
+        println!("{}", shell.snippet());
+        println!("# Cargo aliases, add them to ~/.cargo/config.toml:");
+        print!("{}", cargo_snippet());
+        return Ok(());
+    }
+
+    let home = home_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "unable to locate the home directory",
+        )
+    })?;
+    let rc_file = home.join(shell.rc_file());
+    let contents = read_optional(&rc_file)?;
+    if contents.contains(MARKER) {
+        println!("{}: already installed", rc_file.display());
+    } else {
+        write_with_dirs(&rc_file, &format!("{}\n{}", contents, shell.snippet()))?;
+        println!("{}: shell functions installed", rc_file.display());
+    }
+
+    let cargo_home = cargo_home().unwrap_or_else(|| home.join(".cargo"));
+    install_cargo_aliases(&cargo_home.join("config.toml"))
+}
+
+/// Adds the cargo aliases to the `config` file, unless they're there already.
+pub fn install_cargo_aliases(config: &Path) -> io::Result<()> {
+    let contents = read_optional(config)?;
+    if contents.contains(MARKER) {
+        println!("{}: already installed", config.display());
+    } else {
+        write_with_dirs(config, &add_cargo_aliases(&contents))?;
+        println!("{}: cargo aliases installed", config.display());
+    }
+    Ok(())
+}
+
+/// Adds our aliases to the cargo config. An existing `[alias]` table is
+/// extended (since a table can't be defined twice), and aliases the user has
+/// already defined are left alone.
+fn add_cargo_aliases(config: &str) -> String {
+    let defined = defined_aliases(config);
+    let defined = |alias: &str| defined.contains(&alias);
+    let aliases: String = CARGO_ALIASES
+        .iter()
+        .filter(|(alias, _)| !defined(alias))
+        .map(|(alias, command)| format!("{} = \"{}\"\n", alias, command))
+        .collect();
+
+    let mut lines: Vec<&str> = config.lines().collect();
+    match lines.iter().position(|line| line.trim() == "[alias]") {
+        Some(index) => {
+            let insertion = format!("{}\n{}", MARKER, aliases.trim_end());
+            lines.insert(index + 1, &insertion);
+            let mut result = lines.join("\n");
+            result.push('\n');
+            result
+        }
+        None => {
+            let mut result = config.to_owned();
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(&format!("{}\n[alias]\n{}", MARKER, aliases));
+            result
+        }
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Cargo's home, `~/.cargo` unless `CARGO_HOME` says otherwise.
+pub fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(home_dir()?.join(".cargo")))
+}
+
+/// Reads a file, a missing file is considered empty.
+fn read_optional(path: &Path) -> io::Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_with_dirs(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)
+}
+
+/// The aliases defined in the `config`, in the `[alias]` table or as
+/// `alias.NAME = ...` before any table. The same keys in the other tables are
+/// something else.
+fn defined_aliases(config: &str) -> Vec<&str> {
+    let mut table = None;
+    let mut aliases = Vec::new();
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            table = Some(line);
+            continue;
+        }
+        let Some((key, _)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let alias = match table {
+            Some("[alias]") => key,
+            Some(_) => continue,
+            None => match key.strip_prefix("alias.") {
+                Some(alias) => alias,
+                None => continue,
+            },
+        };
+        aliases.push(alias.trim_matches('"'));
+    }
+    aliases
+}
+
+#[test]
+fn shell_snippets_bypass_functions() {
+    let snippet = Shell::Bash.snippet();
+    assert!(snippet.contains("cb() { command cargo single-line build \"$@\"; }"));
+    assert!(snippet.contains("csl() { command cargo single-line \"$@\"; }"));
+    let snippet = Shell::Fish.snippet();
+    assert!(snippet.contains("function ct; command cargo single-line test $argv; end"));
+}
+
+#[test]
+fn extends_existing_alias_table() {
+    let config = "[build]\njobs = 4\n\n[alias]\nb = \"build --release\"\n";
+    let updated = add_cargo_aliases(config);
+    assert_eq!(
+        updated,
+        format!(
+            "[build]\njobs = 4\n\n[alias]\n{}\n\
+             c = \"single-line check\"\n\
+             t = \"single-line test\"\n\
+             r = \"single-line run\"\n\
+             b = \"build --release\"\n",
+            MARKER
+        )
+    );
+
+    let updated = add_cargo_aliases("");
+    assert_eq!(updated, cargo_snippet());
+}
+
+#[test]
+fn finds_aliases_in_alias_table_only() {
+    let config = "alias.r = \"run --release\"\n\n[profile.dev]\nc = 1\n\n\
+                  [alias]\n\"t\" = \"test --lib\"\n";
+    assert_eq!(defined_aliases(config), ["r", "t"]);
+    let updated = add_cargo_aliases(config);
+    assert!(updated.contains("b = \"single-line build\"\n"));
+    assert!(updated.contains("c = \"single-line check\"\n"));
+    assert!(!updated.contains("t = \"single-line test\""));
+    assert!(!updated.contains("r = \"single-line run\""));
+}
@@ -0,0 +1,190 @@
+//! Keyboard shortcuts while the build runs.
+//!
+//! The terminal is switched to the non-canonical mode (keys are delivered
+//! without waiting for Enter, and are not echoed), and a background thread
+//! polls stdin for key presses. The listening can be paused to hand the
+//! terminal over to another program, like a pager.
+//!
+//! Since a build can be cancelled from the keyboard, cargo runs in its own
+//! process group while the keys are listened to, and the terminating signals
+//! are passed over to it (see [`crate::signals`]).
+//!
+//! Only supported on Unix for now.
+
+use std::{
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// State shared with the listening thread.
+#[derive(Debug, Default)]
+struct Shared {
+    paused: AtomicBool,
+    stopped: AtomicBool,
+}
+
+/// Listens to the keys until dropped, restoring the terminal afterwards.
+#[derive(Debug)]
+pub struct Keys {
+    shared: Arc<Shared>,
+}
+
+impl Keys {
+    /// Starts listening to the keys, if stdin is a terminal. The `on_key`
+    /// callback is called for every key press from a background thread, and
+    /// stops the listening by returning `false`.
+    pub fn start(on_key: impl FnMut(char) -> bool + Send + 'static) -> Option<Self> {
+        if !atty::is(atty::Stream::Stdin) {
+            return None;
+        }
+        imp::enable_raw_mode()?;
+        let shared = Arc::new(Shared::default());
+        {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || imp::listen(&shared, on_key));
+        }
+        Some(Keys { shared })
+    }
+
+    /// Gives the terminal back until [`resume`](Self::resume) is called.
+    pub fn pause(&self) {
+        self.shared.paused.store(true, Ordering::SeqCst);
+        imp::restore();
+    }
+
+    pub fn resume(&self) {
+        let _ = imp::enable_raw_mode();
+        self.shared.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Makes the command start in its own process group, so it can be
+    /// interrupted without interrupting us. The signals are to be
+    /// [forwarded](crate::signals::forward_to) to the group once it's started,
+    /// so Ctrl-C still reaches it.
+    pub fn isolate(&self, cmd: &mut Command) {
+        imp::isolate(cmd);
+    }
+
+    /// Asks the child's process group to stop, like Ctrl-C does.
+    pub fn interrupt(&self, pid: u32) {
+        imp::interrupt(pid);
+    }
+}
+
+/// Restores the terminal settings changed by [`Keys::start`], if any, without
+/// stopping the listening.
+pub fn restore_terminal() {
+    imp::restore();
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        imp::restore();
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        os::unix::process::CommandExt,
+        process::Command,
+        sync::{atomic::Ordering, OnceLock},
+        time::Duration,
+    };
+
+    use super::Shared;
+
+    /// How long the listening thread waits for a key before checking whether
+    /// it's paused or stopped.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// The terminal settings to restore.
+    static SAVED: OnceLock<libc::termios> = OnceLock::new();
+
+    pub fn enable_raw_mode() -> Option<()> {
+        let saved = match SAVED.get() {
+            Some(saved) => *saved,
+            None => {
+                let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+                // SAFETY: `tcgetattr` only writes into the provided structure.
+                if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+                    return None;
+                }
+                *SAVED.get_or_init(|| saved)
+            }
+        };
+        let mut raw = saved;
+        // Keep `ISIG`, so Ctrl-C still works as usual.
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a valid `termios` obtained from `tcgetattr`.
+        (unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } == 0).then_some(())
+    }
+
+    pub fn restore() {
+        if let Some(saved) = SAVED.get() {
+            // SAFETY: `saved` is a valid `termios` obtained from `tcgetattr`.
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved) };
+        }
+    }
+
+    pub fn isolate(cmd: &mut Command) {
+        cmd.process_group(0);
+    }
+
+    pub fn interrupt(pid: u32) {
+        if let Ok(pid) = libc::pid_t::try_from(pid) {
+            // SAFETY: sending a signal has no memory safety implications.
+            unsafe { libc::kill(-pid, libc::SIGINT) };
+        }
+    }
+
+    pub fn listen(shared: &Shared, mut on_key: impl FnMut(char) -> bool) {
+        while !shared.stopped.load(Ordering::SeqCst) {
+            if shared.paused.load(Ordering::SeqCst) {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            let mut fd = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: a single valid `pollfd` is passed.
+            let ready = unsafe { libc::poll(&mut fd, 1, POLL_INTERVAL.as_millis() as libc::c_int) };
+            if ready <= 0 || shared.paused.load(Ordering::SeqCst) {
+                continue;
+            }
+            let mut byte = 0u8;
+            // SAFETY: reads a single byte into `byte`.
+            let read = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+            if read <= 0 || !on_key(char::from(byte)) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::process::Command;
+
+    use super::Shared;
+
+    pub fn enable_raw_mode() -> Option<()> {
+        None
+    }
+
+    pub fn restore() {}
+
+    pub fn isolate(_cmd: &mut Command) {}
+
+    pub fn interrupt(_pid: u32) {}
+
+    pub fn listen(_shared: &Shared, _on_key: impl FnMut(char) -> bool) {}
+}
@@ -0,0 +1,291 @@
+//! Localization of our own messages.
+//!
+//! The messages live in Fluent files under `locales/`, compiled into the
+//! binary. To add a language, put its catalog next to the English one and list
+//! it in [`CATALOGS`]; the messages it lacks are taken from the English one.
+//!
+//! The language is the one given with `--lang`, or the one of the locale
+//! (`LC_ALL`, `LC_MESSAGES` or `LANG`).
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+/// The built-in catalogs, the fallback one first.
+const CATALOGS: &[(&str, &str)] = &[("en", include_str!("../locales/en.ftl"))];
+
+/// The catalogs of the selected language and of the fallback one.
+static SELECTED: OnceLock<Vec<Catalog>> = OnceLock::new();
+
+/// Selects the language of the messages: the given one, or the one of the
+/// locale. Only the first call matters.
+pub fn init(lang: Option<&str>) {
+    let lang = lang.map(str::to_owned).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+    });
+    let _ = SELECTED.get_or_init(|| catalogs(lang.as_deref()));
+}
+
+/// The catalogs for the language, like `de_DE.UTF-8`, most specific first.
+fn catalogs(lang: Option<&str>) -> Vec<Catalog> {
+    let mut selected = Vec::new();
+    if let Some(lang) = lang {
+        // `de_DE.UTF-8@euro` is looked up as `de-DE`, then as `de`.
+        let lang = lang.split(['.', '@']).next().unwrap_or_default();
+        let lang = lang.replace('_', "-");
+        let language = lang.split('-').next().unwrap_or_default();
+        for wanted in [lang.as_str(), language] {
+            if let Some((_, source)) = CATALOGS
+                .iter()
+                .skip(1)
+                .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            {
+                selected.push(parse(source).expect("Built-in catalogs are well-formed"));
+            }
+        }
+    }
+    selected.push(parse(CATALOGS[0].1).expect("Built-in catalogs are well-formed"));
+    selected
+}
+
+/// Formats the message with the arguments, in the selected language (English
+/// if none is selected). An unknown message is displayed as its id.
+pub fn text(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    render(SELECTED.get_or_init(|| catalogs(None)), id, args)
+}
+
+/// Formats the message from the first of the `catalogs` that has it.
+fn render(catalogs: &[Catalog], id: &str, args: &[(&str, &dyn Display)]) -> String {
+    match catalogs.iter().find_map(|catalog| catalog.get(id)) {
+        Some(pattern) => {
+            let args: Vec<(&str, String)> = args
+                .iter()
+                .map(|(name, value)| (*name, value.to_string()))
+                .collect();
+            let mut text = String::new();
+            format_pattern(pattern, &args, &mut text);
+            text
+        }
+        None => id.to_owned(),
+    }
+}
+
+type Catalog = HashMap<String, Vec<Element>>;
+
+/// A piece of a message.
+#[derive(Debug, PartialEq)]
+enum Element {
+    Text(String),
+    Variable(String),
+    Select {
+        selector: String,
+        variants: Vec<(String, Vec<Element>)>,
+        /// The index of the default variant.
+        default: usize,
+    },
+}
+
+fn format_pattern(pattern: &[Element], args: &[(&str, String)], text: &mut String) {
+    let value = |name: &str| {
+        args.iter()
+            .find(|(arg, _)| *arg == name)
+            .map(|(_, value)| value.as_str())
+    };
+    for element in pattern {
+        match element {
+            Element::Text(part) => text.push_str(part),
+            Element::Variable(name) => match value(name) {
+                Some(value) => text.push_str(value),
+                None => {
+                    text.push('$');
+                    text.push_str(name);
+                }
+            },
+            Element::Select {
+                selector,
+                variants,
+                default,
+            } => {
+                let value = value(selector).unwrap_or_default();
+                let category = match value.parse::<f64>() {
+                    Ok(1.0) => "one",
+                    _ => "other",
+                };
+                let variant = variants
+                    .iter()
+                    .find(|(key, _)| key == value)
+                    .or_else(|| variants.iter().find(|(key, _)| key == category))
+                    .unwrap_or(&variants[*default]);
+                format_pattern(&variant.1, args, text);
+            }
+        }
+    }
+}
+
+/// Parses a catalog.
+fn parse(source: &str) -> Result<Catalog, String> {
+    let mut catalog = Catalog::new();
+    let mut lines = source.lines().enumerate().peekable();
+    while let Some((number, line)) = lines.next() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (id, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected a message", number + 1))?;
+        let id = id.trim();
+        if id.is_empty() || line.starts_with(char::is_whitespace) {
+            return Err(format!("line {}: expected a message", number + 1));
+        }
+        let mut value = value.trim().to_owned();
+        // The indented lines continue the message.
+        while let Some((_, line)) = lines.next_if(|(_, line)| line.starts_with([' ', '\t'])) {
+            value.push('\n');
+            value.push_str(line.trim());
+        }
+        let mut chars = value.chars().peekable();
+        let pattern = parse_pattern(&mut chars, false)
+            .map_err(|e| format!("line {}: message {}: {}", number + 1, id, e))?;
+        catalog.insert(id.to_owned(), pattern);
+    }
+    Ok(catalog)
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+/// Parses a pattern up to the end of the input, or up to the end of the line
+/// for a variant.
+fn parse_pattern(chars: &mut Chars, variant: bool) -> Result<Vec<Element>, String> {
+    let mut pattern = Vec::new();
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        if variant && c == '\n' {
+            break;
+        }
+        let _ = chars.next();
+        if c != '{' {
+            text.push(c);
+            continue;
+        }
+        if !text.is_empty() {
+            pattern.push(Element::Text(std::mem::take(&mut text)));
+        }
+        pattern.push(parse_placeable(chars)?);
+    }
+    let text = if variant { text.trim_end() } else { &text };
+    if !text.is_empty() {
+        pattern.push(Element::Text(text.to_owned()));
+    }
+    Ok(pattern)
+}
+
+/// Parses what's inside the braces, the opening one being consumed already.
+fn parse_placeable(chars: &mut Chars) -> Result<Element, String> {
+    skip_whitespace(chars);
+    let element = match chars.next() {
+        Some('"') => {
+            let mut literal = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => literal.push(c),
+                    None => return Err("unterminated string literal".into()),
+                }
+            }
+            Element::Text(literal)
+        }
+        Some('$') => {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '-' || *c == '_') {
+                name.push(c);
+            }
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'-').is_some() {
+                if chars.next_if_eq(&'>').is_none() {
+                    return Err("expected `->`".into());
+                }
+                parse_variants(chars, name)?
+            } else {
+                Element::Variable(name)
+            }
+        }
+        _ => return Err("expected a variable or a string literal".into()),
+    };
+    skip_whitespace(chars);
+    match chars.next() {
+        Some('}') => Ok(element),
+        _ => Err("expected `}`".into()),
+    }
+}
+
+/// Parses the variants of a select expression, up to the closing brace.
+fn parse_variants(chars: &mut Chars, selector: String) -> Result<Element, String> {
+    let mut variants = Vec::new();
+    let mut default = None;
+    loop {
+        skip_whitespace(chars);
+        if chars.peek() == Some(&'}') {
+            break;
+        }
+        if chars.next_if_eq(&'*').is_some() {
+            default = Some(variants.len());
+        }
+        if chars.next() != Some('[') {
+            return Err("expected a variant".into());
+        }
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some(']') => break,
+                Some(c) => key.push(c),
+                None => return Err("unterminated variant key".into()),
+            }
+        }
+        skip_whitespace(chars);
+        variants.push((key.trim().to_owned(), parse_pattern(chars, true)?));
+    }
+    let default = default.ok_or("expected a default variant")?;
+    Ok(Element::Select {
+        selector,
+        variants,
+        default,
+    })
+}
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+#[test]
+fn parses_catalogs() {
+    for (name, source) in CATALOGS {
+        if let Err(e) = parse(source) {
+            panic!("{}: {}", name, e);
+        }
+    }
+    assert!(parse("  indented = text").is_err());
+    assert!(parse("broken = { $count ->\n  [one] one\n}").is_err());
+}
+
+#[test]
+fn formats_messages() {
+    let catalogs = [
+        parse("greeting = Hallo, { $name }!").unwrap(),
+        parse(
+            "greeting = Hello, { $name }!
+crates = { $count ->
+        [0] no crates
+        [one] 1 crate
+       *[other] { $count } crates
+    } { \"\" }left",
+        )
+        .unwrap(),
+    ];
+    let text = |id: &str, args: &[(&str, &dyn Display)]| render(&catalogs, id, args);
+    assert_eq!(text("greeting", &[("name", &"Welt")]), "Hallo, Welt!");
+    assert_eq!(text("crates", &[("count", &0)]), "no crates left");
+    assert_eq!(text("crates", &[("count", &1)]), "1 crate left");
+    assert_eq!(text("crates", &[("count", &5)]), "5 crates left");
+    assert_eq!(text("no-such-message", &[]), "no-such-message");
+}
@@ -0,0 +1,292 @@
+//! A cargo plugin to shrink cargo's output: the chatter like `Compiling` or
+//! `Downloading` is put on a single status line overwritten in place, while the
+//! warnings, the errors and the rest are printed as is.
+//!
+//! The crate is mostly the `cargo-single-line` binary, see [`run`], but the
+//! line filtering is available on its own, e.g. for a build orchestrator to
+//! collapse the output of the cargo it runs:
+//!
+//! ```no_run
+//! use std::{
+//!     io::BufReader,
+//!     process::{Command, Stdio},
+//! };
+//!
+//! use cargo_single_line::{CaptureRule, LineFilter};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut cargo = Command::new("cargo")
+//!     .args(["build", "--color=always"])
+//!     .stderr(Stdio::piped())
+//!     .spawn()?;
+//! let output = BufReader::new(cargo.stderr.take().expect("stderr is piped"));
+//! LineFilter::new()
+//!     .with_rule(CaptureRule::new(r"^\s*Generating ").expect("the pattern is valid"))
+//!     .process_stream(output, std::io::stderr())?;
+//! cargo.wait()?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod argfile;
+mod artifacts;
+mod baseline;
+mod cargo_args;
+mod child_env;
+mod cli;
+mod config;
+mod crate_filter;
+mod diagnostics;
+mod doctor;
+mod downloads;
+mod dry_run;
+mod duplicates;
+mod events;
+mod exec;
+mod filter;
+mod folding;
+mod format;
+mod handoff;
+mod heartbeat;
+mod hints;
+mod history;
+mod hooks;
+mod ice;
+mod init;
+mod keys;
+mod l10n;
+mod lines;
+mod linker;
+mod localized;
+mod lock_changes;
+mod lock_wait;
+mod lockfile;
+mod log_file;
+mod messages;
+mod notify;
+mod options;
+mod pager;
+mod panic;
+mod preset;
+mod progress;
+mod pty;
+mod rebuild;
+mod recursion;
+mod redact;
+mod replay;
+mod report;
+mod screen;
+mod signals;
+mod slow;
+mod sparkline;
+mod spinner;
+mod stall;
+mod stats;
+mod status;
+mod status_file;
+mod summary;
+mod svg;
+mod tag;
+mod template;
+mod terminal;
+mod test_progress;
+mod timings;
+mod title;
+mod tools;
+mod trace;
+mod transcript;
+mod unit;
+mod update;
+mod verbose;
+mod verbosity;
+mod watch;
+mod width;
+mod workspace;
+
+use std::borrow::Cow;
+
+pub use crate::{
+    cli::run,
+    filter::{process_stream, CaptureRule, Compaction, LineFilter},
+};
+
+/// The color codes as a regular expression, which [`color_code_len`] follows
+/// by hand. See https://stackoverflow.com/a/18000433/1449426 for the
+/// explanation on the regular expression.
+#[cfg(test)]
+fn color_regex() -> regex::Regex {
+    regex::Regex::new(r#"^\x1B\[([0-9]{1,3}(;[0-9]{1,2})?)?[mGK]"#).expect("Regex is well-formed")
+}
+
+/// The length of the color code at the start of the bytes, if there's one: an
+/// `ESC [`, then optionally up to 3 digits, optionally followed by a `;` and up
+/// to 2 more digits, and finally an `m`, a `G` or a `K`.
+///
+/// Every line of the output goes through here (often more than once), so it's
+/// done without the regular expressions and the allocations.
+fn color_code_len(bytes: &[u8]) -> Option<usize> {
+    let rest = bytes.strip_prefix(b"\x1b[")?;
+    let digits = |from: usize| {
+        rest.get(from..)
+            .unwrap_or_default()
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut end = digits(0);
+    if end > 3 {
+        return None;
+    }
+    if end > 0 && rest.get(end) == Some(&b';') {
+        let more = digits(end + 1);
+        if !(1..=2).contains(&more) {
+            return None;
+        }
+        end += 1 + more;
+    }
+    match rest.get(end)? {
+        b'm' | b'G' | b'K' => Some(2 + end + 1),
+        _ => None,
+    }
+}
+
+/// Trims the color codes at the start of the input.
+fn trim_start_color(input: &str) -> &str {
+    // There might be whitespaces before the color codes.
+    let mut input = input.trim_start();
+    while let Some(length) = color_code_len(input.as_bytes()) {
+        // Keep stripping the color codes from the beginning of the line. The
+        // codes are ASCII, so the rest starts at a character boundary.
+        input = &input[length..];
+    }
+    input.trim_start()
+}
+
+/// Removes all the color codes from the input, only allocating if there are
+/// any.
+fn strip_colors(input: &str) -> Cow<'_, str> {
+    let bytes = input.as_bytes();
+    let mut stripped = String::new();
+    // The end of the latest code removed.
+    let mut kept = 0;
+    let mut at = 0;
+    while let Some(escape) = bytes[at..].iter().position(|&b| b == 0x1b) {
+        at += escape;
+        match color_code_len(&bytes[at..]) {
+            Some(length) => {
+                if stripped.is_empty() {
+                    stripped.reserve(input.len());
+                }
+                stripped.push_str(&input[kept..at]);
+                at += length;
+                kept = at;
+            }
+            None => at += 1,
+        }
+    }
+    if kept == 0 {
+        Cow::Borrowed(input)
+    } else {
+        stripped.push_str(&input[kept..]);
+        Cow::Owned(stripped)
+    }
+}
+
+/// Checks whether the line starts with one of the given prefixes, color codes
+/// excluded.
+fn starts_with(line: &str, prefixes: &[&str]) -> bool {
+    let line = trim_start_color(line);
+
+    // If there are no prefixes, the `any` method will return `false`, meaning
+    // the given line does not start with any of the non-existing prefixes,
+    // which is just okay for our purposes.
+    prefixes.iter().any(|prefix| line.starts_with(prefix))
+}
+
+#[test]
+fn verify_regex() {
+    let re = color_regex();
+    let input = "\u{1b}[1m\u{1b}[32m    Finished";
+
+    let finding = re.find(input).unwrap();
+    assert_eq!(finding.as_str(), "\u{1b}[1m");
+    let input = &input[finding.end()..];
+
+    let finding = re.find(input).unwrap();
+    assert_eq!(finding.as_str(), "\u{1b}[32m");
+    let input = &input[finding.end()..];
+
+    assert!(re.find(input).is_none());
+}
+
+#[test]
+fn verify_trim() {
+    let re = color_regex();
+    let example = "\u{1b}[1m\u{1b}[32m    Finished\u{1b}[0m dev [unoptimized + debuginfo] target(s) in 0.01s\n";
+
+    let mut line = example.trim();
+    while let Some(captures) = re.captures(line) {
+        // Keep stripping the color codes from the beginning of the line.
+        let whole_match = captures.get(0).expect("The string matched");
+        line = &line[whole_match.end()..];
+    }
+    // There might be whitespaces after the color codes are trimmed.
+    let line = line.trim_start();
+    assert!(line.starts_with("Finished"));
+}
+
+#[test]
+fn strips_like_regex() {
+    let re = regex::Regex::new(r#"\x1B\[([0-9]{1,3}(;[0-9]{1,2})?)?[mGK]"#).unwrap();
+    let anchored = color_regex();
+    // A tiny xorshift is enough to cover the corner cases of the codes.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let pieces = [
+        "\x1b",
+        "[",
+        "0",
+        "1",
+        "9",
+        ";",
+        "m",
+        "G",
+        "K",
+        "a",
+        " ",
+        "é",
+        "\x1b[",
+        "\x1b[1;32m",
+    ];
+    for _ in 0..20_000 {
+        let length = next() % 12;
+        let input: String = (0..length)
+            .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+            .collect();
+        assert_eq!(
+            strip_colors(&input),
+            re.replace_all(&input, ""),
+            "{:?}",
+            input
+        );
+        let mut expected = input.trim_start();
+        while let Some(found) = anchored.find(expected) {
+            expected = &expected[found.end()..];
+        }
+        assert_eq!(
+            trim_start_color(&input),
+            expected.trim_start(),
+            "{:?}",
+            input
+        );
+    }
+    assert!(matches!(
+        strip_colors("   Compiling foo v0.1.0"),
+        Cow::Borrowed(_)
+    ));
+}
@@ -0,0 +1,213 @@
+//! Splitting of the child's output into lines, the carriage returns included.
+//!
+//! Cargo (when it believes to be writing to a terminal) and some of the tools
+//! it runs redraw their progress bars in place: `   Building [==>  ] 3/10\r`,
+//! and no newline until the bar is erased. Reading up to the newlines only,
+//! such an update would show up late, glued to the next line, so it's read on
+//! its own instead.
+//!
+//! The output is not necessarily UTF-8: a linker or a build script might well
+//! speak the legacy code page of the system. Such text is told apart with the
+//! invalid sequences replaced, but is printed as the original bytes.
+
+use std::{
+    borrow::Cow,
+    io::{self, BufRead},
+};
+
+/// A piece of the output.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// A line, including the trailing newline (if any, the last line might not
+    /// have one).
+    Line(Text),
+    /// Text ended by a carriage return alone, which is to be overwritten.
+    Update(Text),
+}
+
+/// The text of a segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    /// The text, with the invalid UTF-8 sequences replaced (if there are any).
+    pub text: String,
+    /// The original bytes, only kept if they are not valid UTF-8.
+    pub raw: Option<Vec<u8>>,
+}
+
+impl Text {
+    fn new(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Text { text, raw: None },
+            Err(e) => Text {
+                text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                raw: Some(e.into_bytes()),
+            },
+        }
+    }
+
+    /// The bytes to print: the original ones, unless the text has been edited.
+    pub fn bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.text.as_bytes())
+    }
+
+    /// Replaces the text if the `edit` changes it, the original bytes are of
+    /// no use anymore then.
+    pub fn edit(&mut self, edit: impl FnOnce(&str) -> Cow<'_, str>) {
+        if let Cow::Owned(edited) = edit(&self.text) {
+            if edited != self.text {
+                self.text = edited;
+                self.raw = None;
+            }
+        }
+    }
+
+    /// Appends the (valid) text.
+    pub fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+        if let Some(raw) = &mut self.raw {
+            raw.extend_from_slice(text.as_bytes());
+        }
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Text {
+            text: text.to_owned(),
+            raw: None,
+        }
+    }
+}
+
+/// Reads the [segments](Segment) of the output.
+#[derive(Debug)]
+pub struct Segments<R> {
+    input: R,
+}
+
+impl<R: BufRead> Segments<R> {
+    pub fn new(input: R) -> Self {
+        Segments { input }
+    }
+
+    /// The next segment, `None` at the end of the output.
+    ///
+    /// A carriage return ending the available output is held back until the
+    /// next byte tells whether it's a part of a `\r\n`.
+    pub fn read(&mut self) -> io::Result<Option<Segment>> {
+        let mut bytes = Vec::new();
+        // Whether the bytes have been ended by a carriage return, with nothing
+        // after it read yet.
+        let mut carriage_return = false;
+        loop {
+            let available = match self.input.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if carriage_return {
+                if available.first() == Some(&b'\n') {
+                    self.input.consume(1);
+                    bytes.extend_from_slice(b"\r\n");
+                    return Ok(Some(line(bytes)));
+                }
+                return Ok(Some(Segment::Update(Text::new(bytes))));
+            }
+            if available.is_empty() {
+                return Ok((!bytes.is_empty()).then(|| line(bytes)));
+            }
+            let Some(end) = available.iter().position(|&b| b == b'\n' || b == b'\r') else {
+                bytes.extend_from_slice(available);
+                let length = available.len();
+                self.input.consume(length);
+                continue;
+            };
+            bytes.extend_from_slice(&available[..end]);
+            if available[end] == b'\n' {
+                bytes.push(b'\n');
+                self.input.consume(end + 1);
+                return Ok(Some(line(bytes)));
+            }
+            match available.get(end + 1) {
+                Some(b'\n') => {
+                    bytes.extend_from_slice(b"\r\n");
+                    self.input.consume(end + 2);
+                    return Ok(Some(line(bytes)));
+                }
+                Some(_) => {
+                    self.input.consume(end + 1);
+                    return Ok(Some(Segment::Update(Text::new(bytes))));
+                }
+                None => {
+                    self.input.consume(end + 1);
+                    carriage_return = true;
+                }
+            }
+        }
+    }
+}
+
+fn line(bytes: Vec<u8>) -> Segment {
+    Segment::Line(Text::new(bytes))
+}
+
+#[cfg(test)]
+fn read_all(input: impl BufRead) -> Vec<Segment> {
+    let mut segments = Segments::new(input);
+    std::iter::from_fn(|| segments.read().unwrap()).collect()
+}
+
+#[test]
+fn splits_updates() {
+    let output = "    Updating index\n   Building [=>  ] 1/3: foo\r   Building [==> ] 2/3: bar\r\x1b[K   Compiling baz\r\nlast";
+    assert_eq!(
+        read_all(output.as_bytes()),
+        [
+            Segment::Line("    Updating index\n".into()),
+            Segment::Update("   Building [=>  ] 1/3: foo".into()),
+            Segment::Update("   Building [==> ] 2/3: bar".into()),
+            Segment::Line("\x1b[K   Compiling baz\r\n".into()),
+            Segment::Line("last".into()),
+        ]
+    );
+}
+
+#[test]
+fn joins_split_line_endings() {
+    // Every read returns a single byte.
+    let output = io::BufReader::with_capacity(1, "ab\r\ncd\ref\r".as_bytes());
+    assert_eq!(
+        read_all(output),
+        [
+            Segment::Line("ab\r\n".into()),
+            Segment::Update("cd".into()),
+            Segment::Update("ef".into()),
+        ]
+    );
+}
+
+#[test]
+fn keeps_invalid_utf8() {
+    let output = include_bytes!("../tests/fixtures/invalid-utf8.txt");
+    let segments = read_all(&output[..]);
+    let Segment::Line(line) = &segments[0] else {
+        panic!("not a line: {:?}", segments[0]);
+    };
+    assert_eq!(
+        line.text,
+        "   Compiling foo v0.1.0 (C:\\Users\\J\u{fffd}rg\\foo)\n"
+    );
+    assert_eq!(line.bytes(), &output[..line.bytes().len()]);
+    let restored: Vec<u8> = segments
+        .iter()
+        .flat_map(|segment| match segment {
+            Segment::Line(text) | Segment::Update(text) => text.bytes().to_vec(),
+        })
+        .collect();
+    assert_eq!(restored, output);
+
+    let mut line = line.clone();
+    line.edit(|text| text.replace("foo", "baz").into());
+    assert_eq!(line.raw, None);
+    assert_eq!(line.bytes(), line.text.as_bytes());
+}
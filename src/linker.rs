@@ -0,0 +1,105 @@
+//! Linker failures.
+//!
+//! When linking fails, rustc prints the whole linker invocation as a single
+//! note, which easily takes kilobytes and floods the terminal, while the
+//! actual linker errors follow in the next note. The invocation is collapsed
+//! (and saved to a file by `collapse_linker_invocation` in `cli.rs`), the rest
+//! is printed as usual.
+
+/// The invocations shorter than this are left alone.
+const MIN_LENGTH: usize = 256;
+
+/// A linker invocation found in the output.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// What precedes the invocation on the line, like `  = note: `, with the
+    /// colors kept.
+    pub prefix: String,
+    /// The invocation itself, without the colors.
+    pub command: String,
+}
+
+/// Looks for the linker invocations in the linker failures.
+#[derive(Debug, Default)]
+pub struct LinkerErrors {
+    /// Whether we're inside a "linking with ... failed" error, before the
+    /// invocation.
+    in_error: bool,
+}
+
+impl LinkerErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo, returning the invocation if
+    /// it's the line to collapse.
+    pub fn observe(&mut self, line: &str) -> Option<Invocation> {
+        if !self.in_error {
+            // Cheap enough to check for every line.
+            if line.contains("linking with") {
+                let plain = crate::strip_colors(line);
+                self.in_error =
+                    plain.starts_with("error: linking with") && plain.contains("failed");
+            }
+            return None;
+        }
+        let plain = crate::strip_colors(line.trim_end());
+        if !plain.starts_with(char::is_whitespace) {
+            // Another message has started.
+            self.in_error = false;
+            return None;
+        }
+        let command = plain.trim_start().strip_prefix("= note: ")?;
+        // The invocation is the first note, whatever its length.
+        self.in_error = false;
+        if command.len() < MIN_LENGTH {
+            return None;
+        }
+        let note = line.find("note")?;
+        let colon = note + line[note..].find(": ")?;
+        Some(Invocation {
+            prefix: line[..colon + 2].to_owned(),
+            command: command.to_owned(),
+        })
+    }
+}
+
+#[test]
+fn finds_invocations() {
+    let command = format!("\"cc\" \"-m64\" {}\"-lfoo\"", "\"/tmp/foo.o\" ".repeat(40));
+    let mut linker = LinkerErrors::new();
+    assert_eq!(linker.observe("   Compiling foo v0.1.0\n"), None);
+    assert_eq!(linker.observe("  = note: not a linker failure\n"), None);
+    assert_eq!(
+        linker.observe("error: linking with `cc` failed: exit status: 1\n"),
+        None
+    );
+    assert_eq!(linker.observe("  |\n"), None);
+    assert_eq!(
+        linker.observe(&format!("  = note: {}\n", command)),
+        Some(Invocation {
+            prefix: "  = note: ".into(),
+            command: command.clone(),
+        })
+    );
+    assert_eq!(
+        linker.observe("  = note: /usr/bin/ld: cannot find -lfoo\n"),
+        None
+    );
+
+    // Colored, and the invocation comes in the first note only.
+    let colored = "\x1b[0m  \x1b[0m\x1b[1m\x1b[94m= \x1b[0m\x1b[1mnote\x1b[0m: ";
+    assert_eq!(
+        linker.observe("\x1b[1m\x1b[31merror\x1b[0m\x1b[1m: linking with `cc` failed\x1b[0m\n"),
+        None
+    );
+    assert_eq!(
+        linker.observe(&format!("{}{}\n", colored, command)),
+        Some(Invocation {
+            prefix: colored.into(),
+            command: command.clone(),
+        })
+    );
+    assert_eq!(linker.observe(&format!("  = note: {}\n", command)), None);
+}
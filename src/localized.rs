@@ -0,0 +1,186 @@
+//! Cargo's output translated by a wrapper (or, some day, by cargo itself)
+//! never matches the English prefixes, so nothing would be collapsed without
+//! a word. Such output is detected by the shape of the cargo's lines, a verb
+//! right-aligned to the 12th column, none of which is known.
+//!
+//! The translated verbs are mapped back to English with a prefix table given
+//! with `--locale-prefixes`, one `translated = English` pair per line:
+//!
+//! ```text
+//! # Spanish
+//! Compilando = Compiling
+//! Terminado = Finished
+//! ```
+
+use std::{borrow::Cow, io, path::Path};
+
+/// The verbs cargo starts its lines with, as far as telling English output
+/// apart goes.
+const ENGLISH_VERBS: &[&str] = &[
+    "Adding",
+    "Blocking",
+    "Checking",
+    "Compiling",
+    "Documenting",
+    "Downgrading",
+    "Downloaded",
+    "Downloading",
+    "Executable",
+    "Finished",
+    "Fresh",
+    "Generated",
+    "Installed",
+    "Installing",
+    "Locking",
+    "Packaged",
+    "Packaging",
+    "Removed",
+    "Removing",
+    "Replacing",
+    "Running",
+    "Updating",
+    "Upgrading",
+    "Uploading",
+    "Verifying",
+];
+
+/// How many lines shaped like the cargo's ones, with none of the verbs known,
+/// make the output localized.
+const FOREIGN_LINES: usize = 3;
+
+/// The verb of a line shaped like the cargo's status lines.
+fn status_verb(line: &str) -> Option<String> {
+    let plain = crate::strip_colors(line);
+    let word = plain.split_whitespace().next()?;
+    let indent = plain.len() - plain.trim_start().len();
+    let is_verb = indent > 0
+        && indent + word.chars().count() == 12
+        && word.starts_with(char::is_uppercase)
+        && word.chars().all(char::is_alphabetic);
+    is_verb.then(|| word.to_owned())
+}
+
+/// Maps the translated verbs back to English.
+#[derive(Debug, Clone, Default)]
+pub struct Prefixes {
+    table: Vec<(String, String)>,
+}
+
+impl Prefixes {
+    /// Reads the table from the file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut table = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (translated, english) = line
+                .split_once('=')
+                .map(|(translated, english)| (translated.trim(), english.trim()))
+                .filter(|(translated, english)| !translated.is_empty() && !english.is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "line {}: expected `translated = English`, got {:?}",
+                        number + 1,
+                        line
+                    )
+                })?;
+            table.push((translated.to_owned(), english.to_owned()));
+        }
+        Ok(Prefixes { table })
+    }
+
+    /// The line with its translated verb replaced by the English one, the
+    /// alignment kept where possible.
+    pub fn translate<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let plain = crate::trim_start_color(line);
+        let Some((translated, english)) = self.table.iter().find(|(translated, _)| {
+            plain.strip_prefix(translated.as_str()).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with(|c: char| !c.is_alphanumeric())
+            })
+        }) else {
+            return Cow::Borrowed(line);
+        };
+        let head = &line[..line.len() - plain.len()];
+        let codes = head.trim_end_matches(' ');
+        // Cargo right-aligns its verbs, so the indent makes up for the length.
+        let indent = (head.len() - codes.len() + translated.chars().count())
+            .saturating_sub(english.chars().count());
+        Cow::Owned(format!(
+            "{}{:indent$}{}{}",
+            codes,
+            "",
+            english,
+            &plain[translated.len()..],
+            indent = indent
+        ))
+    }
+}
+
+/// Watches for the output that doesn't look English.
+#[derive(Debug, Default)]
+pub struct Detector {
+    /// How many lines have had a known verb.
+    known: usize,
+    /// How many lines have looked like the cargo's, but with an unknown verb.
+    foreign: usize,
+}
+
+impl Detector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes note of a line, returns `true` once the output turns out to be
+    /// localized (and only once).
+    pub fn observe(&mut self, line: &str) -> bool {
+        if self.known > 0 || self.foreign >= FOREIGN_LINES {
+            return false;
+        }
+        match status_verb(line) {
+            Some(verb) if ENGLISH_VERBS.contains(&verb.as_str()) => self.known += 1,
+            Some(_) => self.foreign += 1,
+            None => return false,
+        }
+        self.foreign == FOREIGN_LINES
+    }
+}
+
+#[test]
+fn detects_localized_output() {
+    let mut detector = Detector::new();
+    assert!(!detector.observe("  Compilando libc v0.2.153\n"));
+    assert!(!detector.observe("warning: unused variable: `x`\n"));
+    assert!(!detector.observe("\x1b[1m\x1b[32m  Compilando\x1b[0m syn v2.0.48\n"));
+    assert!(detector.observe("  Compilando foo v0.1.0 (/work/foo)\n"));
+    assert!(!detector.observe("  Compilando bar v0.1.0 (/work/bar)\n"));
+
+    let mut detector = Detector::new();
+    assert!(!detector.observe("   Compiling libc v0.2.153\n"));
+    for _ in 0..FOREIGN_LINES {
+        assert!(!detector.observe("  Compilando foo v0.1.0\n"));
+    }
+}
+
+#[test]
+fn translates_prefixes() {
+    let prefixes =
+        Prefixes::parse("# Spanish\nCompilando = Compiling\n\nTerminado=Finished\n").unwrap();
+    assert_eq!(
+        prefixes.translate("  Compilando libc v0.2.153\n"),
+        "   Compiling libc v0.2.153\n"
+    );
+    assert_eq!(
+        prefixes.translate("\x1b[1m\x1b[32m   Terminado\x1b[0m `dev` profile\n"),
+        "\x1b[1m\x1b[32m    Finished\x1b[0m `dev` profile\n"
+    );
+    assert_eq!(prefixes.translate("Compilandos\n"), "Compilandos\n");
+    assert_eq!(prefixes.translate("warning: foo\n"), "warning: foo\n");
+    assert!(Prefixes::parse("Compilando\n").is_err());
+}
@@ -0,0 +1,96 @@
+//! Changes to `Cargo.lock`, like `Adding foo v1.0.0` or
+//! `Updating foo v1.0.0 -> v1.0.1`, collected to be listed after the build.
+
+/// Kinds of the changes, in the order they are listed, along with the ids of
+/// their counts' messages.
+const KINDS: &[(&str, &str)] = &[
+    ("Adding", "lock-added"),
+    ("Removing", "lock-removed"),
+    ("Updating", "lock-updated"),
+    ("Upgrading", "lock-upgraded"),
+    ("Downgrading", "lock-downgraded"),
+];
+
+/// Collects the lock file changes from the cargo output.
+#[derive(Debug, Default)]
+pub struct LockChanges {
+    /// Changed packages, per kind (an index into `KINDS`).
+    changes: Vec<(usize, String)>,
+}
+
+impl LockChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo.
+    pub fn observe(&mut self, line: &str) {
+        let line = crate::strip_colors(line);
+        let line = line.trim();
+        let (verb, rest) = match line.split_once(' ') {
+            Some(parts) => parts,
+            None => return,
+        };
+        let kind = match KINDS.iter().position(|(kind, _)| *kind == verb) {
+            Some(kind) => kind,
+            None => return,
+        };
+        // `Updating crates.io index` is not a change.
+        if verb == "Updating" && !rest.contains(" -> ") {
+            return;
+        }
+        self.changes.push((kind, rest.trim().to_owned()));
+    }
+
+    /// Renders the counts followed by the changes grouped by kind, if any.
+    pub fn report(&self) -> Option<String> {
+        if self.changes.is_empty() {
+            return None;
+        }
+        let mut counts = Vec::new();
+        let mut groups = Vec::new();
+        for (kind, (verb, message)) in KINDS.iter().enumerate() {
+            let packages: Vec<&str> = self
+                .changes
+                .iter()
+                .filter(|(change_kind, _)| *change_kind == kind)
+                .map(|(_, package)| package.as_str())
+                .collect();
+            if packages.is_empty() {
+                continue;
+            }
+            counts.push(crate::l10n::text(message, &[("count", &packages.len())]));
+            groups.push(format!("{:>12} {}", verb, packages.join(", ")));
+        }
+        Some(format!(
+            "{}\n{}",
+            crate::l10n::text("lock-changes", &[("changes", &counts.join(", "))]),
+            groups.join("\n")
+        ))
+    }
+}
+
+#[test]
+fn groups_changes() {
+    let mut changes = LockChanges::new();
+    assert_eq!(changes.report(), None);
+    for line in [
+        "    Updating crates.io index\n",
+        "     Locking 3 packages to latest compatible versions\n",
+        "\x1b[1m\x1b[32m      Adding\x1b[0m foo v1.0.0\n",
+        "      Adding bar v2.0.0 (latest: v3.0.0)\n",
+        "    Removing baz v0.1.0\n",
+        " Downgrading qux v1.2.0 -> v1.1.0\n",
+        "    Updating quux v0.3.0 -> v0.3.1\n",
+    ] {
+        changes.observe(line);
+    }
+    assert_eq!(
+        changes.report().unwrap(),
+        "Cargo.lock changes: 2 added, 1 removed, 1 updated, 1 downgraded
+      Adding foo v1.0.0, bar v2.0.0 (latest: v3.0.0)
+    Removing baz v0.1.0
+    Updating quux v0.3.0 -> v0.3.1
+ Downgrading qux v1.2.0 -> v1.1.0"
+    );
+}
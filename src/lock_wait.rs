@@ -0,0 +1,238 @@
+//! Cargo waiting for another cargo to release a lock: `Blocking waiting for
+//! file lock on build directory` stays on the status line for as long as the
+//! other build runs, looking just like a hang. The status line counts the time
+//! instead, and names the process holding the lock when it can be found out
+//! (on Linux, from `/proc/locks`).
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{format, l10n};
+
+/// The process holding a lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holder {
+    pub pid: u32,
+    /// The name of the executable, if known.
+    pub command: Option<String>,
+    /// The lock file.
+    pub path: PathBuf,
+}
+
+/// Cargo being blocked.
+#[derive(Debug)]
+struct Waiting {
+    /// What is locked, like `build directory` or `package cache`.
+    lock: String,
+    since: Instant,
+    holder: Option<Holder>,
+}
+
+/// A lock cargo has waited for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Acquired {
+    pub lock: String,
+    pub waited: Duration,
+}
+
+/// Tracks whether cargo is blocked by a lock.
+#[derive(Debug)]
+pub struct LockWait {
+    target_dir: Option<PathBuf>,
+    waiting: Option<Waiting>,
+}
+
+/// What cargo is waiting for, according to the line (the color codes
+/// stripped).
+fn parse(line: &str) -> Option<&str> {
+    let lock = line.trim_start().strip_prefix("Blocking ")?.trim();
+    Some(
+        lock.strip_prefix("waiting for file lock on ")
+            .unwrap_or(lock),
+    )
+}
+
+impl LockWait {
+    /// Tracks the build with the locks in the `target_dir`.
+    pub fn new(target_dir: Option<PathBuf>) -> Self {
+        LockWait {
+            target_dir,
+            waiting: None,
+        }
+    }
+
+    /// Takes a note of a line printed by cargo at the moment `now`. Returns
+    /// the lock cargo has acquired if the line is the first one after the
+    /// wait.
+    pub fn observe(&mut self, line: &str, now: Instant) -> Option<Acquired> {
+        let acquired = self.waiting.take().map(|waiting| Acquired {
+            lock: waiting.lock,
+            waited: now.saturating_duration_since(waiting.since),
+        });
+        if let Some(lock) = parse(&crate::strip_colors(line)) {
+            let holder = self.holder(lock);
+            self.waiting = Some(Waiting {
+                lock: lock.to_owned(),
+                since: now,
+                holder,
+            });
+        }
+        acquired
+    }
+
+    /// What the status line says while cargo is blocked, like `waiting for
+    /// the file lock on build directory for 12s, held by cargo (pid 4321)`.
+    pub fn status(&self, now: Instant) -> Option<String> {
+        let waiting = self.waiting.as_ref()?;
+        let mut status = l10n::text(
+            "lock-waiting",
+            &[
+                ("lock", &waiting.lock),
+                (
+                    "duration",
+                    &format::approximate(now.saturating_duration_since(waiting.since)),
+                ),
+            ],
+        );
+        if let Some(holder) = &waiting.holder {
+            status.push_str(", ");
+            status.push_str(&l10n::text(
+                "lock-holder",
+                &[
+                    ("command", &holder.command.as_deref().unwrap_or("?")),
+                    ("pid", &holder.pid),
+                    ("path", &holder.path.display()),
+                ],
+            ));
+        }
+        Some(status)
+    }
+
+    /// The lock files that might be behind the `lock`.
+    fn candidates(&self, lock: &str) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        match lock {
+            "package cache" => {
+                if let Some(home) = crate::init::cargo_home() {
+                    candidates.push(home.join(".package-cache"));
+                    candidates.push(home.join(".package-cache-mutate"));
+                }
+            }
+            _ => {
+                // `target/debug/.cargo-lock`, or with a target triple
+                // `target/x86_64-unknown-linux-gnu/debug/.cargo-lock`.
+                let mut dirs: Vec<PathBuf> = self.target_dir.iter().cloned().collect();
+                for _ in 0..2 {
+                    dirs = dirs
+                        .iter()
+                        .filter_map(|dir| std::fs::read_dir(dir).ok())
+                        .flatten()
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_dir())
+                        .collect();
+                    candidates.extend(dirs.iter().map(|dir| dir.join(".cargo-lock")));
+                }
+            }
+        }
+        candidates.retain(|path| path.exists());
+        candidates
+    }
+
+    #[cfg(target_os = "linux")]
+    fn holder(&self, lock: &str) -> Option<Holder> {
+        use std::os::unix::fs::MetadataExt;
+
+        let candidates: Vec<(u64, PathBuf)> = self
+            .candidates(lock)
+            .into_iter()
+            .filter_map(|path| Some((std::fs::metadata(&path).ok()?.ino(), path)))
+            .collect();
+        let locks = std::fs::read_to_string("/proc/locks").ok()?;
+        let (pid, path) = find_holder(&locks, &candidates)?;
+        let command = std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|command| command.trim().to_owned());
+        Some(Holder {
+            pid,
+            command,
+            path: crate::artifacts::relative(path),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn holder(&self, _lock: &str) -> Option<Holder> {
+        None
+    }
+}
+
+/// Finds the process holding a lock on one of the `candidates` (along with
+/// their inodes) in the contents of `/proc/locks`:
+///
+/// ```text
+/// 1: FLOCK  ADVISORY  WRITE 4321 fd:01:1835093 0 EOF
+/// 1: -> FLOCK  ADVISORY  WRITE 4400 fd:01:1835093 0 EOF
+/// ```
+///
+/// The lines with an arrow are the processes waiting for the lock.
+fn find_holder<'a>(locks: &str, candidates: &'a [(u64, PathBuf)]) -> Option<(u32, &'a Path)> {
+    locks.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) == Some(&"->") {
+            return None;
+        }
+        let pid = fields.get(4)?.parse().ok()?;
+        let inode: u64 = fields.get(5)?.rsplit(':').next()?.parse().ok()?;
+        candidates
+            .iter()
+            .find(|(candidate, _)| *candidate == inode)
+            .map(|(_, path)| (pid, path.as_path()))
+    })
+}
+
+#[test]
+fn tracks_waiting() {
+    let start = Instant::now();
+    let mut wait = LockWait::new(None);
+    assert_eq!(wait.observe("   Compiling foo v0.1.0\n", start), None);
+    assert_eq!(wait.status(start), None);
+    assert_eq!(
+        wait.observe(
+            "\x1b[1m\x1b[36m    Blocking\x1b[0m waiting for file lock on build directory\n",
+            start
+        ),
+        None
+    );
+    assert_eq!(
+        wait.status(start + Duration::from_secs(12)).unwrap(),
+        "waiting for the file lock on build directory for 12s"
+    );
+    assert_eq!(
+        wait.observe("   Compiling foo v0.1.0\n", start + Duration::from_secs(20)),
+        Some(Acquired {
+            lock: "build directory".into(),
+            waited: Duration::from_secs(20)
+        })
+    );
+    assert_eq!(wait.status(start), None);
+}
+
+#[test]
+fn finds_holders() {
+    let locks = "\
+1: -> FLOCK  ADVISORY  WRITE 4400 fd:01:1835093 0 EOF
+1: FLOCK  ADVISORY  WRITE 4321 fd:01:1835093 0 EOF
+2: POSIX  ADVISORY  WRITE 812 00:19:2077 0 EOF
+";
+    let candidates = [
+        (2078, PathBuf::from("target/release/.cargo-lock")),
+        (1835093, PathBuf::from("target/debug/.cargo-lock")),
+    ];
+    assert_eq!(
+        find_holder(locks, &candidates),
+        Some((4321, Path::new("target/debug/.cargo-lock")))
+    );
+    assert_eq!(find_holder(locks, &candidates[..1]), None);
+}
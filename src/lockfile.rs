@@ -0,0 +1,94 @@
+//! A (very) rough parser of `Cargo.lock`.
+
+use std::path::{Path, PathBuf};
+
+/// A package listed in the lock file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// `false` for workspace members and path dependencies.
+    pub has_source: bool,
+}
+
+/// Looks for `Cargo.lock` in the `dir` and its ancestors.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+/// Extracts the list of packages from the lock file contents.
+///
+/// Only the `[[package]]` tables are looked at, everything else is ignored.
+pub fn packages(contents: &str) -> Vec<Package> {
+    let mut packages = Vec::new();
+    let mut current: Option<Package> = None;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            packages.extend(current.take());
+            if line == "[[package]]" {
+                current = Some(Package {
+                    name: String::new(),
+                    version: String::new(),
+                    has_source: false,
+                });
+            }
+            continue;
+        }
+        let package = match current.as_mut() {
+            Some(package) => package,
+            None => continue,
+        };
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim().trim_matches('"')),
+            None => continue,
+        };
+        match key {
+            "name" => package.name = value.to_owned(),
+            "version" => package.version = value.to_owned(),
+            "source" => package.has_source = true,
+            _ => {}
+        }
+    }
+    packages.extend(current);
+    packages
+}
+
+#[test]
+fn parses_packages() {
+    let contents = r#"
+# This file is automatically @generated by Cargo.
+version = 3
+
+[[package]]
+name = "atty"
+version = "0.2.14"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "libc",
+]
+
+[[package]]
+name = "cargo-single-line"
+version = "0.1.6"
+
+[metadata]
+foo = "bar"
+"#;
+    assert_eq!(
+        packages(contents),
+        [
+            Package {
+                name: "atty".into(),
+                version: "0.2.14".into(),
+                has_source: true,
+            },
+            Package {
+                name: "cargo-single-line".into(),
+                version: "0.1.6".into(),
+                has_source: false,
+            },
+        ]
+    );
+}
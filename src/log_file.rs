@@ -0,0 +1,78 @@
+//! The complete cargo output saved to a file with `--log-file`, while the
+//! terminal only gets the condensed view of it.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The environment variable with the path to the log file, when there's no
+/// `--log-file`. Not to be confused with [`crate::trace::ENV`], the path to
+/// the plugin's own trace.
+pub const ENV: &str = "CARGO_SINGLE_LINE_LOG_FILE";
+
+/// The file the output is copied to.
+#[derive(Debug)]
+pub struct LogFile {
+    /// `None` once writing has failed.
+    file: Option<BufWriter<File>>,
+    /// Whether the color codes are kept.
+    colors: bool,
+    error: Option<io::Error>,
+}
+
+impl LogFile {
+    /// Creates (or truncates) the file.
+    pub fn create(path: &Path, colors: bool) -> io::Result<Self> {
+        Ok(LogFile {
+            file: Some(BufWriter::new(File::create(path)?)),
+            colors,
+            error: None,
+        })
+    }
+
+    /// Appends the text as is, or without the color codes. A failure is kept
+    /// for [`finish`](Self::finish), and nothing is written after it.
+    pub fn write(&mut self, text: &str) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let text = if self.colors {
+            text.into()
+        } else {
+            crate::strip_colors(text)
+        };
+        if let Err(e) = file.write_all(text.as_bytes()) {
+            self.file = None;
+            self.error = Some(e);
+        }
+    }
+
+    /// Flushes the file, reporting the first failure if any.
+    pub fn finish(self) -> io::Result<()> {
+        match (self.error, self.file) {
+            (Some(e), _) => Err(e),
+            (None, Some(mut file)) => file.flush(),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn copies_output() {
+    let path = std::env::temp_dir().join(format!("cargo-single-line-log-{}", std::process::id()));
+    let line = "\x1b[1m\x1b[32m   Compiling\x1b[0m foo v0.1.0\n";
+    let mut log = LogFile::create(&path, false).unwrap();
+    log.write(line);
+    log.finish().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "   Compiling foo v0.1.0\n"
+    );
+    let mut log = LogFile::create(&path, true).unwrap();
+    log.write(line);
+    log.finish().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), line);
+    std::fs::remove_file(&path).unwrap();
+}
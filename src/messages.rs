@@ -0,0 +1,159 @@
+//! Cargo's JSON messages, read from its stdout in the `--json` mode.
+//!
+//! Unlike the human-readable output, the messages tell the warnings from the
+//! errors for sure, whatever the wording of the compiler and cargo. Whatever
+//! is not a message (the output of the tests, for instance) is left as is.
+
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::diagnostics::{Diagnostic, Level};
+
+/// The `--message-format` asking for the messages, the diagnostics rendered
+/// with or without the colors.
+pub fn message_format(colored: bool) -> &'static str {
+    if colored {
+        "--message-format=json-diagnostic-rendered-ansi"
+    } else {
+        "--message-format=json"
+    }
+}
+
+/// A message as cargo puts it, the parts we're interested in.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum RawMessage {
+    CompilerMessage {
+        package_id: String,
+        message: RawDiagnostic,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawDiagnostic {
+    message: String,
+    code: Option<RawCode>,
+    level: String,
+    spans: Vec<IgnoredAny>,
+    rendered: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawCode {
+    code: String,
+}
+
+/// A message from cargo.
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    /// A compiler message to be displayed, along with the diagnostic if it's
+    /// a warning or an error.
+    Compiler {
+        diagnostic: Option<Diagnostic>,
+        rendered: String,
+    },
+    /// The artifacts, the build scripts output and the like.
+    Other,
+}
+
+/// Parses a line of cargo's stdout, `None` if it's not a message.
+pub fn parse(line: &str) -> Option<Message> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let (package_id, raw) = match serde_json::from_str(line).ok()? {
+        RawMessage::CompilerMessage {
+            package_id,
+            message,
+        } => (package_id, message),
+        RawMessage::Other => return Some(Message::Other),
+    };
+    let mut rendered = raw.rendered.unwrap_or_else(|| raw.message.clone());
+    if !rendered.ends_with('\n') {
+        rendered.push('\n');
+    }
+    let level = match raw.level.as_str() {
+        "error" => Some(Level::Error),
+        "warning" => Some(Level::Warning),
+        _ => None,
+    };
+    // The compiler sums up the diagnostics on its own, cargo does it too.
+    let summary = raw.spans.is_empty()
+        && (raw.message.starts_with("aborting due to") || raw.message.ends_with(" emitted"));
+    let diagnostic = level.filter(|_| !summary).map(|level| Diagnostic {
+        level,
+        code: raw.code.map(|code| code.code),
+        message: raw.message,
+        lines: rendered
+            .lines()
+            .map(|line| crate::strip_colors(line).into_owned())
+            .collect(),
+        crate_name: package_name(&package_id).map(str::to_owned),
+    });
+    Some(Message::Compiler {
+        diagnostic,
+        rendered,
+    })
+}
+
+/// Extracts the package name from its id, which is either like
+/// `foo 0.1.0 (path+file:///tmp/foo)` or, since cargo 1.77, like
+/// `registry+https://github.com/rust-lang/crates.io-index#foo@0.1.0` or
+/// `path+file:///tmp/foo#0.1.0` (when the name matches the directory).
+fn package_name(package_id: &str) -> Option<&str> {
+    let (url, fragment) = match package_id.split_once('#') {
+        Some(parts) => parts,
+        None => return package_id.split_whitespace().next(),
+    };
+    match fragment.split_once('@') {
+        Some((name, _version)) => Some(name),
+        None => url.trim_end_matches('/').rsplit('/').next(),
+    }
+}
+
+#[test]
+fn parses_compiler_messages() {
+    let line = r#"{"reason":"compiler-message","package_id":"path+file:///tmp/foo#0.1.0","manifest_path":"/tmp/foo/Cargo.toml","target":{"name":"foo"},"message":{"rendered":"warning: unused variable: `x`\n --> src/main.rs:2:9\n","$message_type":"diagnostic","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `x`","spans":[{}]}}"#;
+    let Some(Message::Compiler {
+        diagnostic: Some(diagnostic),
+        rendered,
+    }) = parse(line)
+    else {
+        panic!("not a diagnostic: {:?}", parse(line));
+    };
+    assert_eq!(
+        rendered,
+        "warning: unused variable: `x`\n --> src/main.rs:2:9\n"
+    );
+    assert_eq!(diagnostic.level, Level::Warning);
+    assert_eq!(diagnostic.code.as_deref(), Some("unused_variables"));
+    assert_eq!(diagnostic.crate_name.as_deref(), Some("foo"));
+    assert_eq!(diagnostic.lines.len(), 2);
+
+    let line = r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///tmp/foo)","message":{"rendered":"warning: 1 warning emitted\n\n","code":null,"level":"warning","message":"1 warning emitted","spans":[]}}"#;
+    assert_eq!(
+        parse(line),
+        Some(Message::Compiler {
+            diagnostic: None,
+            rendered: "warning: 1 warning emitted\n\n".into(),
+        })
+    );
+
+    let line = r#"{"reason":"build-finished","success":true}"#;
+    assert_eq!(parse(line), Some(Message::Other));
+    assert_eq!(parse("test tests::it_works ... ok\n"), None);
+}
+
+#[test]
+fn extracts_package_names() {
+    assert_eq!(
+        package_name("foo 0.1.0 (path+file:///tmp/foo)"),
+        Some("foo")
+    );
+    assert_eq!(
+        package_name("registry+https://github.com/rust-lang/crates.io-index#serde@1.0.0"),
+        Some("serde")
+    );
+    assert_eq!(package_name("path+file:///tmp/bar#0.1.0"), Some("bar"));
+}
@@ -0,0 +1,99 @@
+//! The desktop notification sent with `--notify` once cargo exits, for those
+//! who switch to another window while a long build runs.
+//!
+//! The notifications are sent with whatever the system already has:
+//! `notify-send` on Linux and the BSDs, `osascript` on macOS and a PowerShell
+//! toast on Windows.
+
+use std::{
+    io,
+    process::{Command, Stdio},
+};
+
+/// Whether there's a desktop to display the notification on. Nobody's going to
+/// see it in a CI job or over SSH.
+pub fn available() -> bool {
+    let set = |key| std::env::var_os(key).is_some_and(|value| !value.is_empty());
+    if set("CI") || set("SSH_CONNECTION") {
+        return false;
+    }
+    if cfg!(any(windows, target_os = "macos")) {
+        true
+    } else {
+        set("DISPLAY") || set("WAYLAND_DISPLAY")
+    }
+}
+
+#[cfg(windows)]
+const TOAST: &str = "\
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null
+$xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$texts = $xml.GetElementsByTagName('text')
+$texts.Item(0).AppendChild($xml.CreateTextNode($env:CARGO_SINGLE_LINE_TITLE)) > $null
+$texts.Item(1).AppendChild($xml.CreateTextNode($env:CARGO_SINGLE_LINE_BODY)) > $null
+$toast = [Windows.UI.Notifications.ToastNotification]::new($xml)
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('cargo-single-line').Show($toast)";
+
+/// The command displaying the notification. The texts are passed as the
+/// arguments (or the environment), so there's nothing to escape.
+fn command(title: &str, body: &str, failed: bool) -> Command {
+    #[cfg(windows)]
+    {
+        let _ = failed;
+        let mut cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-NonInteractive", "-Command", TOAST])
+            .env("CARGO_SINGLE_LINE_TITLE", title)
+            .env("CARGO_SINGLE_LINE_BODY", body);
+        cmd
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = failed;
+        let mut cmd = Command::new("osascript");
+        cmd.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            body,
+        ]);
+        cmd
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let mut cmd = Command::new("notify-send");
+        cmd.args([
+            "--app-name=cargo-single-line",
+            if failed {
+                "--urgency=critical"
+            } else {
+                "--urgency=normal"
+            },
+            title,
+            body,
+        ]);
+        cmd
+    }
+}
+
+/// Displays the notification, waiting for the command to hand it over.
+pub fn send(title: &str, body: &str, failed: bool) -> io::Result<()> {
+    let mut cmd = command(title, body, failed);
+    let status = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{:?} exited with {}",
+            cmd.get_program(),
+            status
+        )))
+    }
+}
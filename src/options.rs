@@ -0,0 +1,1027 @@
+//! Command line options of the plugin itself.
+//!
+//! The options are expected to come right after `single-line` and before the
+//! cargo subcommand, e.g. `cargo single-line --stall-timeout 10 build`.
+//! Everything starting from the first argument that is not recognized as an
+//! option of the plugin is forwarded to cargo as is.
+//!
+//! The defaults can be changed in the [configuration files](crate::config).
+
+use std::{ffi::OsString, iter::Peekable, path::PathBuf, time::Duration};
+
+use crate::{
+    child_env::EnvChange,
+    config::Value,
+    handoff::RunStatus,
+    template::{Icons, Template},
+    tools::Tool,
+    verbosity::Verbosity,
+};
+
+/// Default stall timeout, see [`Options::stall_timeout`].
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options of the plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// How long cargo is allowed to stay in a network-bound phase (updating
+    /// the index, downloading crates) without printing anything before the
+    /// build is considered stalled. `None` disables the detection.
+    pub stall_timeout: Option<Duration>,
+
+    /// Whether to terminate cargo once the build is considered stalled.
+    pub exit_on_stall: bool,
+
+    /// How much of the output is displayed.
+    pub verbosity: Verbosity,
+
+    /// Whether to display the progress indicator on the status line.
+    pub progress: bool,
+
+    /// Whether to display the spinner and the elapsed time when the status
+    /// stays the same for a while.
+    pub spinner: bool,
+
+    /// Whether to fold the test harness output of `cargo test` into the
+    /// status line.
+    pub test_progress: bool,
+
+    /// How the `Compiling`/`Checking` lines look on the status line, instead
+    /// of the cargo's own lines.
+    pub format: Option<Template>,
+
+    /// The icons of the `{icon}` field of the format.
+    pub icons: Icons,
+
+    /// Whether to highlight the names of the workspace members on the status
+    /// line.
+    pub workspace_highlight: bool,
+
+    /// Whether to print the lines of the workspace members instead of putting
+    /// them on the status line.
+    pub echo_workspace_members: bool,
+
+    /// How many of the most recent statuses are kept visible.
+    pub window: usize,
+
+    /// How many of the most recent status lines are printed again once the
+    /// build fails.
+    pub replay: usize,
+
+    /// Whether to print the heartbeats instead of drawing the status line,
+    /// even on a terminal.
+    pub ci: bool,
+
+    /// The longest interval between the heartbeats.
+    pub heartbeat: Duration,
+
+    /// The most crates compiled between the heartbeats.
+    pub heartbeat_crates: usize,
+
+    /// Whether to record the builds into the project's history.
+    pub history: bool,
+
+    /// Whether to check for a newer version of the plugin.
+    pub update_check: bool,
+
+    /// Print the command instead of running it.
+    pub dry_run: bool,
+
+    /// Whether to run the command again whenever the project changes.
+    pub watch: bool,
+
+    /// The transcript to feed through the filtering instead of running cargo.
+    pub dry_filter: Option<PathBuf>,
+
+    /// Whether to play the transcript at the pace it's been recorded at.
+    pub realtime: bool,
+
+    /// Where to record the transcript of the build to.
+    pub record: Option<PathBuf>,
+
+    /// Changes to the child's environment, in the order they are given.
+    pub env: Vec<EnvChange>,
+
+    /// Whether to start the child with a minimal environment.
+    pub clean_env: bool,
+
+    /// How much slower than the baseline (in percents) a build may be before
+    /// it's reported as a regression.
+    pub regression_threshold: f64,
+
+    /// Whether to fail when the build time has regressed.
+    pub fail_on_regression: bool,
+
+    /// Where to copy the complete cargo output to.
+    pub log_file: Option<PathBuf>,
+
+    /// Whether to keep the color codes in the log file.
+    pub log_colors: bool,
+
+    /// Where to write the build events to: `fd:N` for a file descriptor, or a
+    /// path.
+    pub emit_events: Option<String>,
+
+    /// Where to write the SVG timeline of the build.
+    pub timeline_svg: Option<PathBuf>,
+
+    /// Where to write the HTML report of the build.
+    pub report_html: Option<PathBuf>,
+
+    /// Where to append the Markdown summary of the build.
+    pub summary_md: Option<PathBuf>,
+
+    /// Whether to report how much of the build the slow crates took.
+    pub slow_crates_summary: bool,
+
+    /// Whether to draw the compilation throughput after the build.
+    pub throughput: bool,
+
+    /// Whether to print the slowest crates of the build after it.
+    pub timings_lite: bool,
+
+    /// Whether to send a desktop notification once cargo exits.
+    pub notify: bool,
+
+    /// Whether to mirror the status to the terminal's title.
+    pub set_title: bool,
+
+    /// Where to keep the status as plain text, for the status bars.
+    pub status_file: Option<PathBuf>,
+
+    /// The [hooks](crate::hooks): the commands to run once cargo succeeds,
+    /// fails, or reports warnings.
+    pub on_success: Option<String>,
+    pub on_failure: Option<String>,
+    pub on_warning: Option<String>,
+
+    /// What to prefix the lines with, when several instances share the
+    /// terminal.
+    pub tag: Option<String>,
+
+    /// Whether to ask cargo for the JSON messages and display the diagnostics
+    /// from them.
+    pub json: bool,
+
+    /// Whether to put the status lines printed to stdout (like the progress
+    /// of `cargo run`'s program) on the status line as well.
+    pub filter_stdout: bool,
+
+    /// Whether to hold the diagnostics back until the end of the build and
+    /// print their summary then.
+    pub summary: bool,
+
+    /// Whether to print the diagnostics as one-liners while building, and in
+    /// full after the build.
+    pub compact_diagnostics: bool,
+
+    /// Whether to explain why the workspace crates were rebuilt.
+    pub explain_rebuild: bool,
+
+    /// Whether to hold back the rustc invocations of `cargo -v`, printing only
+    /// the one of a crate failing to compile.
+    pub hide_invocations: bool,
+
+    /// Whether to listen to the keyboard shortcuts.
+    pub keys: bool,
+
+    /// What to do with the build status once `cargo run` starts the program.
+    pub run_status: RunStatus,
+
+    /// The tool whose output to compact, instead of the one picked by the
+    /// cargo subcommand.
+    pub tool: Option<Tool>,
+
+    /// Whether to run cargo in a pseudo console.
+    pub pty: bool,
+
+    /// The language of the messages, instead of the locale's.
+    pub lang: Option<String>,
+
+    /// The table mapping the translated cargo's verbs back to English.
+    pub locale_prefixes: Option<PathBuf>,
+
+    /// Whether to run cargo in the C locale, so that its output is English.
+    pub force_english: bool,
+
+    /// Whether to apply the default redaction rules.
+    pub redact_defaults: bool,
+
+    /// Additional redaction rules, regular expressions.
+    pub redact: Vec<String>,
+
+    /// Whether the cargo's own lines are put on the status line.
+    pub default_prefixes: bool,
+
+    /// Regular expressions of the additional lines to put on the status line.
+    pub collapse_patterns: Vec<String>,
+
+    /// The [globs](crate::crate_filter) of the crates to show on the status
+    /// line, `--only`, and of the ones not to, `--hide`.
+    pub only: Vec<String>,
+    pub hide: Vec<String>,
+
+    /// The `--color` to run cargo with when none is given, set in the
+    /// configuration files or to `never` with `--no-color`.
+    pub color: Option<String>,
+
+    /// The [presets](crate::preset) given with `--preset`, applied beneath
+    /// the configuration files by [`config::load`](crate::config::load).
+    pub presets: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            stall_timeout: Some(DEFAULT_STALL_TIMEOUT),
+            exit_on_stall: false,
+            verbosity: Verbosity::Normal,
+            progress: true,
+            spinner: true,
+            test_progress: true,
+            format: None,
+            icons: Icons::Unicode,
+            workspace_highlight: true,
+            echo_workspace_members: false,
+            window: 1,
+            replay: 0,
+            ci: false,
+            heartbeat: crate::heartbeat::DEFAULT_INTERVAL,
+            heartbeat_crates: crate::heartbeat::DEFAULT_CRATES,
+            history: true,
+            update_check: std::env::var_os(crate::update::NO_CHECK_ENV).is_none(),
+            dry_run: false,
+            watch: false,
+            dry_filter: None,
+            realtime: false,
+            record: None,
+            env: Vec::new(),
+            clean_env: false,
+            regression_threshold: crate::baseline::DEFAULT_THRESHOLD,
+            fail_on_regression: false,
+            log_file: std::env::var_os(crate::log_file::ENV)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            log_colors: false,
+            emit_events: None,
+            timeline_svg: None,
+            report_html: None,
+            summary_md: None,
+            slow_crates_summary: false,
+            throughput: false,
+            timings_lite: false,
+            notify: false,
+            set_title: false,
+            status_file: None,
+            on_success: None,
+            on_failure: None,
+            on_warning: None,
+            tag: None,
+            json: false,
+            filter_stdout: false,
+            summary: false,
+            compact_diagnostics: false,
+            explain_rebuild: false,
+            hide_invocations: false,
+            keys: true,
+            run_status: RunStatus::Keep,
+            tool: None,
+            pty: false,
+            lang: None,
+            locale_prefixes: None,
+            force_english: false,
+            redact_defaults: true,
+            redact: Vec::new(),
+            default_prefixes: true,
+            collapse_patterns: Vec::new(),
+            only: Vec::new(),
+            hide: Vec::new(),
+            color: None,
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Consumes the plugin's options from the beginning of the `args`,
+    /// leaving the rest of the arguments (that are meant for cargo) intact.
+    /// A `--` ends the options explicitly, and is consumed as well.
+    pub fn parse<I>(args: &mut Peekable<I>) -> Result<Self, String>
+    where
+        I: Iterator<Item = OsString>,
+    {
+        Self::parse_with(Options::default(), args)
+    }
+
+    /// Same as [`parse`](Self::parse), but the options not given in the `args`
+    /// are taken from the `options` instead of the defaults.
+    pub fn parse_with<I>(mut options: Options, args: &mut Peekable<I>) -> Result<Self, String>
+    where
+        I: Iterator<Item = OsString>,
+    {
+        // Our options are all valid UTF-8, so a non-UTF-8 argument must be
+        // something for cargo.
+        while let Some(arg) = args.peek().and_then(|arg| arg.to_str()) {
+            let arg = arg.to_owned();
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (arg.as_str(), None),
+            };
+            match name {
+                "--stall-timeout" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    let seconds: u64 = value
+                        .parse()
+                        .map_err(|e| format!("invalid value {:?} for {}: {}", value, name, e))?;
+                    options.stall_timeout = if seconds == 0 {
+                        None
+                    } else {
+                        Some(Duration::from_secs(seconds))
+                    };
+                }
+                "--exit-on-stall" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.exit_on_stall = true;
+                }
+                "-q" | "--quiet" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.verbosity = Verbosity::Quiet;
+                }
+                "-v" | "--verbose" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.verbosity = options.verbosity.max(Verbosity::Normal).increase();
+                }
+                "-vv" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.verbosity = Verbosity::VeryVerbose;
+                }
+                "--no-progress" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.progress = false;
+                }
+                "--no-spinner" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.spinner = false;
+                }
+                "--no-test-progress" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.test_progress = false;
+                }
+                "--format" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.format = Some(
+                        Template::parse(&value)
+                            .map_err(|e| format!("invalid value for {}: {}", name, e))?,
+                    );
+                }
+                "--icons" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.icons = Icons::from_name(&value).ok_or_else(|| {
+                        format!(
+                            "invalid value {:?} for {}, expected unicode or ascii",
+                            value, name
+                        )
+                    })?;
+                }
+                "--no-workspace-highlight" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.workspace_highlight = false;
+                }
+                "--echo-workspace-members" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.echo_workspace_members = true;
+                }
+                "--window" | "--lines" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.window = value
+                        .parse()
+                        .ok()
+                        .filter(|window: &usize| *window > 0)
+                        .ok_or_else(|| format!("invalid value {:?} for {}", value, name))?;
+                }
+                "--replay" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.replay = value
+                        .parse()
+                        .map_err(|_| format!("invalid value {:?} for {}", value, name))?;
+                }
+                "--ci" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.ci = true;
+                }
+                "--heartbeat" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.heartbeat = value
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &u64| *seconds > 0)
+                        .map(Duration::from_secs)
+                        .ok_or_else(|| format!("invalid value {:?} for {}", value, name))?;
+                }
+                "--heartbeat-crates" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.heartbeat_crates = value
+                        .parse()
+                        .ok()
+                        .filter(|crates: &usize| *crates > 0)
+                        .ok_or_else(|| format!("invalid value {:?} for {}", value, name))?;
+                }
+                "--no-history" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.history = false;
+                }
+                "--no-update-check" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.update_check = false;
+                }
+                "--dry-run" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.dry_run = true;
+                }
+                "--watch" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.watch = true;
+                }
+                "--dry-filter" => {
+                    let _ = args.next();
+                    options.dry_filter = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--realtime" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.realtime = true;
+                }
+                "--record" => {
+                    let _ = args.next();
+                    options.record = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--env" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.env.push(EnvChange::parse_set(&value)?);
+                }
+                "--unset-env" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.env.push(EnvChange::parse_unset(&value)?);
+                }
+                "--clean-env" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.clean_env = true;
+                }
+                "--regression-threshold" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.regression_threshold = value
+                        .parse()
+                        .ok()
+                        .filter(|threshold: &f64| *threshold >= 0.0)
+                        .ok_or_else(|| format!("invalid value {:?} for {}", value, name))?;
+                }
+                "--fail-on-regression" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.fail_on_regression = true;
+                }
+                "--log-file" => {
+                    let _ = args.next();
+                    options.log_file = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--emit-events" => {
+                    let _ = args.next();
+                    options.emit_events = Some(option_value(name, inline_value, args)?);
+                }
+                "--log-colors" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.log_colors = true;
+                }
+                "--timeline-svg" => {
+                    let _ = args.next();
+                    options.timeline_svg = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--report-html" => {
+                    let _ = args.next();
+                    options.report_html = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--summary-md" => {
+                    let _ = args.next();
+                    options.summary_md = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--slow-crates-summary" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.slow_crates_summary = true;
+                }
+                "--throughput" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.throughput = true;
+                }
+                "--timings-lite" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.timings_lite = true;
+                }
+                "--no-color" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.color = Some("never".to_owned());
+                }
+                "--notify" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.notify = true;
+                }
+                "--status-file" => {
+                    let _ = args.next();
+                    options.status_file = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--on-success" => {
+                    let _ = args.next();
+                    options.on_success = Some(option_value(name, inline_value, args)?);
+                }
+                "--on-failure" => {
+                    let _ = args.next();
+                    options.on_failure = Some(option_value(name, inline_value, args)?);
+                }
+                "--on-warning" => {
+                    let _ = args.next();
+                    options.on_warning = Some(option_value(name, inline_value, args)?);
+                }
+                "--tag" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    if value.is_empty() {
+                        return Err(format!("invalid value {:?} for {}", value, name));
+                    }
+                    options.tag = Some(value);
+                }
+                "--set-title" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.set_title = true;
+                }
+                "--json" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.json = true;
+                }
+                "--filter-stdout" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.filter_stdout = true;
+                }
+                "--summary" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.summary = true;
+                }
+                "--compact-diagnostics" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.compact_diagnostics = true;
+                }
+                "--explain-rebuild" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.explain_rebuild = true;
+                }
+                "--hide-invocations" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.hide_invocations = true;
+                }
+                "--no-keys" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.keys = false;
+                }
+                "--run-status" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.run_status = RunStatus::from_name(&value).ok_or_else(|| {
+                        format!(
+                            "invalid value {:?} for {}, expected keep, clear or collapse",
+                            value, name
+                        )
+                    })?;
+                }
+                "--tool" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    options.tool = Some(Tool::from_name(&value).ok_or_else(|| {
+                        format!(
+                            "invalid value {:?} for {}, expected miri, fuzz, embedded or android",
+                            value, name
+                        )
+                    })?);
+                }
+                "--pty" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.pty = true;
+                }
+                "--lang" => {
+                    let _ = args.next();
+                    options.lang = Some(option_value(name, inline_value, args)?);
+                }
+                "--locale-prefixes" => {
+                    let _ = args.next();
+                    options.locale_prefixes = Some(option_value(name, inline_value, args)?.into());
+                }
+                "--force-english" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.force_english = true;
+                }
+                "--redact" => {
+                    let _ = args.next();
+                    options.redact.push(option_value(name, inline_value, args)?);
+                }
+                "--no-default-redaction" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.redact_defaults = false;
+                }
+                "--collapse-pattern" => {
+                    let _ = args.next();
+                    options
+                        .collapse_patterns
+                        .push(option_value(name, inline_value, args)?);
+                }
+                "--only" => {
+                    let _ = args.next();
+                    options.only.push(option_value(name, inline_value, args)?);
+                }
+                "--hide" => {
+                    let _ = args.next();
+                    options.hide.push(option_value(name, inline_value, args)?);
+                }
+                "--no-default-prefixes" => {
+                    let _ = args.next();
+                    no_value(name, inline_value)?;
+                    options.default_prefixes = false;
+                }
+                "--preset" => {
+                    let _ = args.next();
+                    let value = option_value(name, inline_value, args)?;
+                    let count = options.presets.len();
+                    options
+                        .presets
+                        .extend(crate::preset::names(&value).map(str::to_owned));
+                    if options.presets.len() == count {
+                        return Err(format!("{} requires a name", name));
+                    }
+                }
+                "--" => {
+                    let _ = args.next();
+                    break;
+                }
+                _ => break,
+            }
+        }
+        Ok(options)
+    }
+
+    /// Applies a setting from a [configuration file](crate::config).
+    pub fn apply(&mut self, key: &str, value: Value) -> Result<(), String> {
+        let values = match (key, value) {
+            ("color", Value::String(color)) => {
+                if !matches!(color.as_str(), "auto" | "always" | "never") {
+                    return Err(format!(
+                        "invalid value {:?} for color, expected auto, always or never",
+                        color
+                    ));
+                }
+                self.color = Some(color);
+                return Ok(());
+            }
+            (key, Value::Bool(value)) => {
+                let flag = self
+                    .flag(key)
+                    .ok_or_else(|| format!("{} is not a flag", key))?;
+                *flag = value;
+                return Ok(());
+            }
+            (key, _) if self.flag(key).is_some() => {
+                return Err(format!("{} expects true or false", key));
+            }
+            (_, Value::String(value)) => vec![value],
+            (_, Value::Array(values)) => values,
+        };
+        for value in values {
+            let mut args =
+                std::iter::once(OsString::from(format!("--{}={}", key, value))).peekable();
+            *self = Self::parse_with(std::mem::take(self), &mut args)?;
+            if args.peek().is_some() {
+                return Err(format!("unknown setting {:?}", key));
+            }
+        }
+        Ok(())
+    }
+
+    /// The flag that is set with `key = true` in the configuration files.
+    fn flag(&mut self, key: &str) -> Option<&mut bool> {
+        Some(match key {
+            "exit-on-stall" => &mut self.exit_on_stall,
+            "progress" => &mut self.progress,
+            "ci" => &mut self.ci,
+            "log-colors" => &mut self.log_colors,
+            "spinner" => &mut self.spinner,
+            "test-progress" => &mut self.test_progress,
+            "workspace-highlight" => &mut self.workspace_highlight,
+            "echo-workspace-members" => &mut self.echo_workspace_members,
+            "history" => &mut self.history,
+            "update-check" => &mut self.update_check,
+            "clean-env" => &mut self.clean_env,
+            "fail-on-regression" => &mut self.fail_on_regression,
+            "slow-crates-summary" => &mut self.slow_crates_summary,
+            "throughput" => &mut self.throughput,
+            "timings-lite" => &mut self.timings_lite,
+            "notify" => &mut self.notify,
+            "set-title" => &mut self.set_title,
+            "json" => &mut self.json,
+            "filter-stdout" => &mut self.filter_stdout,
+            "summary" => &mut self.summary,
+            "compact-diagnostics" => &mut self.compact_diagnostics,
+            "explain-rebuild" => &mut self.explain_rebuild,
+            "hide-invocations" => &mut self.hide_invocations,
+            "keys" => &mut self.keys,
+            "pty" => &mut self.pty,
+            "force-english" => &mut self.force_english,
+            "default-redaction" => &mut self.redact_defaults,
+            "default-prefixes" => &mut self.default_prefixes,
+            _ => return None,
+        })
+    }
+}
+
+/// Extracts a value of the option, either from the `--name=value` form or from
+/// the following argument.
+fn option_value<I>(
+    name: &str,
+    inline_value: Option<String>,
+    args: &mut Peekable<I>,
+) -> Result<String, String>
+where
+    I: Iterator<Item = OsString>,
+{
+    if let Some(value) = inline_value {
+        return Ok(value);
+    }
+    let value = args
+        .next()
+        .ok_or_else(|| format!("{} requires a value", name))?;
+    value
+        .into_string()
+        .map_err(|value| format!("invalid value {:?} for {}", value, name))
+}
+
+/// Makes sure a flag is not given a value.
+fn no_value(name: &str, inline_value: Option<String>) -> Result<(), String> {
+    match inline_value {
+        Some(value) => Err(format!("{} does not take a value, got {:?}", name, value)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+fn parse_strs(args: &[&str]) -> (Result<Options, String>, Vec<OsString>) {
+    let mut args = args.iter().map(OsString::from).peekable();
+    let options = Options::parse(&mut args);
+    (options, args.collect())
+}
+
+#[test]
+fn stops_at_cargo_arguments() {
+    let (options, rest) = parse_strs(&["build", "--stall-timeout", "5"]);
+    assert_eq!(options.unwrap(), Options::default());
+    assert_eq!(rest, ["build", "--stall-timeout", "5"]);
+
+    // `cargo publish --dry-run` is not ours.
+    let (options, rest) = parse_strs(&["--dry-run", "publish", "--dry-run"]);
+    assert!(options.unwrap().dry_run);
+    assert_eq!(rest, ["publish", "--dry-run"]);
+
+    // Neither are cargo's global options and the toolchain selectors.
+    let (options, rest) = parse_strs(&["--window", "2", "--", "--locked", "+nightly"]);
+    assert_eq!(options.unwrap().window, 2);
+    assert_eq!(rest, ["--locked", "+nightly"]);
+}
+
+#[test]
+fn parses_stall_options() {
+    let (options, rest) = parse_strs(&["--stall-timeout", "5", "--exit-on-stall", "check"]);
+    let options = options.unwrap();
+    assert_eq!(options.stall_timeout, Some(Duration::from_secs(5)));
+    assert!(options.exit_on_stall);
+    assert_eq!(rest, ["check"]);
+
+    let (options, _) = parse_strs(&["--stall-timeout=0"]);
+    assert_eq!(options.unwrap().stall_timeout, None);
+
+    let (options, _) = parse_strs(&["--stall-timeout"]);
+    assert!(options.is_err());
+    let (options, _) = parse_strs(&["--exit-on-stall=yes"]);
+    assert!(options.is_err());
+}
+
+#[test]
+fn parses_window() {
+    let (options, rest) = parse_strs(&["--window", "3", "build"]);
+    assert_eq!(options.unwrap().window, 3);
+    assert_eq!(rest, ["build"]);
+
+    let (options, _) = parse_strs(&["--lines=4"]);
+    assert_eq!(options.unwrap().window, 4);
+
+    let (options, rest) = parse_strs(&["--replay", "5", "build"]);
+    assert_eq!(options.unwrap().replay, 5);
+    assert_eq!(rest, ["build"]);
+
+    let (options, _) = parse_strs(&["--window=0"]);
+    assert!(options.is_err());
+}
+
+#[test]
+fn parses_presets() {
+    let (options, rest) = parse_strs(&["--preset", "ci,fancy", "--preset=mine", "build"]);
+    assert_eq!(options.unwrap().presets, ["ci", "fancy", "mine"]);
+    assert_eq!(rest, ["build"]);
+
+    let (options, _) = parse_strs(&["--preset=,"]);
+    assert!(options.is_err());
+}
+
+#[test]
+fn parses_crate_globs() {
+    let (options, rest) = parse_strs(&[
+        "--only",
+        "my-org-*",
+        "--hide=*-macros",
+        "--only=app",
+        "check",
+    ]);
+    let options = options.unwrap();
+    assert_eq!(options.only, ["my-org-*", "app"]);
+    assert_eq!(options.hide, ["*-macros"]);
+    assert_eq!(rest, ["check"]);
+}
+
+#[test]
+fn parses_format() {
+    let (options, rest) = parse_strs(&["--format", "{icon} {crate:bold}", "--icons=ascii", "b"]);
+    let options = options.unwrap();
+    assert_eq!(
+        options.format,
+        Some(Template::parse("{icon} {crate:bold}").unwrap())
+    );
+    assert_eq!(options.icons, Icons::Ascii);
+    assert_eq!(rest, ["b"]);
+
+    let (options, _) = parse_strs(&["--format={nope}"]);
+    assert!(options.is_err());
+    let (options, _) = parse_strs(&["--icons=emoji"]);
+    assert!(options.is_err());
+}
+
+#[test]
+fn parses_heartbeat() {
+    let (options, rest) =
+        parse_strs(&["--ci", "--heartbeat=10", "--heartbeat-crates", "5", "test"]);
+    let options = options.unwrap();
+    assert!(options.ci);
+    assert_eq!(options.heartbeat, Duration::from_secs(10));
+    assert_eq!(options.heartbeat_crates, 5);
+    assert_eq!(rest, ["test"]);
+
+    let (options, _) = parse_strs(&["--heartbeat", "0"]);
+    assert!(options.is_err());
+}
+
+#[test]
+fn parses_verbosity() {
+    let (options, rest) = parse_strs(&["-q", "build", "-v"]);
+    assert_eq!(options.unwrap().verbosity, Verbosity::Quiet);
+    assert_eq!(rest, ["build", "-v"]);
+
+    let (options, _) = parse_strs(&["-v", "--verbose"]);
+    assert_eq!(options.unwrap().verbosity, Verbosity::VeryVerbose);
+    let (options, _) = parse_strs(&["-vv"]);
+    assert_eq!(options.unwrap().verbosity, Verbosity::VeryVerbose);
+}
+
+#[test]
+fn parses_log_file() {
+    let (options, rest) = parse_strs(&["--dry-filter=build.jsonl", "--realtime", "build"]);
+    let options = options.unwrap();
+    assert_eq!(options.dry_filter, Some(PathBuf::from("build.jsonl")));
+    assert!(options.realtime);
+    assert_eq!(rest, ["build"]);
+
+    let (options, rest) = parse_strs(&["--log-file", "build.log", "--log-colors", "build"]);
+    let options = options.unwrap();
+    assert_eq!(options.log_file, Some(PathBuf::from("build.log")));
+    assert!(options.log_colors);
+    assert_eq!(rest, ["build"]);
+}
+
+#[test]
+fn parses_hooks() {
+    let (options, rest) = parse_strs(&[
+        "--on-success",
+        "./deploy.sh",
+        "--on-failure=paplay fail.oga",
+        "build",
+    ]);
+    let options = options.unwrap();
+    assert_eq!(options.on_success.as_deref(), Some("./deploy.sh"));
+    assert_eq!(options.on_failure.as_deref(), Some("paplay fail.oga"));
+    assert_eq!(options.on_warning, None);
+    assert_eq!(rest, ["build"]);
+
+    let (options, _) = parse_strs(&["--status-file=/tmp/status", "build"]);
+    assert_eq!(
+        options.unwrap().status_file,
+        Some(PathBuf::from("/tmp/status"))
+    );
+}
+
+#[test]
+fn parses_env_options() {
+    let (options, rest) = parse_strs(&[
+        "--env",
+        "RUSTFLAGS=-Dwarnings",
+        "--unset-env=RUSTC_WRAPPER",
+        "--env=A=B=C",
+        "--clean-env",
+        "build",
+    ]);
+    let options = options.unwrap();
+    assert_eq!(
+        options.env,
+        [
+            EnvChange::Set("RUSTFLAGS".into(), "-Dwarnings".into()),
+            EnvChange::Unset("RUSTC_WRAPPER".into()),
+            EnvChange::Set("A".into(), "B=C".into()),
+        ]
+    );
+    assert!(options.clean_env);
+    assert_eq!(rest, ["build"]);
+}
+
+#[test]
+fn applies_settings() {
+    let mut options = Options::default();
+    options.apply("progress", Value::Bool(false)).unwrap();
+    options
+        .apply("stall-timeout", Value::String("10".into()))
+        .unwrap();
+    options
+        .apply("redact", Value::Array(vec!["a".into(), "b".into()]))
+        .unwrap();
+    assert!(!options.progress);
+    assert_eq!(options.stall_timeout, Some(Duration::from_secs(10)));
+    assert_eq!(options.redact, ["a", "b"]);
+
+    assert!(options.apply("dry-run", Value::Bool(true)).is_err());
+    assert!(options.apply("build", Value::String("x".into())).is_err());
+    assert!(options
+        .apply("throughput", Value::String("1".into()))
+        .is_err());
+    assert!(options
+        .apply("color", Value::String("sometimes".into()))
+        .is_err());
+
+    // The command line takes precedence.
+    let mut args = ["--stall-timeout", "5"]
+        .iter()
+        .map(OsString::from)
+        .peekable();
+    let options = Options::parse_with(options, &mut args).unwrap();
+    assert_eq!(options.stall_timeout, Some(Duration::from_secs(5)));
+    assert!(!options.progress);
+}
@@ -0,0 +1,39 @@
+//! Displaying long texts with the user's pager.
+
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+#[cfg(unix)]
+const DEFAULT_PAGER: &str = "less -R";
+#[cfg(not(unix))]
+const DEFAULT_PAGER: &str = "more";
+
+/// `$PAGER` is a shell command, possibly with arguments.
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Shows the `text` in `$PAGER` and waits until the pager exits.
+pub fn show(text: &str) -> io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_owned());
+    let mut child = shell_command(&pager).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager might quit before reading everything, that's fine.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Cleaning up after a panic.
+//!
+//! A panic, be it in the main loop or in the thread reading the cargo output,
+//! would leave the status line dangling, the terminal in the non-canonical
+//! mode (and with the build status for a title) and cargo running with nobody
+//! to read its output. The hook puts all of that in order before the panic is
+//! reported as usual, and then exits right away: with the output reader gone,
+//! the main loop would wait forever.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// The exit code of a panicking Rust program.
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// The process id of cargo, if it's running.
+static CHILD: AtomicU32 = AtomicU32::new(0);
+
+/// Whether the cleanup has been done already, in case several threads panic
+/// at once.
+static CLEANED_UP: AtomicBool = AtomicBool::new(false);
+
+/// Installs the panic hook.
+pub fn install() {
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !CLEANED_UP.swap(true, Ordering::SeqCst) {
+            crate::keys::restore_terminal();
+            crate::title::restore();
+            crate::status::finish_dangling();
+            let child = CHILD.swap(0, Ordering::SeqCst);
+            if child != 0 {
+                imp::kill(child);
+            }
+        }
+        report(info);
+        std::process::exit(PANIC_EXIT_CODE);
+    }));
+}
+
+/// Makes the hook stop the process with the `pid`.
+pub fn watch_child(pid: u32) {
+    CHILD.store(pid, Ordering::SeqCst);
+}
+
+/// Lets the process be once it has exited.
+pub fn forget_child() {
+    CHILD.store(0, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+mod imp {
+    pub fn kill(pid: u32) {
+        if let Ok(pid) = libc::pid_t::try_from(pid) {
+            // SAFETY: sending a signal has no memory safety implications.
+            unsafe { libc::kill(pid, libc::SIGTERM) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use windows_sys::Win32::{
+        Foundation::CloseHandle,
+        System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE},
+    };
+
+    pub fn kill(pid: u32) {
+        // SAFETY: the handle is checked and closed after use.
+        unsafe {
+            let process = OpenProcess(PROCESS_TERMINATE, 0, pid);
+            if !process.is_null() {
+                TerminateProcess(process, 1);
+                CloseHandle(process);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    pub fn kill(_pid: u32) {}
+}
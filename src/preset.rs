@@ -0,0 +1,166 @@
+//! Presets: named sets of settings selected at once with `--preset NAME` (or
+//! `CARGO_SINGLE_LINE_PRESET`, or `preset = "NAME"` in the configuration
+//! files), like `--preset ci` for the logs of a CI job.
+//!
+//! A few presets are built in, more are defined in the configuration files as
+//! lists of settings written like the files' own, and may refer to other
+//! presets:
+//!
+//! ```toml
+//! preset.nightly = ["preset = 'ci'", "window = 3", "color = 'never'"]
+//! ```
+//!
+//! Several presets are applied one after another, `--preset ci,verbose`. The
+//! presets are the base the configuration files and the command line options
+//! are applied over, so that anything set explicitly takes precedence.
+
+use crate::{
+    config::{self, Value},
+    options::Options,
+};
+
+/// The environment variable selecting the presets when `--preset` is not
+/// given.
+pub const ENV: &str = "CARGO_SINGLE_LINE_PRESET";
+
+/// The built-in presets, in the configuration files' syntax.
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        "minimal",
+        "window = 1\nprogress = false\nspinner = false\nsummary = false",
+    ),
+    (
+        "verbose",
+        "window = 6\nsummary = true\nslow-crates-summary = true\nthroughput = true",
+    ),
+    (
+        "ci",
+        "ci = true\ncolor = \"never\"\nheartbeat = 60\nsummary = true",
+    ),
+    (
+        "fancy",
+        "window = 4\nsummary = true\ncompact-diagnostics = true\nset-title = true\nthroughput = true",
+    ),
+];
+
+/// How deep the presets may refer to each other.
+const MAX_DEPTH: usize = 8;
+
+/// Splits a list of presets, like `ci,verbose`.
+pub fn names(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// The presets defined in the configuration files, on top of the built-in
+/// ones.
+#[derive(Debug, Default)]
+pub struct Presets {
+    defined: Vec<(String, Vec<(String, Value)>)>,
+}
+
+impl Presets {
+    /// Takes a `preset.NAME = [...]` setting. Returns `false` if the setting
+    /// is something else.
+    pub fn define(&mut self, key: &str, value: &Value) -> Result<bool, String> {
+        let Some(name) = key.strip_prefix("preset.") else {
+            return Ok(false);
+        };
+        let Value::Array(lines) = value else {
+            return Err(format!("{} expects a list of settings", key));
+        };
+        let settings = config::parse(&lines.join("\n")).map_err(|e| format!("{}: {}", key, e))?;
+        self.defined
+            .push((name.trim_matches('"').to_owned(), settings));
+        Ok(true)
+    }
+
+    /// The settings of the preset, the latest definition winning.
+    fn settings(&self, name: &str) -> Result<Vec<(String, Value)>, String> {
+        if let Some((_, settings)) = self
+            .defined
+            .iter()
+            .rev()
+            .find(|(defined, _)| defined == name)
+        {
+            return Ok(settings.clone());
+        }
+        if let Some((_, contents)) = BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+            return config::parse(contents);
+        }
+        let mut known: Vec<&str> = BUILTIN.iter().map(|(name, _)| *name).collect();
+        known.extend(self.defined.iter().map(|(name, _)| name.as_str()));
+        Err(format!(
+            "unknown preset {:?}, expected one of {}",
+            name,
+            known.join(", ")
+        ))
+    }
+
+    /// Applies the preset, along with the presets it refers to.
+    pub fn apply(&self, options: &mut Options, name: &str) -> Result<(), String> {
+        self.apply_nested(options, name, 0)
+    }
+
+    fn apply_nested(&self, options: &mut Options, name: &str, depth: usize) -> Result<(), String> {
+        if depth == MAX_DEPTH {
+            return Err(format!("preset {:?} refers to itself", name));
+        }
+        for (key, value) in self.settings(name)? {
+            match (key.as_str(), value) {
+                ("preset", Value::String(list)) => {
+                    for nested in names(&list) {
+                        self.apply_nested(options, nested, depth + 1)?;
+                    }
+                }
+                ("preset", _) => return Err(format!("preset {:?}: preset expects a name", name)),
+                (key, value) => options
+                    .apply(key, value)
+                    .map_err(|e| format!("preset {:?}: {}", name, e))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn applies_presets() {
+    let mut presets = Presets::default();
+    assert!(!presets
+        .define("window", &Value::String("3".into()))
+        .unwrap());
+    assert!(presets
+        .define(
+            "preset.nightly",
+            &Value::Array(vec!["preset = 'ci'".into(), "window = 3".into()])
+        )
+        .unwrap());
+    assert!(presets
+        .define("preset.broken", &Value::String("window = 3".into()))
+        .is_err());
+
+    let mut options = Options::default();
+    presets.apply(&mut options, "nightly").unwrap();
+    assert!(options.ci);
+    assert!(options.summary);
+    assert_eq!(options.color.as_deref(), Some("never"));
+    assert_eq!(options.window, 3);
+
+    // Applied one after another.
+    presets.apply(&mut options, "minimal").unwrap();
+    assert!(options.ci);
+    assert!(!options.summary);
+    assert_eq!(options.window, 1);
+
+    assert!(presets.apply(&mut options, "nope").is_err());
+    presets
+        .define("preset.loop", &Value::Array(vec!["preset = 'loop'".into()]))
+        .unwrap();
+    assert!(presets.apply(&mut options, "loop").is_err());
+
+    for (name, _) in BUILTIN {
+        presets.apply(&mut Options::default(), name).unwrap();
+    }
+    assert_eq!(names(" ci, ,fancy").collect::<Vec<_>>(), ["ci", "fancy"]);
+}
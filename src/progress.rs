@@ -0,0 +1,118 @@
+//! Build progress, i.e. how many crates are done out of how many.
+
+use crate::{cargo_args::CargoArgs, lockfile, unit::UNIT_PREFIXES};
+
+/// Counts compiled crates.
+#[derive(Debug)]
+pub struct Progress {
+    done: usize,
+    /// Estimated total amount of crates to be compiled.
+    total: Option<usize>,
+}
+
+impl Progress {
+    pub fn new(total: Option<usize>) -> Self {
+        Progress { done: 0, total }
+    }
+
+    /// Takes a note of a line printed by cargo.
+    pub fn observe(&mut self, line: &str) {
+        if crate::starts_with(line, UNIT_PREFIXES) {
+            self.done += 1;
+        }
+    }
+
+    /// How many crates have been compiled so far.
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// The estimated total amount of crates, if known.
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    /// Prepends the progress indicator to the status line. If the total is not
+    /// known, the line is returned as is.
+    pub fn decorate(&self, line: &str) -> String {
+        let total = match self.total {
+            // The estimate might be off, and it's better to show nothing than
+            // nonsense like "120%".
+            Some(total) if total >= self.done && total != 0 => total,
+            _ => return line.to_owned(),
+        };
+        let width = total.to_string().len();
+        format!(
+            "[{:>width$}/~{} {:>3}%] {}",
+            self.done,
+            total,
+            self.done * 100 / total,
+            line,
+            width = width,
+        )
+    }
+}
+
+/// Estimates how many crates cargo is going to compile by looking at
+/// `Cargo.lock`, which is pretty cheap compared to asking cargo itself.
+///
+/// The estimate is an upper bound: the lock file lists dependencies for all the
+/// platforms and features, and the crates that are up to date are not compiled
+/// again at all.
+pub fn estimate_total(cargo_args: &CargoArgs) -> Option<usize> {
+    let contents = std::fs::read_to_string(lockfile::find(&cargo_args.manifest_dir()?)?).ok()?;
+    Some(count_units(
+        &lockfile::packages(&contents),
+        &cargo_args.packages,
+    ))
+}
+
+/// Counts the packages that are expected to be compiled. When specific packages
+/// are selected with `-p`, the rest of the workspace members are not built.
+fn count_units(packages: &[lockfile::Package], selected: &[String]) -> usize {
+    packages
+        .iter()
+        .filter(|package| {
+            package.has_source
+                || selected.is_empty()
+                || selected.iter().any(|name| name == &package.name)
+        })
+        .count()
+}
+
+#[test]
+fn decorates_status() {
+    let mut progress = Progress::new(Some(40));
+    for _ in 0..10 {
+        progress.observe("   Compiling foo v0.1.0\n");
+    }
+    progress.observe("    Updating crates.io index\n");
+    assert_eq!(
+        progress.decorate("   Compiling foo v0.1.0"),
+        "[10/~40  25%]    Compiling foo v0.1.0"
+    );
+
+    // An obviously wrong estimate is not displayed.
+    let mut progress = Progress::new(Some(5));
+    for _ in 0..6 {
+        progress.observe("    Checking foo v0.1.0\n");
+    }
+    assert_eq!(progress.decorate("    Checking foo"), "    Checking foo");
+}
+
+#[test]
+fn counts_selected_packages() {
+    let package = |name: &str, has_source| lockfile::Package {
+        name: name.into(),
+        version: "1.0.0".into(),
+        has_source,
+    };
+    let packages = [
+        package("foo", false),
+        package("bar", false),
+        package("baz", false),
+        package("serde", true),
+    ];
+    assert_eq!(count_units(&packages, &[]), 4);
+    assert_eq!(count_units(&packages, &["foo".into(), "bar".into()]), 3);
+}
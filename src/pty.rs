@@ -0,0 +1,485 @@
+//! Running cargo in a pseudo console.
+//!
+//! With the stderr piped, cargo and many build scripts decide they're not
+//! talking to a human and drop the colors and the progress. On Windows, cargo
+//! can be run in a pseudo console (ConPTY) instead: it sees a real console,
+//! while we read what the console renders.
+//!
+//! The rendering is not quite the output of cargo: the console moves the cursor
+//! around, sets the title and the like, so the lines are cleaned up before
+//! being looked at.
+//!
+//! With `--dry-filter`, nothing is run at all: the "child" is a
+//! [transcript](crate::transcript) played back.
+
+use std::{
+    io::{self, Read},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use crate::transcript::{Player, Transcript};
+
+/// Whether cargo can be run in a pseudo console on this platform.
+pub const SUPPORTED: bool = cfg!(windows);
+
+/// A running cargo, either with the stderr piped or in a pseudo console.
+pub enum Child {
+    Piped(std::process::Child),
+    #[cfg(windows)]
+    Console(imp::Console),
+    /// A transcript played back: its stdout (until taken) and the exit code.
+    Transcript(Option<Player>, Option<i32>),
+}
+
+impl Child {
+    /// Starts the command, returning it along with its output. In a pseudo
+    /// console (when `pty` is set and [`SUPPORTED`]) both stdout and stderr end
+    /// up in the output, otherwise it's only the stderr, while the stdout is
+    /// left as configured in the `cmd` (inherited by default).
+    pub fn spawn(cmd: &mut Command, pty: bool) -> io::Result<(Self, Box<dyn Read + Send>)> {
+        #[cfg(windows)]
+        if pty {
+            let (console, output) = imp::Console::spawn(cmd)?;
+            return Ok((Child::Console(console), Box::new(output)));
+        }
+        #[cfg(not(windows))]
+        let _ = pty;
+        let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+        let stderr = child.stderr.take().expect("There should be a channel");
+        Ok((Child::Piped(child), Box::new(stderr)))
+    }
+
+    /// Plays the transcript back, returning it along with its stderr. With
+    /// `realtime`, the output comes at the pace it's been recorded at.
+    pub fn replay(transcript: Transcript, realtime: bool) -> (Self, Box<dyn Read + Send>) {
+        let exit = transcript.exit;
+        let (stderr, stdout) = transcript.play(realtime);
+        (Child::Transcript(Some(stdout), exit), Box::new(stderr))
+    }
+
+    /// The process id, `0` for a transcript.
+    pub fn id(&self) -> u32 {
+        match self {
+            Child::Piped(child) => child.id(),
+            #[cfg(windows)]
+            Child::Console(console) => console.id(),
+            Child::Transcript(..) => 0,
+        }
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        match self {
+            Child::Piped(child) => child.kill(),
+            #[cfg(windows)]
+            Child::Console(console) => console.kill(),
+            Child::Transcript(..) => Ok(()),
+        }
+    }
+
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        match self {
+            Child::Piped(child) => child.wait(),
+            #[cfg(windows)]
+            Child::Console(console) => console.wait(),
+            // A transcript without the exit code is from a build that went fine
+            // as far as anyone can tell.
+            Child::Transcript(_, exit) => Ok(exit_status(exit.unwrap_or(0))),
+        }
+    }
+
+    /// The stdout of cargo, if it's piped.
+    pub fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        match self {
+            Child::Piped(child) => child
+                .stdout
+                .take()
+                .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>),
+            #[cfg(windows)]
+            Child::Console(_) => None,
+            Child::Transcript(stdout, _) => stdout
+                .take()
+                .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>),
+        }
+    }
+
+    /// Whether the output is rendered by a pseudo console and needs to be
+    /// [cleaned](clean_line).
+    pub fn is_console(&self) -> bool {
+        !matches!(self, Child::Piped(_) | Child::Transcript(..))
+    }
+}
+
+/// The status of a process exited with the `code`.
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+/// The status of a process exited with the `code`.
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+
+    ExitStatus::from_raw(code as u32)
+}
+
+/// Turns a line rendered by the pseudo console back into a line of output:
+/// only what's left visible after the carriage returns is kept, the colors are
+/// preserved, the cursor movements forward become spaces, and the rest of the
+/// control sequences are dropped.
+pub fn clean_line(line: &str) -> String {
+    let (line, newline) = match line.strip_suffix('\n') {
+        Some(line) => (line.strip_suffix('\r').unwrap_or(line), "\n"),
+        None => (line, ""),
+    };
+    let line = line.rsplit('\r').next().unwrap_or_default();
+    let mut cleaned = String::with_capacity(line.len() + newline.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            cleaned.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                let mut params = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !matches!(c, '0'..='9' | ';' | '?') {
+                        break;
+                    }
+                    params.push(c);
+                    let _ = chars.next();
+                }
+                match chars.next() {
+                    Some('m') => {
+                        cleaned.push_str("\x1b[");
+                        cleaned.push_str(&params);
+                        cleaned.push('m');
+                    }
+                    Some('C') => {
+                        let count = params.parse().unwrap_or(1usize);
+                        cleaned.extend(std::iter::repeat_n(' ', count));
+                    }
+                    _ => {}
+                }
+            }
+            // Operating system commands, like setting the title, end with
+            // either BEL or ST.
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    cleaned.push_str(newline);
+    cleaned
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::{
+        ffi::OsString,
+        fs::File,
+        io,
+        os::windows::{
+            ffi::OsStrExt,
+            io::{FromRawHandle, RawHandle},
+            process::ExitStatusExt,
+        },
+        process::{Command, ExitStatus},
+        thread::JoinHandle,
+    };
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        System::{
+            Console::{ClosePseudoConsole, CreatePseudoConsole, COORD, HPCON},
+            Pipes::CreatePipe,
+            Threading::{
+                CreateProcessW, DeleteProcThreadAttributeList, GetExitCodeProcess,
+                InitializeProcThreadAttributeList, TerminateProcess, UpdateProcThreadAttribute,
+                WaitForSingleObject, CREATE_UNICODE_ENVIRONMENT, EXTENDED_STARTUPINFO_PRESENT,
+                INFINITE, LPPROC_THREAD_ATTRIBUTE_LIST, PROCESS_INFORMATION,
+                PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE, STARTF_USESTDHANDLES, STARTUPINFOEXW,
+            },
+        },
+    };
+
+    /// The size of the console when it's unknown.
+    const DEFAULT_SIZE: (u16, u16) = (120, 30);
+
+    /// A process running in a pseudo console.
+    pub struct Console {
+        pid: u32,
+        /// The process handle, as a number to be shared with the watcher.
+        process: usize,
+        /// Waits for the process to exit and closes the console, which is what
+        /// makes the output reach its end.
+        watcher: Option<JoinHandle<io::Result<u32>>>,
+    }
+
+    impl Console {
+        pub fn spawn(cmd: &Command) -> io::Result<(Self, File)> {
+            let (input_read, input_write) = pipe()?;
+            let (output_read, output_write) = pipe()?;
+            let (columns, rows) = crate::terminal::size().unwrap_or(DEFAULT_SIZE);
+            let size = COORD {
+                X: i16::try_from(columns).unwrap_or(i16::MAX),
+                Y: i16::try_from(rows).unwrap_or(i16::MAX),
+            };
+            let mut console: HPCON = 0;
+            // SAFETY: the handles are valid pipe ends, `console` is written on
+            // success only.
+            let result =
+                unsafe { CreatePseudoConsole(size, input_read, output_write, 0, &mut console) };
+            if result != 0 {
+                close(&[input_read, input_write, output_read, output_write]);
+                return Err(io::Error::from_raw_os_error(result));
+            }
+            let spawned = create_process(cmd, console);
+            // The console has its own copies of its ends of the pipes.
+            close(&[input_read, output_write]);
+            let process = match spawned {
+                Ok(process) => process,
+                Err(e) => {
+                    // SAFETY: the console has been successfully created.
+                    unsafe { ClosePseudoConsole(console) };
+                    close(&[input_write, output_read]);
+                    return Err(e);
+                }
+            };
+            // SAFETY: the thread handle is valid and of no interest.
+            unsafe { CloseHandle(process.hThread) };
+            // Raw handles are not `Send`, so they cross the thread boundary
+            // as plain numbers.
+            let (handle, input) = (process.hProcess as usize, input_write as usize);
+            let watcher = std::thread::spawn(move || {
+                let handle = handle as HANDLE;
+                let mut code = 0;
+                // SAFETY: the handle stays valid until the watcher is joined.
+                let waited = unsafe {
+                    WaitForSingleObject(handle, INFINITE);
+                    GetExitCodeProcess(handle, &mut code)
+                };
+                let result = if waited != 0 {
+                    Ok(code)
+                } else {
+                    Err(io::Error::last_os_error())
+                };
+                // SAFETY: the console and the handles are not used anymore.
+                unsafe { ClosePseudoConsole(console) };
+                close(&[input as HANDLE]);
+                result
+            });
+            // SAFETY: the handle is a valid pipe end owned by us alone.
+            let output = unsafe { File::from_raw_handle(output_read as RawHandle) };
+            Ok((
+                Console {
+                    pid: process.dwProcessId,
+                    process: handle,
+                    watcher: Some(watcher),
+                },
+                output,
+            ))
+        }
+
+        pub fn id(&self) -> u32 {
+            self.pid
+        }
+
+        pub fn kill(&mut self) -> io::Result<()> {
+            // SAFETY: the handle is valid until the console is dropped.
+            if unsafe { TerminateProcess(self.process as HANDLE, 1) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub fn wait(&mut self) -> io::Result<ExitStatus> {
+            let watcher = self
+                .watcher
+                .take()
+                .ok_or_else(|| io::Error::other("already waited for"))?;
+            let code = watcher
+                .join()
+                .map_err(|_| io::Error::other("the process watcher has panicked"))??;
+            Ok(ExitStatus::from_raw(code))
+        }
+    }
+
+    impl Drop for Console {
+        fn drop(&mut self) {
+            // While the watcher is still waiting for the process, the handle
+            // is left to the operating system to close on exit.
+            if self.watcher.is_none() {
+                close(&[self.process as HANDLE]);
+            }
+        }
+    }
+
+    fn pipe() -> io::Result<(HANDLE, HANDLE)> {
+        let (mut read, mut write) = (std::ptr::null_mut(), std::ptr::null_mut());
+        // SAFETY: the handles are written on success only.
+        if unsafe { CreatePipe(&mut read, &mut write, std::ptr::null(), 0) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((read, write))
+    }
+
+    fn close(handles: &[HANDLE]) {
+        for &handle in handles {
+            // SAFETY: only the handles we own are closed, once.
+            unsafe { CloseHandle(handle) };
+        }
+    }
+
+    /// Starts the command attached to the `console`.
+    fn create_process(cmd: &Command, console: HPCON) -> io::Result<PROCESS_INFORMATION> {
+        let mut size = 0;
+        // SAFETY: only asks for the size of the attribute list.
+        unsafe { InitializeProcThreadAttributeList(std::ptr::null_mut(), 1, 0, &mut size) };
+        // `usize`s to keep the list aligned.
+        let mut buffer = vec![0usize; size.div_ceil(std::mem::size_of::<usize>())];
+        let attributes: LPPROC_THREAD_ATTRIBUTE_LIST = buffer.as_mut_ptr().cast();
+        // SAFETY: the buffer is of the requested size.
+        if unsafe { InitializeProcThreadAttributeList(attributes, 1, 0, &mut size) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the pseudo console attribute takes the console itself.
+        let updated = unsafe {
+            UpdateProcThreadAttribute(
+                attributes,
+                0,
+                PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE as usize,
+                console as *const core::ffi::c_void,
+                std::mem::size_of::<HPCON>(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+            )
+        };
+        let result = if updated == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            let mut startup: STARTUPINFOEXW = unsafe { std::mem::zeroed() };
+            startup.StartupInfo.cb = std::mem::size_of::<STARTUPINFOEXW>() as u32;
+            // Otherwise our own redirected handles would be inherited instead
+            // of the console.
+            startup.StartupInfo.dwFlags = STARTF_USESTDHANDLES;
+            startup.lpAttributeList = attributes;
+            let mut command_line = command_line(cmd);
+            let mut environment = environment(cmd);
+            let mut process: PROCESS_INFORMATION = unsafe { std::mem::zeroed() };
+            // SAFETY: all the pointers are valid for the duration of the call,
+            // the strings are null-terminated.
+            let created = unsafe {
+                CreateProcessW(
+                    std::ptr::null(),
+                    command_line.as_mut_ptr(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    0,
+                    EXTENDED_STARTUPINFO_PRESENT | CREATE_UNICODE_ENVIRONMENT,
+                    environment.as_mut_ptr().cast(),
+                    std::ptr::null(),
+                    &startup.StartupInfo,
+                    &mut process,
+                )
+            };
+            if created == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(process)
+            }
+        };
+        // SAFETY: the list has been initialized.
+        unsafe { DeleteProcThreadAttributeList(attributes) };
+        result
+    }
+
+    /// The null-terminated command line, quoted the way the C runtime parses
+    /// it.
+    fn command_line(cmd: &Command) -> Vec<u16> {
+        let mut line = Vec::new();
+        let program = std::iter::once(cmd.get_program());
+        for (index, arg) in program.chain(cmd.get_args()).enumerate() {
+            if index != 0 {
+                line.push(u16::from(b' '));
+            }
+            let arg: Vec<u16> = arg.encode_wide().collect();
+            let needs_quotes = arg.is_empty()
+                || arg.iter().any(|&c| {
+                    c == u16::from(b' ') || c == u16::from(b'\t') || c == u16::from(b'"')
+                });
+            if !needs_quotes {
+                line.extend(arg);
+                continue;
+            }
+            line.push(u16::from(b'"'));
+            let mut backslashes = 0;
+            for c in arg {
+                if c == u16::from(b'\\') {
+                    backslashes += 1;
+                } else {
+                    if c == u16::from(b'"') {
+                        // The backslashes before a quote are escaped, and so
+                        // is the quote.
+                        line.extend(std::iter::repeat_n(u16::from(b'\\'), backslashes + 1));
+                    }
+                    backslashes = 0;
+                }
+                line.push(c);
+            }
+            // The closing quote must not be escaped.
+            line.extend(std::iter::repeat_n(u16::from(b'\\'), backslashes));
+            line.push(u16::from(b'"'));
+        }
+        line.push(0);
+        line
+    }
+
+    /// The environment block: our own environment with the command's changes.
+    fn environment(cmd: &Command) -> Vec<u16> {
+        let mut variables: Vec<(OsString, OsString)> = std::env::vars_os().collect();
+        for (name, value) in cmd.get_envs() {
+            // The names are case-insensitive on Windows.
+            let same = |other: &OsString| {
+                other.to_string_lossy().to_uppercase() == name.to_string_lossy().to_uppercase()
+            };
+            variables.retain(|(other, _)| !same(other));
+            if let Some(value) = value {
+                variables.push((name.to_owned(), value.to_owned()));
+            }
+        }
+        let mut block = Vec::new();
+        for (name, value) in variables {
+            block.extend(name.encode_wide());
+            block.push(u16::from(b'='));
+            block.extend(value.encode_wide());
+            block.push(0);
+        }
+        if block.is_empty() {
+            block.push(0);
+        }
+        block.push(0);
+        block
+    }
+}
+
+#[test]
+fn cleans_rendered_lines() {
+    assert_eq!(
+        clean_line("\x1b[?25l\x1b]0;cargo\x07\x1b[3C\x1b[1m\x1b[32mCompiling\x1b[0m foo\x1b[K\r\n"),
+        "   \x1b[1m\x1b[32mCompiling\x1b[0m foo\n"
+    );
+    assert_eq!(
+        clean_line("    Building [==>  ] 1/3: foo\r\x1b[K    Checking bar\r\n"),
+        "    Checking bar\n"
+    );
+    assert_eq!(clean_line("\x1b]0;title\x1b\\done"), "done");
+}
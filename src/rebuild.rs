@@ -0,0 +1,192 @@
+//! `--explain-rebuild`: why the workspace crates were rebuilt, according to
+//! the cargo's fingerprint logs.
+
+use std::collections::BTreeMap;
+
+/// Value of `CARGO_LOG` making cargo log the fingerprint checks.
+pub const CARGO_LOG: &str = "cargo::core::compiler::fingerprint=info";
+
+/// The logging target the fingerprint messages come from.
+const LOG_TARGET: &str = "cargo::core::compiler::fingerprint:";
+
+/// Extracts the quoted string following the `key`, like `stale: "/path"`.
+fn quoted_after<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let (_, rest) = text.split_once(key)?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+    let (value, _) = rest.split_once('"')?;
+    Some(value)
+}
+
+/// Turns a `DirtyReason` debug representation into something readable. Paths
+/// are shown relative to the package `root`, when possible.
+fn describe(reason: &str, root: Option<&str>) -> String {
+    let relative = |path: &str| {
+        root.and_then(|root| path.strip_prefix(root))
+            .map(|path| path.trim_start_matches(['/', '\\']))
+            .unwrap_or(path)
+            .to_owned()
+    };
+    let variant = reason
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or(reason);
+    match variant {
+        "FsStatusOutdated" => {
+            if let Some(path) = quoted_after(reason, "stale:") {
+                format!("{} changed", relative(path))
+            } else if reason.contains("MissingFile") {
+                match quoted_after(reason, "MissingFile(") {
+                    Some(path) => format!("{} is missing", relative(path)),
+                    None => "a file is missing".to_owned(),
+                }
+            } else if reason.contains("ChangedEnv") {
+                match quoted_after(reason, "var:") {
+                    Some(var) => format!("environment variable {} changed", var),
+                    None => "an environment variable changed".to_owned(),
+                }
+            } else if reason.contains("StaleDependency") || reason.contains("StaleDepFingerprint") {
+                match quoted_after(reason, "name:") {
+                    Some(name) => format!("dependency {} was rebuilt", name),
+                    None => "a dependency was rebuilt".to_owned(),
+                }
+            } else {
+                "files changed".to_owned()
+            }
+        }
+        "FreshBuild" => "never built before".to_owned(),
+        "Forced" => "forced".to_owned(),
+        "RustcChanged" => "the compiler changed".to_owned(),
+        "FeaturesChanged" | "DeclaredFeaturesChanged" => "features changed".to_owned(),
+        "RustflagsChanged" => "RUSTFLAGS changed".to_owned(),
+        "ProfileConfigurationChanged" => "profile settings changed".to_owned(),
+        "TargetConfigurationChanged" | "CompileKindChanged" => "target changed".to_owned(),
+        "ConfigSettingsChanged" => "cargo config changed".to_owned(),
+        "PathToSourceChanged" => "path to the sources changed".to_owned(),
+        "EnvVarChanged" => match quoted_after(reason, "name:") {
+            Some(name) => format!("environment variable {} changed", name),
+            None => "an environment variable changed".to_owned(),
+        },
+        "EnvVarsChanged" => "environment variables changed".to_owned(),
+        "NumberOfDependenciesChanged" => "dependencies changed".to_owned(),
+        "UnitDependencyNameChanged" | "UnitDependencyInfoChanged" => {
+            match quoted_after(reason, "new_name:").or_else(|| quoted_after(reason, "name:")) {
+                Some(name) => format!("dependency {} changed", name),
+                None => "a dependency changed".to_owned(),
+            }
+        }
+        "LocalLengthsChanged"
+        | "PrecalculatedComponentsChanged"
+        | "RerunIfChangedOutputFileChanged"
+        | "RerunIfChangedOutputPathsChanged" => "build script inputs changed".to_owned(),
+        "NothingObvious" => "no obvious reason".to_owned(),
+        _ => reason.to_owned(),
+    }
+}
+
+/// Splits a package id like `foo v0.1.0 (/path/to/foo)` into the name with the
+/// version and the source. Only local packages have a plain path as a source.
+fn split_package_id(package_id: &str) -> (&str, Option<&str>) {
+    match package_id.split_once(" (") {
+        Some((name, source)) => (name, Some(source.trim_end_matches(')'))),
+        None => (package_id, None),
+    }
+}
+
+/// Collects the reasons of the rebuilds from the cargo's log.
+#[derive(Debug, Default)]
+pub struct RebuildExplainer {
+    /// Reasons per package, like `foo v0.1.0`.
+    reasons: BTreeMap<String, Vec<String>>,
+}
+
+impl RebuildExplainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a note of a line printed by cargo. Returns `true` if the line is
+    /// a fingerprint log message, which is not meant to be displayed.
+    pub fn observe(&mut self, line: &str) -> bool {
+        let line = crate::strip_colors(line);
+        let (context, message) = match line.split_once(LOG_TARGET) {
+            Some(parts) => parts,
+            None => return false,
+        };
+        let reason = match message.trim().strip_prefix("dirty:") {
+            Some(reason) => reason.trim(),
+            None => return true,
+        };
+        let package_id = match context.split_once("package_id=") {
+            Some((_, rest)) => rest.split(" target=").next().unwrap_or(rest),
+            None => return true,
+        };
+        let (package, source) = split_package_id(package_id);
+        // Registry and git dependencies are rebuilt because of the workspace
+        // settings (or their own dependencies), which is shown on the
+        // workspace crates anyhow.
+        let is_local = source.is_some_and(|source| !source.contains('+'));
+        if is_local {
+            let reason = describe(reason, source);
+            let reasons = self.reasons.entry(package.to_owned()).or_default();
+            if !reasons.contains(&reason) {
+                reasons.push(reason);
+            }
+        }
+        true
+    }
+
+    /// Renders the summary, one line per rebuilt package.
+    pub fn summary(&self) -> Option<String> {
+        if self.reasons.is_empty() {
+            return None;
+        }
+        let lines: Vec<_> = self
+            .reasons
+            .iter()
+            .map(|(package, reasons)| format!("  {}: {}", package, reasons.join("; ")))
+            .collect();
+        Some(lines.join("\n"))
+    }
+}
+
+#[test]
+fn explains_rebuilds() {
+    let mut explainer = RebuildExplainer::new();
+    let prefix = "   0.014s  INFO prepare_target{force=false package_id=tproj v0.1.0 (/tmp/tproj) target=\"tproj\"}: cargo::core::compiler::fingerprint:";
+    assert!(explainer.observe(&format!(
+        "{} stale: changed \"/tmp/tproj/src/main.rs\"\n",
+        prefix
+    )));
+    assert!(explainer.observe(&format!(
+        "{}     dirty: FsStatusOutdated(StaleItem(ChangedFile {{ reference: \"/tmp/tproj/target/debug/.fingerprint/tproj-8f/dep-bin-tproj\", reference_mtime: FileTime {{ seconds: 1, nanos: 2 }}, stale: \"/tmp/tproj/src/main.rs\", stale_mtime: FileTime {{ seconds: 3, nanos: 4 }} }}))\n",
+        prefix
+    )));
+    assert!(explainer.observe(&format!(
+        "{}     dirty: EnvVarChanged {{ name: \"FOO\", old_value: None, new_value: Some(\"1\") }}\n",
+        prefix
+    )));
+    assert!(explainer.observe(
+        "   0.1s  INFO prepare_target{force=false package_id=syn v2.0.48 (registry+https://github.com/rust-lang/crates.io-index) target=\"syn\"}: cargo::core::compiler::fingerprint:     dirty: RustflagsChanged { old: [], new: [\"-Copt-level=1\"] }\n"
+    ));
+    assert!(!explainer.observe("   Compiling tproj v0.1.0 (/tmp/tproj)\n"));
+    assert_eq!(
+        explainer.summary().as_deref(),
+        Some("  tproj v0.1.0: src/main.rs changed; environment variable FOO changed")
+    );
+}
+
+#[test]
+fn describes_reasons() {
+    assert_eq!(describe("FreshBuild", None), "never built before");
+    assert_eq!(
+        describe(
+            "FsStatusOutdated(StaleDependency { name: \"serde\", dep_mtime: FileTime { seconds: 1, nanos: 0 }, max_mtime: FileTime { seconds: 0, nanos: 0 } })",
+            None
+        ),
+        "dependency serde was rebuilt"
+    );
+    assert_eq!(
+        describe("SomethingNew { x: 1 }", None),
+        "SomethingNew { x: 1 }"
+    );
+}
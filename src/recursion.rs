@@ -0,0 +1,147 @@
+//! Protection against the plugin invoking itself over and over, which happens
+//! when `CARGO` points to the plugin or a cargo alias loops back to it, and
+//! detection of the instances nested on purpose (like a build run by an xtask
+//! or a build script).
+
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// How deep the plugin is nested, set for the child processes.
+pub const DEPTH_ENV: &str = "CARGO_SINGLE_LINE_DEPTH";
+
+/// The arguments the parent instance of the plugin has been invoked with.
+const ARGS_ENV: &str = "CARGO_SINGLE_LINE_ARGS";
+
+/// Beyond this depth we give up, something is clearly looping.
+pub const MAX_DEPTH: usize = 8;
+
+/// Separates the arguments in [`ARGS_ENV`].
+const SEPARATOR: &str = "\u{1f}";
+
+/// What we know about the instances of the plugin up the process tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// How many instances of the plugin are there above us.
+    pub depth: usize,
+    /// Whether the parent instance was invoked with the very same arguments,
+    /// which is what an alias loop looks like.
+    pub is_loop: bool,
+}
+
+impl Invocation {
+    pub fn detect(args: &[OsString]) -> Self {
+        Self::from_env(
+            std::env::var(DEPTH_ENV).ok().as_deref(),
+            std::env::var(ARGS_ENV).ok().as_deref(),
+            args,
+        )
+    }
+
+    fn from_env(depth: Option<&str>, parent_args: Option<&str>, args: &[OsString]) -> Self {
+        let depth = depth.and_then(|depth| depth.parse().ok()).unwrap_or(0);
+        let is_loop = depth > 0 && parent_args == Some(join(args).as_str());
+        Invocation { depth, is_loop }
+    }
+
+    /// Whether another instance up the process tree is already rendering the
+    /// output, so this one is to stay out of its way.
+    pub fn is_nested(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Marks the environment of the child process.
+    pub fn mark(&self, cmd: &mut Command, args: &[OsString]) {
+        cmd.env(DEPTH_ENV, (self.depth + 1).to_string())
+            .env(ARGS_ENV, join(args));
+    }
+}
+
+fn join(args: &[OsString]) -> String {
+    let args: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+    args.join(SEPARATOR)
+}
+
+/// Looks the program up in the `PATH`, unless it's a path already.
+fn resolve(program: &OsStr) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return Some(program.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) { &["exe", ""] } else { &[""] };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |extension| dir.join(program).with_extension(extension))
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Checks whether the `program` is actually the plugin itself.
+pub fn is_self(program: &OsStr) -> bool {
+    let canonical = |path: &Path| std::fs::canonicalize(path).ok();
+    let program = resolve(program).and_then(|path| canonical(&path));
+    let current = std::env::current_exe()
+        .ok()
+        .and_then(|path| canonical(&path));
+    matches!((program, current), (Some(program), Some(current)) if program == current)
+}
+
+/// Finds a `cargo` in the `PATH` that is neither the plugin nor the `program`
+/// (which has been found looping).
+pub fn other_cargo(program: &OsStr) -> Option<PathBuf> {
+    let cargo = real_cargo()?;
+    let canonical = |path: &Path| std::fs::canonicalize(path).ok();
+    let program = resolve(program).and_then(|path| canonical(&path));
+    (program.is_none() || program != canonical(&cargo)).then_some(cargo)
+}
+
+/// Finds a `cargo` in the `PATH` that is not the plugin.
+pub fn real_cargo() -> Option<PathBuf> {
+    let current = std::fs::canonicalize(std::env::current_exe().ok()?).ok()?;
+    let name = if cfg!(windows) { "cargo.exe" } else { "cargo" };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .filter(|candidate| candidate.is_file())
+        .find(|candidate| std::fs::canonicalize(candidate).ok().as_ref() != Some(&current))
+}
+
+#[test]
+fn detects_loops() {
+    let args: Vec<OsString> = vec!["b".into(), "--release".into()];
+    let joined = join(&args);
+
+    let top = Invocation::from_env(None, None, &args);
+    assert!(!top.is_nested());
+    assert_eq!(
+        top,
+        Invocation {
+            depth: 0,
+            is_loop: false
+        }
+    );
+
+    let looped = Invocation::from_env(Some("1"), Some(&joined), &args);
+    assert_eq!(
+        looped,
+        Invocation {
+            depth: 1,
+            is_loop: true
+        }
+    );
+
+    // E.g. a build script running another build.
+    let nested = Invocation::from_env(Some("1"), Some("build"), &args);
+    assert!(!nested.is_loop);
+    assert!(nested.is_nested());
+}
+
+#[test]
+fn recognizes_itself() {
+    let current = std::env::current_exe().unwrap();
+    assert!(is_self(current.as_os_str()));
+    assert!(!is_self(OsStr::new("definitely-not-cargo-single-line")));
+}
@@ -0,0 +1,59 @@
+//! The lines put on the status line are overwritten by the next ones, which
+//! loses the context of a failure (like which crate was compiling). With
+//! `--replay K` the last `K` of them are kept and printed again once the build
+//! fails.
+
+use std::collections::VecDeque;
+
+/// A ring buffer of the most recent collapsed lines.
+#[derive(Debug)]
+pub struct Replay {
+    capacity: usize,
+    lines: VecDeque<String>,
+}
+
+impl Replay {
+    /// Keeps up to `capacity` lines, none at all if it's zero.
+    pub fn new(capacity: usize) -> Self {
+        Replay {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Keeps the line, forgetting the oldest one if there are too many.
+    pub fn record(&mut self, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.trim_end().to_owned());
+    }
+
+    /// The kept lines, oldest first, each with a newline.
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines.iter().map(|line| format!("{}\n", line))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+#[test]
+fn keeps_recent_lines() {
+    let mut replay = Replay::new(2);
+    replay.record("   Compiling foo v0.1.0\n");
+    replay.record("   Compiling bar v0.1.0\n");
+    replay.record("   Compiling baz v0.1.0\n");
+    assert_eq!(
+        replay.lines().collect::<Vec<_>>(),
+        ["   Compiling bar v0.1.0\n", "   Compiling baz v0.1.0\n"]
+    );
+
+    let mut replay = Replay::new(0);
+    replay.record("   Compiling foo v0.1.0\n");
+    assert!(replay.is_empty());
+}
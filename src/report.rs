@@ -0,0 +1,297 @@
+//! A single-file HTML report of a build, meant to be kept as a CI artifact.
+
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use crate::{
+    diagnostics::{self, Diagnostic, Level},
+    downloads::Downloads,
+    format,
+    svg::escape,
+    timings::CrateTiming,
+};
+
+/// Everything known about a finished build.
+#[derive(Debug)]
+pub struct Report<'a> {
+    /// The command line, as typed.
+    pub command: String,
+    pub success: bool,
+    pub duration: Duration,
+    /// When the first error was reported, since the build start.
+    pub first_error: Option<Duration>,
+    pub timings: &'a [CrateTiming],
+    pub diagnostics: &'a [Diagnostic],
+    pub downloads: &'a Downloads,
+    /// The cargo output, color codes excluded.
+    pub log: &'a [String],
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+table { border-collapse: collapse; }
+td, th { padding: 0.2em 0.8em; text-align: left; }
+.bar { background: #4a90d9; height: 0.8em; }
+.error { color: #c62828; }
+.warning { color: #b8860b; }
+.success { color: #2e7d32; }";
+
+/// How many of the slowest crates are listed in the Markdown summary.
+const SLOWEST_CRATES: usize = 5;
+
+/// Escapes the text for a Markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+impl Report<'_> {
+    /// The timings, the slowest crates first.
+    fn slowest(&self) -> Vec<&CrateTiming> {
+        let mut timings: Vec<_> = self.timings.iter().collect();
+        timings.sort_by_key(|timing| std::cmp::Reverse(timing.duration));
+        timings
+    }
+
+    fn count(&self, level: Level) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == level)
+            .count()
+    }
+
+    /// Renders the report as a standalone HTML page.
+    pub fn html(&self) -> String {
+        let mut html = String::new();
+        // Writing into a `String` never fails.
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>",
+            escape(&self.command),
+            STYLE
+        );
+
+        let _ = writeln!(html, "<h1><code>{}</code></h1>", escape(&self.command));
+        let (class, outcome) = if self.success {
+            ("success", "succeeded")
+        } else {
+            ("error", "failed")
+        };
+        let _ = writeln!(
+            html,
+            "<ul>\n<li>Outcome: <span class=\"{}\">{}</span></li>\n<li>Duration: {}</li>\n<li>Crates compiled: {}</li>\n<li>Errors: {}</li>\n<li>Warnings: {}</li>\n<li>Crates downloaded: {}</li>\n</ul>",
+            class,
+            outcome,
+            format::duration(self.duration),
+            self.timings.len(),
+            self.count(Level::Error),
+            self.count(Level::Warning),
+            self.downloads.crates.len(),
+        );
+        if let Some(first_error) = self.first_error {
+            let _ = writeln!(
+                html,
+                "<p>The first error appeared after {}.</p>",
+                format::duration(first_error)
+            );
+        }
+
+        self.write_diagnostics(&mut html);
+        self.write_timings(&mut html);
+        self.write_downloads(&mut html);
+
+        let _ = writeln!(
+            html,
+            "<details>\n<summary>Raw log ({} lines)</summary>\n<pre>{}</pre>\n</details>",
+            self.log.len(),
+            escape(&self.log.join("\n"))
+        );
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn write_diagnostics(&self, html: &mut String) {
+        let _ = writeln!(html, "<h2>Errors and warnings</h2>");
+        if self.diagnostics.is_empty() {
+            let _ = writeln!(html, "<p>None.</p>");
+            return;
+        }
+        let mut by_crate: BTreeMap<&str, Vec<&Diagnostic>> = BTreeMap::new();
+        for diagnostic in self.diagnostics {
+            by_crate
+                .entry(
+                    diagnostic
+                        .crate_name
+                        .as_deref()
+                        .unwrap_or("(unknown crate)"),
+                )
+                .or_default()
+                .push(diagnostic);
+        }
+        for (name, diagnostics) in by_crate {
+            let _ = writeln!(html, "<h3>{}</h3>", escape(name));
+            for diagnostic in diagnostics {
+                let level = diagnostic.level.as_str();
+                let _ = writeln!(
+                    html,
+                    "<details>\n<summary><span class=\"{}\">{}</span>: {}</summary>\n<pre>{}</pre>\n</details>",
+                    level,
+                    level,
+                    escape(&diagnostic.message),
+                    escape(&diagnostic.lines.join("\n"))
+                );
+            }
+        }
+    }
+
+    fn write_timings(&self, html: &mut String) {
+        let _ = writeln!(html, "<h2>Compilation times</h2>");
+        if self.timings.is_empty() {
+            let _ = writeln!(html, "<p>Nothing was compiled.</p>");
+            return;
+        }
+        let timings = self.slowest();
+        let longest = timings[0].duration.as_secs_f64().max(0.001);
+        let _ = writeln!(
+            html,
+            "<table>\n<tr><th>Crate</th><th>Time</th><th></th></tr>"
+        );
+        for timing in timings {
+            let _ = writeln!(
+                html,
+                "<tr><td>{} v{}</td><td>{}</td><td><div class=\"bar\" style=\"width: {:.0}px\"></div></td></tr>",
+                escape(&timing.name),
+                escape(&timing.version),
+                format::duration(timing.duration),
+                timing.duration.as_secs_f64() / longest * 300.0,
+            );
+        }
+        let _ = writeln!(html, "</table>");
+    }
+
+    /// Renders a short summary as Markdown, for the GitHub Actions job summary.
+    pub fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "### `{}`\n", self.command);
+        let _ = writeln!(markdown, "| | |\n|---|---|");
+        let outcome = if self.success {
+            "✅ succeeded"
+        } else {
+            "❌ failed"
+        };
+        let _ = writeln!(markdown, "| Outcome | {} |", outcome);
+        let _ = writeln!(
+            markdown,
+            "| Duration | {} |",
+            format::duration(self.duration)
+        );
+        let _ = writeln!(markdown, "| Errors | {} |", self.count(Level::Error));
+        if let Some(first_error) = self.first_error {
+            let _ = writeln!(
+                markdown,
+                "| First error after | {} |",
+                format::duration(first_error)
+            );
+        }
+        let _ = writeln!(markdown, "| Warnings | {} |", self.count(Level::Warning));
+        let failed = diagnostics::failed_crates(self.diagnostics);
+        if !failed.is_empty() {
+            let names: Vec<_> = failed
+                .iter()
+                .map(|name| format!("`{}`", markdown_cell(name)))
+                .collect();
+            let _ = writeln!(markdown, "| Failed crates | {} |", names.join(", "));
+        }
+
+        if !self.timings.is_empty() {
+            let _ = writeln!(
+                markdown,
+                "\n**Slowest crates**\n\n| Crate | Time |\n|---|---|"
+            );
+            for timing in self.slowest().into_iter().take(SLOWEST_CRATES) {
+                let _ = writeln!(
+                    markdown,
+                    "| {} v{} | {} |",
+                    markdown_cell(&timing.name),
+                    markdown_cell(&timing.version),
+                    format::duration(timing.duration)
+                );
+            }
+        }
+        markdown.push('\n');
+        markdown
+    }
+
+    fn write_downloads(&self, html: &mut String) {
+        let _ = writeln!(html, "<h2>Downloads</h2>");
+        match &self.downloads.summary {
+            Some(summary) => {
+                let _ = writeln!(html, "<p>{}</p>", escape(summary));
+            }
+            None if self.downloads.crates.is_empty() => {
+                let _ = writeln!(html, "<p>Nothing was downloaded.</p>");
+            }
+            None => {}
+        }
+        if !self.downloads.crates.is_empty() {
+            let _ = writeln!(html, "<ul>");
+            for name in &self.downloads.crates {
+                let _ = writeln!(html, "<li>{}</li>", escape(name));
+            }
+            let _ = writeln!(html, "</ul>");
+        }
+    }
+}
+
+#[cfg(test)]
+fn sample_report(check: impl FnOnce(&Report)) {
+    let timings = [CrateTiming {
+        name: "foo".into(),
+        version: "0.1.0".into(),
+        start: Duration::ZERO,
+        duration: Duration::from_secs(2),
+    }];
+    let diagnostics = [Diagnostic {
+        level: Level::Error,
+        code: Some("E0308".into()),
+        message: "mismatched types".into(),
+        lines: vec!["error[E0308]: mismatched types".into()],
+        crate_name: Some("foo".into()),
+    }];
+    let downloads = Downloads::new();
+    let log = ["<unexpected>".to_owned()];
+    check(&Report {
+        command: "cargo build".into(),
+        success: false,
+        duration: Duration::from_secs(3),
+        first_error: Some(Duration::from_secs(1)),
+        timings: &timings,
+        diagnostics: &diagnostics,
+        downloads: &downloads,
+        log: &log,
+    });
+}
+
+#[test]
+fn renders_html() {
+    sample_report(|report| {
+        let html = report.html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span class=\"error\">failed</span>"));
+        assert!(html.contains("<li>Errors: 1</li>"));
+        assert!(html.contains("<h3>foo</h3>"));
+        assert!(html.contains("<td>foo v0.1.0</td><td>2.0s</td>"));
+        assert!(html.contains("&lt;unexpected&gt;"));
+    });
+}
+
+#[test]
+fn renders_markdown() {
+    sample_report(|report| {
+        let markdown = report.markdown();
+        assert!(markdown.starts_with("### `cargo build`\n"));
+        assert!(markdown.contains("| Outcome | ❌ failed |\n"));
+        assert!(markdown.contains("| Errors | 1 |\n"));
+        assert!(markdown.contains("| First error after | 1.0s |\n"));
+        assert!(markdown.contains("| Failed crates | `foo` |\n"));
+        assert!(markdown.contains("| foo v0.1.0 | 2.0s |\n"));
+    });
+}
@@ -0,0 +1,97 @@
+//! How the status line is drawn over in place.
+//!
+//! After a status is drawn, the cursor goes back to the start of the line, so
+//! that the next status (or a printed line) overwrites it. Whatever is left of
+//! a longer previous status is erased with `CSI K` (erase to the end of the
+//! line), which doesn't care about the color codes or the wide characters of
+//! either. The consoles that don't understand the ANSI sequences (the Windows
+//! ones without the virtual terminal processing) get the previous status
+//! overwritten with spaces instead, which takes knowing its display width.
+
+use std::io::{self, Write};
+
+use crate::width;
+
+/// The way the status line is erased.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// With the ANSI sequences.
+    Ansi,
+    /// With the spaces, only the carriage return is understood.
+    Plain,
+}
+
+impl Screen {
+    /// Draws the `line` over the one `previous` columns wide, leaving the
+    /// cursor at the start of it.
+    pub fn draw(self, out: &mut impl Write, line: &str, previous: usize) -> io::Result<()> {
+        self.overwrite(out, line, previous, "\r")
+    }
+
+    /// Draws the `line` over the one `previous` columns wide for good, moving
+    /// on to the next line.
+    pub fn finish(self, out: &mut impl Write, line: &str, previous: usize) -> io::Result<()> {
+        self.overwrite(out, line, previous, "\n")
+    }
+
+    fn overwrite(
+        self,
+        out: &mut impl Write,
+        line: &str,
+        previous: usize,
+        end: &str,
+    ) -> io::Result<()> {
+        match self {
+            Screen::Ansi => write!(out, "{}\x1b[K{}", line, end),
+            Screen::Plain => {
+                let remnants = previous.saturating_sub(width::width(line));
+                write!(out, "{}{:2$}{3}", line, "", remnants, end)
+            }
+        }
+    }
+
+    /// Erases the line `previous` columns wide, the cursor being at the start
+    /// of it.
+    pub fn erase(self, out: &mut impl Write, previous: usize) -> io::Result<()> {
+        match self {
+            _ if previous == 0 => Ok(()),
+            Screen::Ansi => out.write_all(b"\x1b[K"),
+            Screen::Plain => write!(out, "{0:1$}\r", "", previous),
+        }
+    }
+}
+
+#[test]
+fn erases_with_ansi_sequences() {
+    let mut out = Vec::new();
+    let screen = Screen::Ansi;
+    screen
+        .draw(&mut out, "\x1b[1;32m   Compiling\x1b[0m föö", 40)
+        .unwrap();
+    screen.erase(&mut out, 0).unwrap();
+    screen.erase(&mut out, 16).unwrap();
+    screen.finish(&mut out, "✔ Finished", 16).unwrap();
+    assert_eq!(
+        out,
+        "\x1b[1;32m   Compiling\x1b[0m föö\x1b[K\r\x1b[K✔ Finished\x1b[K\n".as_bytes()
+    );
+}
+
+#[test]
+fn erases_with_spaces() {
+    let mut out = Vec::new();
+    let screen = Screen::Plain;
+    // The color codes take no room, the remnants of the wider line are
+    // covered.
+    screen
+        .draw(&mut out, "\x1b[1;32m   Compiling\x1b[0m föö", 20)
+        .unwrap();
+    screen.erase(&mut out, 0).unwrap();
+    screen.erase(&mut out, 3).unwrap();
+    screen.finish(&mut out, "✔ Finished", 12).unwrap();
+    screen.draw(&mut out, "wider than before", 4).unwrap();
+    assert_eq!(
+        out,
+        "\x1b[1;32m   Compiling\x1b[0m föö    \r   \r✔ Finished  \nwider than before\r".as_bytes()
+    );
+}
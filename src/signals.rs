@@ -0,0 +1,223 @@
+//! Handling of Ctrl-C and the other terminating signals while cargo runs.
+//!
+//! Instead of dying on the spot, leaving the status line half-drawn and maybe
+//! cargo running, the signal is passed over to cargo (to its process group,
+//! when it has its own, see [`crate::keys`]), and the build is wrapped up once
+//! cargo exits, like when it's cancelled from the keyboard. A second signal
+//! kills us right away, in case cargo doesn't give up.
+//!
+//! The signals that matter to the main loop (the interrupts, and the terminal
+//! resizes on Unix) are also reported to it as events, see [`watch`]. The
+//! handlers themselves can't do much, so on Unix they write the signal number
+//! to a pipe, read by a thread of its own.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// Whether a terminating signal has been received.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// A signal the main loop is told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// The terminal has been resized.
+    Resize,
+    /// The first terminating signal, already passed over to cargo.
+    Interrupt,
+}
+
+type Watcher = Box<dyn FnMut(Signal) -> bool + Send>;
+
+/// Who is told about the signals.
+static WATCHER: Mutex<Option<Watcher>> = Mutex::new(None);
+
+/// Calls the `watcher` for every signal received from now on (on another
+/// thread), until it returns `false`. Replaces the previous watcher.
+pub fn watch(watcher: impl FnMut(Signal) -> bool + Send + 'static) {
+    *WATCHER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(watcher));
+    imp::watch();
+}
+
+fn notify(signal: Signal) {
+    let mut watcher = WATCHER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(watch) = watcher.as_mut() {
+        if !watch(signal) {
+            *watcher = None;
+        }
+    }
+}
+
+/// Whether the build has been interrupted by a signal.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Starts handling the signals.
+pub fn install() {
+    imp::install();
+}
+
+/// Makes the signals reach the child with the `pid`, or its whole process
+/// group if it runs in its own `group`.
+pub fn forward_to(pid: u32, group: bool) {
+    imp::forward_to(pid, group);
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        fs::File,
+        io::Read,
+        os::unix::io::FromRawFd,
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    use super::{Signal, INTERRUPTED};
+
+    /// Where to send the signals: the pid of the child, or the negated id of
+    /// its process group.
+    static TARGET: AtomicI32 = AtomicI32::new(0);
+
+    /// The writing end of the pipe the signals are reported through, if it's
+    /// been set up.
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    /// Sets up the pipe (once) and starts reporting the resizes.
+    pub fn watch() {
+        if PIPE.load(Ordering::SeqCst) != -1 {
+            return;
+        }
+        let mut fds = [0; 2];
+        // SAFETY: `pipe` fills the two descriptors in, and a full pipe must
+        // not block the handler, hence `O_NONBLOCK` for the writing end.
+        unsafe {
+            if libc::pipe(fds.as_mut_ptr()) != 0 {
+                return;
+            }
+            let flags = libc::fcntl(fds[1], libc::F_GETFL);
+            libc::fcntl(fds[1], libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        // SAFETY: the reading end is ours alone.
+        let mut reader = unsafe { File::from_raw_fd(fds[0]) };
+        PIPE.store(fds[1], Ordering::SeqCst);
+        std::thread::spawn(move || {
+            let mut signal = [0];
+            while reader.read_exact(&mut signal).is_ok() {
+                super::notify(if libc::c_int::from(signal[0]) == libc::SIGWINCH {
+                    Signal::Resize
+                } else {
+                    Signal::Interrupt
+                });
+            }
+        });
+        // SAFETY: the handler only calls async-signal-safe functions.
+        unsafe {
+            libc::signal(
+                libc::SIGWINCH,
+                on_signal as extern "C" fn(libc::c_int) as usize,
+            )
+        };
+    }
+
+    /// Reports the signal through the pipe, if there's one.
+    fn report(signal: libc::c_int) {
+        let pipe = PIPE.load(Ordering::SeqCst);
+        if let (true, Ok(signal)) = (pipe != -1, u8::try_from(signal)) {
+            // SAFETY: `write` is async-signal-safe, and the byte outlives the
+            // call.
+            unsafe { libc::write(pipe, std::ptr::addr_of!(signal).cast(), 1) };
+        }
+    }
+
+    pub fn install() {
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only calls async-signal-safe functions.
+            unsafe { libc::signal(signal, on_signal as extern "C" fn(libc::c_int) as usize) };
+        }
+    }
+
+    pub fn forward_to(pid: u32, group: bool) {
+        if let Ok(pid) = libc::pid_t::try_from(pid) {
+            TARGET.store(if group { -pid } else { pid }, Ordering::SeqCst);
+        }
+    }
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        if signal == libc::SIGWINCH {
+            report(signal);
+            return;
+        }
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            // Enough waiting: die as if there was no handler.
+            crate::keys::restore_terminal();
+            // SAFETY: `signal` and `raise` are async-signal-safe.
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+            return;
+        }
+        let target = TARGET.load(Ordering::SeqCst);
+        if target != 0 {
+            // SAFETY: `kill` is async-signal-safe.
+            unsafe { libc::kill(target, signal) };
+        }
+        report(signal);
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::sync::atomic::Ordering;
+
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+
+    use super::{Signal, INTERRUPTED};
+
+    pub fn install() {
+        // SAFETY: the handler is called on a thread of its own, like any
+        // other.
+        unsafe { SetConsoleCtrlHandler(Some(on_ctrl), 1) };
+    }
+
+    /// The console handler reports the interrupts directly, and the resizes
+    /// are not signaled.
+    pub fn watch() {}
+
+    /// Cargo is attached to the same console, so it gets Ctrl-C on its own.
+    pub fn forward_to(_pid: u32, _group: bool) {}
+
+    /// Swallows the first Ctrl-C (or Ctrl-Break, or closing the console), and
+    /// lets the default handler kill us on the second one.
+    unsafe extern "system" fn on_ctrl(_ctrl_type: u32) -> i32 {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            return 0;
+        }
+        super::notify(Signal::Interrupt);
+        1
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    pub fn install() {}
+
+    pub fn watch() {}
+
+    pub fn forward_to(_pid: u32, _group: bool) {}
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_resizes() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    watch(move |signal| sender.send(signal).is_ok());
+    // SAFETY: the handler only calls async-signal-safe functions.
+    unsafe { libc::raise(libc::SIGWINCH) };
+    assert_eq!(
+        receiver.recv_timeout(std::time::Duration::from_secs(5)),
+        Ok(Signal::Resize)
+    );
+}
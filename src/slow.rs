@@ -0,0 +1,132 @@
+//! The `slow` subcommand: the crates that dominate the build time across the
+//! recorded builds.
+
+use std::{collections::HashMap, time::Duration};
+
+use crate::{format, history::Build};
+
+/// How many crates are displayed.
+const TOP: usize = 10;
+
+/// Compilation statistics of a crate across the builds.
+#[derive(Debug, PartialEq)]
+struct CrateStats<'a> {
+    name: &'a str,
+    /// Durations of the crate's compilations, oldest first.
+    durations: Vec<f64>,
+}
+
+impl CrateStats<'_> {
+    fn total(&self) -> f64 {
+        self.durations.iter().sum()
+    }
+
+    fn average(&self) -> f64 {
+        self.total() / self.durations.len() as f64
+    }
+
+    /// How much slower (in percents) the latest compilation was compared to
+    /// the average of the previous ones.
+    fn trend(&self) -> Option<f64> {
+        let (latest, previous) = self.durations.split_last()?;
+        if previous.is_empty() {
+            return None;
+        }
+        let average = previous.iter().sum::<f64>() / previous.len() as f64;
+        if average > 0.0 {
+            Some((latest - average) / average * 100.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Collects per-crate statistics, the crates that took the most time overall
+/// come first.
+fn leaderboard(builds: &[Build]) -> Vec<CrateStats<'_>> {
+    let mut stats: HashMap<&str, Vec<f64>> = HashMap::new();
+    for build in builds {
+        for record in &build.crates {
+            stats.entry(&record.name).or_default().push(record.duration);
+        }
+    }
+    let mut stats: Vec<_> = stats
+        .into_iter()
+        .map(|(name, durations)| CrateStats { name, durations })
+        .collect();
+    stats.sort_by(|a, b| b.total().total_cmp(&a.total()).then(a.name.cmp(b.name)));
+    stats
+}
+
+/// Prints the slowest crates of the recorded `builds`.
+pub fn print(builds: &[Build]) {
+    let stats = leaderboard(builds);
+    if stats.is_empty() {
+        println!("No crate timings recorded yet, run a build with `cargo single-line` first.");
+        return;
+    }
+    let stats = &stats[..stats.len().min(TOP)];
+    let name_width = stats
+        .iter()
+        .map(|crate_stats| crate_stats.name.len())
+        .chain(Some("crate".len()))
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<name_width$}  {:>6}  {:>8}  {:>8}  {:>8}",
+        "crate",
+        "builds",
+        "total",
+        "average",
+        "trend",
+        name_width = name_width,
+    );
+    for crate_stats in stats {
+        let trend = match crate_stats.trend() {
+            Some(trend) => format!("{:+.0}%", trend),
+            None => "-".to_owned(),
+        };
+        println!(
+            "{:<name_width$}  {:>6}  {:>8}  {:>8}  {:>8}",
+            crate_stats.name,
+            crate_stats.durations.len(),
+            format::duration(Duration::from_secs_f64(crate_stats.total())),
+            format::duration(Duration::from_secs_f64(crate_stats.average())),
+            trend,
+            name_width = name_width,
+        );
+    }
+}
+
+#[test]
+fn ranks_crates_by_total_time() {
+    use crate::history::CrateRecord;
+
+    let build = |crates: &[(&str, f64)]| Build {
+        timestamp: 0,
+        command: "build".into(),
+        duration: 0.0,
+        success: true,
+        crates: crates
+            .iter()
+            .map(|&(name, duration)| CrateRecord {
+                name: name.into(),
+                version: "1.0.0".into(),
+                duration,
+            })
+            .collect(),
+        first_error: None,
+        compiled: None,
+        warnings: None,
+    };
+    let builds = [
+        build(&[("syn", 10.0), ("serde", 5.0)]),
+        build(&[("syn", 10.0), ("tokio", 12.0)]),
+        build(&[("syn", 15.0)]),
+    ];
+    let stats = leaderboard(&builds);
+    let names: Vec<_> = stats.iter().map(|stats| stats.name).collect();
+    assert_eq!(names, ["syn", "tokio", "serde"]);
+    assert_eq!(stats[0].trend(), Some(50.0));
+    assert_eq!(stats[1].trend(), None);
+}
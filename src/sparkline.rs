@@ -0,0 +1,81 @@
+//! The compilation throughput over the course of the build, as a sparkline
+//! like `▂▅██▇▃▁▁`: the serial tail of a build, where the parallelism
+//! collapses, is obvious at a glance.
+
+use std::time::Duration;
+
+use crate::{format, timings::CrateTiming};
+
+/// The shortest bucket of the build time.
+const BUCKET: Duration = Duration::from_secs(10);
+
+/// The widest sparkline, longer builds get longer buckets.
+const MAX_WIDTH: u64 = 60;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How long a bucket is for a build of the `total` duration: a multiple of
+/// [`BUCKET`] keeping the sparkline within [`MAX_WIDTH`].
+fn bucket(total: Duration) -> Duration {
+    let buckets = total.as_secs().div_ceil(BUCKET.as_secs());
+    BUCKET * buckets.div_ceil(MAX_WIDTH).max(1) as u32
+}
+
+/// How many crates have started compiling in each bucket.
+fn counts(timings: &[CrateTiming], total: Duration, bucket: Duration) -> Vec<usize> {
+    let width = (total.as_nanos().div_ceil(bucket.as_nanos())).max(1) as usize;
+    let mut counts = vec![0; width];
+    for timing in timings {
+        let index = (timing.start.as_nanos() / bucket.as_nanos()) as usize;
+        counts[index.min(width - 1)] += 1;
+    }
+    counts
+}
+
+fn render(counts: &[usize]) -> String {
+    let peak = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => BARS[(count * BARS.len()).div_ceil(peak) - 1],
+        })
+        .collect()
+}
+
+/// The throughput note, unless the build is too short for it to make sense.
+pub fn summary(timings: &[CrateTiming], total: Duration) -> Option<String> {
+    let bucket = bucket(total);
+    if timings.is_empty() || total < bucket * 2 {
+        return None;
+    }
+    let counts = counts(timings, total, bucket);
+    Some(crate::l10n::text(
+        "throughput",
+        &[
+            ("sparkline", &render(&counts)),
+            ("bucket", &format::approximate(bucket)),
+            ("peak", &counts.iter().copied().max().unwrap_or(0)),
+        ],
+    ))
+}
+
+#[test]
+fn draws_throughput() {
+    let timing = |start| CrateTiming {
+        name: "foo".into(),
+        version: "0.1.0".into(),
+        start: Duration::from_secs(start),
+        duration: Duration::from_secs(1),
+    };
+    let timings: Vec<_> = [0, 1, 2, 3, 5, 7, 12, 14, 15, 25, 41]
+        .into_iter()
+        .map(timing)
+        .collect();
+    let total = Duration::from_secs(45);
+    assert_eq!(bucket(total), BUCKET);
+    assert_eq!(counts(&timings, total, BUCKET), [6, 3, 1, 0, 1]);
+    assert_eq!(render(&[6, 3, 1, 0, 1]), "█▄▂ ▂");
+    assert_eq!(bucket(Duration::from_secs(1800)), Duration::from_secs(30));
+    assert_eq!(summary(&timings, Duration::from_secs(15)), None);
+}
@@ -0,0 +1,35 @@
+//! The spinner and the elapsed time appended to the status line when it stays
+//! the same for a while, like when a big crate takes half a minute to compile.
+
+use std::time::Duration;
+
+/// How long the status stays the same before the spinner shows up.
+pub const DELAY: Duration = Duration::from_secs(1);
+
+/// How often the spinner turns.
+pub const INTERVAL: Duration = Duration::from_millis(100);
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// The spinner along with the time the status has been displayed for, if it's
+/// been long enough, like `⠹ 12s`.
+pub fn indicator(elapsed: Duration) -> Option<String> {
+    if elapsed < DELAY {
+        return None;
+    }
+    let frame = (elapsed.as_millis() / INTERVAL.as_millis()) as usize % FRAMES.len();
+    let seconds = elapsed.as_secs();
+    Some(if seconds < 60 {
+        format!("{} {}s", FRAMES[frame], seconds)
+    } else {
+        format!("{} {}m {:02}s", FRAMES[frame], seconds / 60, seconds % 60)
+    })
+}
+
+#[test]
+fn shows_elapsed_time() {
+    assert_eq!(indicator(Duration::from_millis(500)), None);
+    assert_eq!(indicator(Duration::from_millis(1000)).unwrap(), "⠋ 1s");
+    assert_eq!(indicator(Duration::from_millis(12_300)).unwrap(), "⠸ 12s");
+    assert_eq!(indicator(Duration::from_secs(75)).unwrap(), "⠋ 1m 15s");
+}
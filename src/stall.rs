@@ -0,0 +1,84 @@
+//! Detection of the builds that are stuck on the network.
+
+use std::time::{Duration, Instant};
+
+/// Cargo output that indicates it's waiting for the network.
+const NETWORK_PREFIXES: &[&str] = &["Updating", "Downloading", "Downloaded"];
+
+/// Tracks for how long cargo is stuck in a network-bound phase.
+#[derive(Debug)]
+pub struct StallDetector {
+    timeout: Option<Duration>,
+
+    /// When the current network-bound phase has started, if any.
+    since: Option<Instant>,
+
+    /// Whether the current stall has been reported already.
+    reported: bool,
+}
+
+impl StallDetector {
+    /// Creates a detector; when `timeout` is `None` nothing is ever reported.
+    pub fn new(timeout: Option<Duration>) -> Self {
+        StallDetector {
+            timeout,
+            since: None,
+            reported: false,
+        }
+    }
+
+    /// Takes a note of a line printed by cargo at the moment `now`.
+    pub fn observe(&mut self, line: &str, now: Instant) {
+        self.since = if crate::starts_with(line, NETWORK_PREFIXES) {
+            Some(now)
+        } else {
+            None
+        };
+        self.reported = false;
+    }
+
+    /// Returns `true` once cargo has stayed in a network-bound phase for longer
+    /// than the timeout. A stall is only reported once.
+    pub fn check(&mut self, now: Instant) -> bool {
+        match (self.timeout, self.since) {
+            (Some(timeout), Some(since))
+                if !self.reported && now.saturating_duration_since(since) >= timeout =>
+            {
+                self.reported = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn reports_network_stalls_once() {
+    let start = Instant::now();
+    let mut detector = StallDetector::new(Some(Duration::from_secs(10)));
+
+    detector.observe("    Updating crates.io index\n", start);
+    assert!(!detector.check(start + Duration::from_secs(5)));
+    assert!(detector.check(start + Duration::from_secs(10)));
+    assert!(!detector.check(start + Duration::from_secs(20)));
+
+    // Progress resets the timer.
+    detector.observe(
+        "  Downloaded serde v1.0.0\n",
+        start + Duration::from_secs(20),
+    );
+    assert!(!detector.check(start + Duration::from_secs(25)));
+    assert!(detector.check(start + Duration::from_secs(30)));
+}
+
+#[test]
+fn ignores_non_network_phases() {
+    let start = Instant::now();
+    let mut detector = StallDetector::new(Some(Duration::from_secs(1)));
+    detector.observe("   Compiling syn v2.0.0\n", start);
+    assert!(!detector.check(start + Duration::from_secs(60)));
+
+    let mut detector = StallDetector::new(None);
+    detector.observe("    Updating crates.io index\n", start);
+    assert!(!detector.check(start + Duration::from_secs(60)));
+}
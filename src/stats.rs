@@ -0,0 +1,253 @@
+//! The `stats` subcommand: where the compile time goes, according to the
+//! recorded builds.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{format, history::Build, l10n};
+
+/// How many of the latest builds `stats` lists.
+const LATEST_BUILDS: usize = 5;
+
+/// Parses a period like `90m`, `12h`, `7d` or `2w`.
+fn parse_period(period: &str) -> Result<Duration, String> {
+    let error = || format!("invalid period {:?}, expected something like 7d", period);
+    let unit_at = period
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(error)?;
+    let (number, unit) = period.split_at(unit_at);
+    let number: u64 = number.parse().map_err(|_| error())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(error()),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(error)
+}
+
+/// Totals over a number of builds.
+#[derive(Debug, Default, PartialEq)]
+struct Totals {
+    builds: usize,
+    successful: usize,
+    /// In seconds.
+    duration: f64,
+}
+
+impl Totals {
+    fn add(&mut self, build: &Build) {
+        self.builds += 1;
+        if build.success {
+            self.successful += 1;
+        }
+        self.duration += build.duration;
+    }
+
+    fn average(&self) -> Duration {
+        Duration::from_secs_f64(self.duration / self.builds.max(1) as f64)
+    }
+}
+
+/// Aggregates the builds, overall and per subcommand.
+fn aggregate<'a>(
+    builds: impl IntoIterator<Item = &'a Build>,
+) -> (Totals, BTreeMap<&'a str, Totals>) {
+    let mut overall = Totals::default();
+    let mut per_command: BTreeMap<&str, Totals> = BTreeMap::new();
+    for build in builds {
+        overall.add(build);
+        per_command.entry(&build.command).or_default().add(build);
+    }
+    (overall, per_command)
+}
+
+/// Runs the `stats [--since PERIOD]` subcommand over the `builds`; `now` is in
+/// seconds since the Unix epoch.
+pub fn run(builds: &[Build], args: &[&str], now: u64) -> Result<(), String> {
+    let since = match args {
+        [] => None,
+        ["--since", period] => Some(parse_period(period)?),
+        [arg] if arg.starts_with("--since=") => Some(parse_period(&arg["--since=".len()..])?),
+        _ => return Err("usage: cargo single-line stats [--since PERIOD]".into()),
+    };
+    let oldest = since.map_or(0, |since| now.saturating_sub(since.as_secs()));
+    let recent: Vec<&Build> = builds
+        .iter()
+        .filter(|build| build.timestamp >= oldest)
+        .collect();
+    let (overall, per_command) = aggregate(recent.iter().copied());
+    if overall.builds == 0 {
+        println!("No builds recorded yet, run a build with `cargo single-line` first.");
+        return Ok(());
+    }
+
+    println!(
+        "builds: {} ({:.0}% successful)",
+        overall.builds,
+        overall.successful as f64 / overall.builds as f64 * 100.0
+    );
+    println!(
+        "total time: {}",
+        format::duration(Duration::from_secs_f64(overall.duration))
+    );
+    println!("average: {}", format::duration(overall.average()));
+    println!();
+
+    let width = per_command
+        .keys()
+        .map(|command| command.len())
+        .chain(Some("command".len()))
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<width$}  {:>6}  {:>8}  {:>8}  {:>6}",
+        "command",
+        "builds",
+        "total",
+        "average",
+        "share",
+        width = width,
+    );
+    for (command, totals) in &per_command {
+        println!(
+            "{:<width$}  {:>6}  {:>8}  {:>8}  {:>5.0}%",
+            command,
+            totals.builds,
+            format::duration(Duration::from_secs_f64(totals.duration)),
+            format::duration(totals.average()),
+            totals.duration / overall.duration.max(f64::EPSILON) * 100.0,
+            width = width,
+        );
+    }
+
+    println!();
+    println!("latest builds:");
+    for build in recent.iter().rev().take(LATEST_BUILDS) {
+        let mut line = format!(
+            "{:<width$}  {:>8}",
+            build.command,
+            format::duration(Duration::from_secs_f64(build.duration)),
+            width = width,
+        );
+        let counts = [(build.compiled, "crate"), (build.warnings, "warning")];
+        for (count, noun) in counts {
+            match count {
+                Some(1) => line.push_str(&format!("  1 {}", noun)),
+                Some(count) => line.push_str(&format!("  {} {}s", count, noun)),
+                None => {}
+            }
+        }
+        if !build.success {
+            line.push_str("  (failed)");
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// A difference, like `+1`, `−2.1s` or `±0`.
+fn signed(delta: f64, format: impl Fn(f64) -> String) -> String {
+    if delta == 0.0 {
+        format!("±{}", format(0.0))
+    } else if delta < 0.0 {
+        format!("−{}", format(-delta))
+    } else {
+        format!("+{}", format(delta))
+    }
+}
+
+/// The build compared to the previous one of the same command, like
+/// `12.3s (−2.1s vs previous), 4 warnings (+1)`. The warnings are left out
+/// unless both builds have counted them.
+pub fn compare(build: &Build, previous: &Build) -> String {
+    let seconds = |seconds: f64| format::duration(Duration::from_secs_f64(seconds));
+    let mut comparison = l10n::text(
+        "compared-to-previous",
+        &[
+            ("duration", &seconds(build.duration)),
+            (
+                "delta",
+                &signed(build.duration - previous.duration, seconds),
+            ),
+        ],
+    );
+    if let (Some(warnings), Some(before)) = (build.warnings, previous.warnings) {
+        let delta = signed(warnings as f64 - before as f64, |count| count.to_string());
+        comparison.push_str(", ");
+        comparison.push_str(&l10n::text(
+            "warnings-compared",
+            &[("warnings", &warnings), ("delta", &delta)],
+        ));
+    }
+    comparison
+}
+
+#[test]
+fn parses_periods() {
+    assert_eq!(parse_period("7d"), Ok(Duration::from_secs(7 * 24 * 3600)));
+    assert_eq!(parse_period("90m"), Ok(Duration::from_secs(90 * 60)));
+    assert!(parse_period("7").is_err());
+    assert!(parse_period("d").is_err());
+    assert!(parse_period("7y").is_err());
+}
+
+#[test]
+fn aggregates_builds() {
+    let build = |command: &str, duration: f64, success: bool| Build {
+        timestamp: 0,
+        command: command.into(),
+        duration,
+        success,
+        crates: Vec::new(),
+        first_error: None,
+        compiled: None,
+        warnings: None,
+    };
+    let builds = [
+        build("build", 10.0, true),
+        build("check", 2.0, false),
+        build("build", 20.0, true),
+    ];
+    let (overall, per_command) = aggregate(&builds);
+    assert_eq!(
+        overall,
+        Totals {
+            builds: 3,
+            successful: 2,
+            duration: 32.0
+        }
+    );
+    assert_eq!(per_command["build"].average(), Duration::from_secs(15));
+    assert_eq!(per_command["check"].successful, 0);
+}
+
+#[test]
+fn compares_builds() {
+    let build = |duration: f64, warnings: Option<usize>| Build {
+        timestamp: 0,
+        command: "build".into(),
+        duration,
+        success: true,
+        crates: Vec::new(),
+        first_error: None,
+        compiled: Some(12),
+        warnings,
+    };
+    assert_eq!(
+        compare(&build(12.3, Some(4)), &build(14.4, Some(3))),
+        "12.3s (−2.1s vs previous), 4 warnings (+1)"
+    );
+    assert_eq!(
+        compare(&build(12.3, Some(1)), &build(12.3, Some(1))),
+        "12.3s (±0.0s vs previous), 1 warning (±0)"
+    );
+    assert_eq!(
+        compare(&build(75.0, Some(0)), &build(15.0, None)),
+        "1m 15s (+1m 00s vs previous)"
+    );
+}
@@ -0,0 +1,495 @@
+//! The single status line at the bottom of the output.
+//!
+//! A noisy build can produce lines faster than the terminal renders them, so
+//! the status line is redrawn at most once a [frame](FRAME): the updates in
+//! between only replace the text to draw, and the latest one is drawn once the
+//! frame is over (see [`StatusLine::redraw_in`]). The lines printed for good
+//! are never skipped, of course. The status stays at the bottom: once a line
+//! is printed, the status is drawn again below it.
+//!
+//! The status is cut down to the terminal width, so that it never wraps. The
+//! width is queried on every redraw, which picks up the resizes as well. The
+//! previous status is erased as the [screen](crate::screen) allows.
+//!
+//! With a [window](StatusLine::with_window) of several lines, the most recent
+//! statuses stay visible as a small block redrawn in place, the oldest one
+//! scrolling off as the new ones come.
+//!
+//! With the [spinner](StatusLine::with_spinner) on, a status staying the same
+//! for a while gets the time it's been displayed for appended, so that a long
+//! compilation doesn't look like a hang. The spinner is kept turning by
+//! [`StatusLine::tick`].
+//!
+//! A [detached](StatusLine::detached) status is not drawn at all, only the
+//! printed lines make it to the output, for when it's not a terminal.
+//!
+//! The output is buffered: when everything is fresh, hundreds of lines come in
+//! a few milliseconds, and a write (or three) per line would be a waste. The
+//! buffer goes to stderr in one go with [`StatusLine::write_out`] once the
+//! caller has caught up, when it grows large, and right away for the final
+//! state of the status.
+//!
+//! With a [tag](StatusLine::with_tag), every line is prefixed with it, and the
+//! output is written in turn with the other instances on the same terminal.
+
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{screen::Screen, spinner, tag::Tag, terminal, trace::trace, width};
+
+/// Whether a status line is displayed without a newline after it, for
+/// [`finish_dangling`].
+static DANGLING: AtomicBool = AtomicBool::new(false);
+
+/// Moves on from the status line left dangling, when the [`StatusLine`] itself
+/// is out of reach (like in a panic hook).
+pub fn finish_dangling() {
+    if DANGLING.swap(false, Ordering::SeqCst) {
+        eprintln!();
+    }
+}
+
+/// The minimal interval between the redraws of the status line.
+pub const FRAME: Duration = Duration::from_millis(1000 / 30);
+
+/// How much output is buffered before it's written out regardless.
+const BUFFER_LIMIT: usize = 16 * 1024;
+
+/// Keeps track of what's been printed to stderr so that captured lines
+/// overwrite each other while the rest of the output is kept intact.
+#[derive(Debug)]
+pub struct StatusLine {
+    /// The current status, if any.
+    current: String,
+
+    /// Whether the current status is yet to be drawn.
+    pending: bool,
+
+    /// When the status has been drawn the last time.
+    drawn_at: Option<Instant>,
+
+    /// Display width of the previous line, the "remnants" of which are to be
+    /// cleared (or covered with spaces, depending on the screen).
+    previous_width: usize,
+
+    /// How the remnants are cleared.
+    screen: Screen,
+
+    /// Whether the latest line printed to stderr contains a "newline".
+    has_newline: bool,
+
+    /// How many statuses are kept visible, the current one included.
+    window: usize,
+
+    /// The statuses preceding the current one, the oldest first, when the
+    /// window has more than one line.
+    recent: VecDeque<String>,
+
+    /// How many rows of the window are drawn on the screen.
+    drawn_rows: usize,
+
+    /// Whether to append the spinner to a status staying the same.
+    spinner: bool,
+
+    /// When the current status has been set.
+    since: Instant,
+
+    /// Whether the status is kept track of without being drawn.
+    detached: bool,
+
+    /// Whether the drawing is paused from the keyboard.
+    paused: bool,
+
+    /// The output not written to stderr yet.
+    buffer: Vec<u8>,
+
+    /// The tag of the instance, if there are others on the terminal.
+    tag: Option<Tag>,
+}
+
+impl StatusLine {
+    pub fn new() -> Self {
+        Self::with_window(1)
+    }
+
+    /// Keeps the `window` most recent statuses visible instead of only the
+    /// current one. The window is redrawn with the ANSI cursor movements, so
+    /// it's only meant for terminals.
+    pub fn with_window(window: usize) -> Self {
+        StatusLine {
+            current: String::new(),
+            pending: false,
+            drawn_at: None,
+            previous_width: 0,
+            screen: Screen::Ansi,
+            has_newline: true,
+            window: window.max(1),
+            recent: VecDeque::new(),
+            drawn_rows: 0,
+            spinner: false,
+            since: Instant::now(),
+            detached: false,
+            paused: false,
+            buffer: Vec::new(),
+            tag: None,
+        }
+    }
+
+    /// Keeps track of the status without drawing it, so that nothing but the
+    /// printed lines is output.
+    pub fn detached() -> Self {
+        let mut status = Self::new();
+        status.detached = true;
+        status
+    }
+
+    /// Erases the status as the `screen` allows, with the ANSI sequences by
+    /// default.
+    pub fn with_screen(mut self, screen: Screen) -> Self {
+        self.screen = screen;
+        self
+    }
+
+    /// Turns the spinner on or off.
+    pub fn with_spinner(mut self, spinner: bool) -> Self {
+        self.spinner = spinner;
+        self
+    }
+
+    /// Prefixes the lines with the tag, and shares the terminal with the other
+    /// instances.
+    pub fn with_tag(mut self, tag: Option<Tag>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// The line prefixed with the tag, if any.
+    fn tagged<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        match &self.tag {
+            Some(tag) if !line.is_empty() => Cow::Owned(format!("{}{}", tag.prefix(), line)),
+            _ => Cow::Borrowed(line),
+        }
+    }
+
+    /// Writes the buffered output to stderr. To be called once there's
+    /// nothing more to process for the moment, so that a burst of lines costs
+    /// a single write.
+    pub fn write_out(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        // Nowhere to report the failure to, just like with `eprint`.
+        let _ = match &self.tag {
+            Some(tag) => tag.write(&self.buffer),
+            None => {
+                let mut stderr = io::stderr().lock();
+                stderr.write_all(&self.buffer).and_then(|()| stderr.flush())
+            }
+        };
+        self.buffer.clear();
+    }
+
+    /// Writes the output out if too much of it is buffered.
+    fn spill(&mut self) {
+        if self.buffer.len() >= BUFFER_LIMIT {
+            self.write_out();
+        }
+    }
+
+    fn set_has_newline(&mut self, has_newline: bool) {
+        self.has_newline = has_newline;
+        DANGLING.store(!has_newline, Ordering::SeqCst);
+    }
+
+    /// The current status (without the trailing whitespaces), even if it's
+    /// not drawn yet.
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Replaces the status line with the given text, right away unless it has
+    /// been redrawn less than a frame ago.
+    pub fn update(&mut self, line: &str) {
+        let now = Instant::now();
+        let line = line.trim_end();
+        if self.current != line {
+            self.since = now;
+        }
+        self.current.clear();
+        self.current.push_str(line);
+        if is_frame_over(self.drawn_at, now) {
+            self.draw(now);
+        } else {
+            self.pending = true;
+        }
+    }
+
+    /// Puts a new status below the current one, scrolling the oldest status
+    /// off the window. Without a window it's the same as [`update`](Self::update).
+    pub fn push(&mut self, line: &str) {
+        self.scroll();
+        self.update(line);
+    }
+
+    /// Moves the current status to the recent ones, if there's a window.
+    fn scroll(&mut self) {
+        if self.window > 1 && !self.current.is_empty() {
+            if self.recent.len() + 1 == self.window {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(std::mem::take(&mut self.current));
+        }
+    }
+
+    /// How soon the latest status is to be drawn with [`flush`](Self::flush),
+    /// if it's not drawn yet.
+    pub fn redraw_in(&self) -> Option<Duration> {
+        if !self.pending {
+            return None;
+        }
+        let elapsed = self.drawn_at.map_or(FRAME, |drawn_at| drawn_at.elapsed());
+        Some(FRAME.saturating_sub(elapsed))
+    }
+
+    /// Redraws the status to fit the terminal once it's been resized.
+    pub fn resized(&mut self) {
+        trace!("redraw", "resized to {:?}", terminal::size());
+        self.pending = !self.current.is_empty() || !self.recent.is_empty();
+    }
+
+    /// Redraws the status if the spinner is to turn.
+    pub fn tick(&mut self) {
+        if self.spinner && !self.current.is_empty() && self.since.elapsed() >= spinner::DELAY {
+            self.pending = true;
+        }
+    }
+
+    /// Draws the latest status if it's not drawn yet, and writes the output
+    /// out.
+    pub fn flush(&mut self) {
+        if self.pending {
+            self.draw(Instant::now());
+        }
+        self.write_out();
+    }
+
+    /// Erases the status and stops drawing it, or draws it again. The status
+    /// is still kept track of meanwhile.
+    pub fn set_paused(&mut self, paused: bool) {
+        trace!("redraw", "paused: {}", paused);
+        self.paused = paused;
+        if paused {
+            self.erase();
+            self.write_out();
+            self.set_has_newline(true);
+        } else {
+            self.pending = !self.current.is_empty() || !self.recent.is_empty();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn draw(&mut self, now: Instant) {
+        if self.detached || self.paused {
+            self.pending = false;
+            return;
+        }
+        trace!("redraw", "update {:?}", self.current);
+        // The last column is left empty, some terminals wrap right after it's
+        // filled.
+        let columns = terminal::size().map(|(columns, _)| usize::from(columns).saturating_sub(1));
+        let current = match spinner::indicator(now.saturating_duration_since(self.since))
+            .filter(|_| self.spinner && !self.current.is_empty())
+        {
+            Some(indicator) => Cow::Owned(format!("{} {}", self.current, indicator)),
+            None => Cow::Borrowed(self.current.as_str()),
+        };
+        let current = match self.tagged(&current) {
+            Cow::Owned(tagged) => Cow::Owned(tagged),
+            Cow::Borrowed(_) => current,
+        };
+        if self.window > 1 {
+            let lines: Vec<String> = self
+                .recent
+                .iter()
+                .map(|line| self.tagged(line))
+                .chain(Some(Cow::Borrowed(current.as_ref())).filter(|current| !current.is_empty()))
+                .map(|line| fit(&line, columns).into_owned())
+                .collect();
+            let _ = write!(
+                self.buffer,
+                "{}{}",
+                erase_rows(self.drawn_rows),
+                lines.join("\n")
+            );
+            self.drawn_rows = lines.len();
+        } else {
+            let line = fit(&current, columns);
+            // The terminal might have shrunk since the previous line was drawn.
+            let previous = self.previous_width.min(columns.unwrap_or(usize::MAX));
+            let _ = self.screen.draw(&mut self.buffer, &line, previous);
+            self.previous_width = width::width(&line);
+        }
+        self.set_has_newline(false);
+        self.pending = false;
+        self.drawn_at = Some(now);
+        self.spill();
+    }
+
+    /// Prints the line "as is", so it won't be overwritten by the following
+    /// status updates. The status itself (along with the progress) is drawn
+    /// again below the line.
+    pub fn print(&mut self, line: &str) {
+        self.print_bytes(line.as_bytes());
+    }
+
+    /// Prints the line like [`print`](Self::print) does, whatever its
+    /// encoding.
+    pub fn print_bytes(&mut self, line: &[u8]) {
+        // "line" is expected to contain '\n' already.
+        trace!("redraw", "print {:?}", String::from_utf8_lossy(line));
+        self.erase();
+        match &self.tag {
+            Some(tag) => self.buffer.extend(tag.apply(line)),
+            None => self.buffer.extend_from_slice(line),
+        }
+        self.printed();
+        self.spill();
+    }
+
+    /// Prints the line to stdout "as is", like [`print`](Self::print) does it
+    /// to stderr.
+    pub fn print_stdout(&mut self, line: &[u8]) {
+        trace!(
+            "redraw",
+            "print to stdout {:?}",
+            String::from_utf8_lossy(line)
+        );
+        self.erase();
+        // The erasure must reach the terminal before the line does.
+        self.write_out();
+        let mut stdout = std::io::stdout().lock();
+        // Nowhere to report the failure to, just like with `eprint`.
+        let line = match &self.tag {
+            Some(tag) => Cow::Owned(tag.apply(line)),
+            None => Cow::Borrowed(line),
+        };
+        let _ = stdout.write_all(&line).and_then(|()| stdout.flush());
+        self.printed();
+    }
+
+    fn printed(&mut self) {
+        // Since we print a newline, there is no "remnants".
+        self.set_has_newline(true);
+        self.pending = !self.current.is_empty() || !self.recent.is_empty();
+    }
+
+    /// Erases the status line.
+    pub fn clear(&mut self) {
+        trace!("redraw", "clear");
+        self.erase();
+        self.recent.clear();
+        // Nothing to protect from being overwritten.
+        self.set_has_newline(true);
+        self.current.clear();
+        self.pending = false;
+        self.write_out();
+    }
+
+    /// Erases whatever is drawn, leaving the cursor where the status starts.
+    fn erase(&mut self) {
+        if self.window > 1 {
+            let _ = write!(self.buffer, "{}", erase_rows(self.drawn_rows));
+        } else {
+            let _ = self.screen.erase(&mut self.buffer, self.previous_width);
+        }
+        self.previous_width = 0;
+        self.drawn_rows = 0;
+    }
+
+    /// Replaces the status line with the given text for good, so it stays in
+    /// the scrollback.
+    pub fn finish_with(&mut self, line: &str) {
+        let line = self.tagged(line.trim_end()).into_owned();
+        trace!("redraw", "finish with {:?}", line);
+        if self.window > 1 {
+            let _ = writeln!(self.buffer, "{}{}", erase_rows(self.drawn_rows), line);
+        } else {
+            let _ = self
+                .screen
+                .finish(&mut self.buffer, &line, self.previous_width);
+        }
+        self.previous_width = 0;
+        self.drawn_rows = 0;
+        self.set_has_newline(true);
+        self.current.clear();
+        self.pending = false;
+        self.write_out();
+    }
+
+    /// Makes sure the status line is not overwritten by whatever is printed
+    /// after us.
+    pub fn finish(&mut self) {
+        self.flush();
+        if !self.has_newline {
+            self.buffer.push(b'\n');
+            self.set_has_newline(true);
+        }
+        self.write_out();
+        self.previous_width = 0;
+        // The window stays in the scrollback as is.
+        self.drawn_rows = 0;
+        self.recent.clear();
+    }
+}
+
+impl Drop for StatusLine {
+    fn drop(&mut self) {
+        self.write_out();
+    }
+}
+
+/// Cuts the line down to the `columns`, if the terminal width is known.
+fn fit(line: &str, columns: Option<usize>) -> Cow<'_, str> {
+    match columns {
+        Some(columns) => width::truncate(line, columns),
+        None => Cow::Borrowed(line),
+    }
+}
+
+/// The escape sequences erasing the `rows` drawn so far, leaving the cursor at
+/// the beginning of the topmost of them.
+fn erase_rows(rows: usize) -> String {
+    match rows {
+        0 => String::new(),
+        1 => "\r\x1b[J".to_owned(),
+        rows => format!("\r\x1b[{}A\x1b[J", rows - 1),
+    }
+}
+
+/// Whether it's time to redraw the status line drawn at `drawn_at`.
+fn is_frame_over(drawn_at: Option<Instant>, now: Instant) -> bool {
+    drawn_at.is_none_or(|drawn_at| now.saturating_duration_since(drawn_at) >= FRAME)
+}
+
+#[test]
+fn limits_frame_rate() {
+    let now = Instant::now();
+    assert!(is_frame_over(None, now));
+    assert!(!is_frame_over(Some(now), now + FRAME / 2));
+    assert!(is_frame_over(Some(now), now + FRAME));
+}
+
+#[test]
+fn erases_window_rows() {
+    assert_eq!(erase_rows(0), "");
+    assert_eq!(erase_rows(1), "\r\x1b[J");
+    assert_eq!(erase_rows(3), "\r\x1b[2A\x1b[J");
+}
@@ -0,0 +1,80 @@
+//! `--status-file PATH`: the status kept in a file as plain text, for the
+//! status bars of tmux, polybar and the like to display, say, with
+//! `#(cat ~/.cache/build-status)`. Once cargo exits, the file is left with the
+//! outcome of the build, like `✔ Finished in 12.4s (180 crates)`.
+//!
+//! The file is replaced as a whole every time (a temporary file next to it is
+//! renamed over it), so that a reader never sees it half-written.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The file the status is written to.
+#[derive(Debug)]
+pub struct StatusFile {
+    path: PathBuf,
+    /// What the file says at the moment, if it's been written already.
+    written: Option<String>,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        StatusFile {
+            path,
+            written: None,
+        }
+    }
+
+    /// Writes the status to the file, the color codes stripped, unless it's
+    /// the same as before.
+    pub fn update(&mut self, status: &str) -> io::Result<()> {
+        let status = crate::strip_colors(status);
+        let status = status.trim();
+        if self.written.as_deref() == Some(status) {
+            return Ok(());
+        }
+        replace(&self.path, &format!("{}\n", status))?;
+        self.written = Some(status.to_owned());
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Replaces the contents of the file at once.
+fn replace(path: &Path, contents: &str) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    let temporary = path.with_file_name(name);
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)
+}
+
+#[test]
+fn writes_status() {
+    let path = std::env::temp_dir().join(format!(
+        "cargo-single-line-status-{}.txt",
+        std::process::id()
+    ));
+    let mut file = StatusFile::new(path.clone());
+    file.update("[3/~180] \x1b[1;32m   Compiling\x1b[0m serde v1.0.200")
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[3/~180]    Compiling serde v1.0.200\n"
+    );
+    // Only the changes are written.
+    std::fs::remove_file(&path).unwrap();
+    file.update("[3/~180]    Compiling serde v1.0.200").unwrap();
+    assert!(!path.exists());
+    file.update("    ✔ Finished in 12.4s (180 crates)").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "✔ Finished in 12.4s (180 crates)\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
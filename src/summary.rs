@@ -0,0 +1,117 @@
+//! The summary of the warnings and errors printed after the build in the
+//! `--summary` mode, instead of the diagnostics scrolling past the status line.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    diagnostics::{Diagnostic, Level},
+    l10n,
+};
+
+/// Groups the diagnostics by crate and then by lint (or error code). The
+/// identical diagnostics, like the same warning in a library and in its tests,
+/// are displayed once.
+pub fn render(diagnostics: &[Diagnostic]) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+    let count = |level| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == level)
+            .count()
+    };
+    let mut summary = l10n::text(
+        "diagnostics-summary",
+        &[
+            ("errors", &count(Level::Error)),
+            ("warnings", &count(Level::Warning)),
+        ],
+    );
+    summary.push('\n');
+
+    type Group<'a> = BTreeMap<(Level, Option<&'a str>), Vec<(&'a Diagnostic, usize)>>;
+    let mut by_crate: BTreeMap<Option<&str>, Group> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        let group = by_crate
+            .entry(diagnostic.crate_name.as_deref())
+            .or_default()
+            .entry((diagnostic.level, diagnostic.code.as_deref()))
+            .or_default();
+        match group
+            .iter_mut()
+            .find(|(seen, _)| seen.lines == diagnostic.lines)
+        {
+            Some((_, times)) => *times += 1,
+            None => group.push((diagnostic, 1)),
+        }
+    }
+    for (name, groups) in by_crate {
+        match name {
+            Some(name) => {
+                let _ = writeln!(summary, "`{}`:", name);
+            }
+            None => {
+                let _ = writeln!(summary, "(unknown crate):");
+            }
+        }
+        for ((level, code), diagnostics) in groups {
+            let total: usize = diagnostics.iter().map(|(_, times)| times).sum();
+            match code {
+                Some(code) => {
+                    let _ = writeln!(summary, "  {}[{}] ({})", level.as_str(), code, total);
+                }
+                None => {
+                    let _ = writeln!(summary, "  {} ({})", level.as_str(), total);
+                }
+            }
+            for (diagnostic, times) in diagnostics {
+                for line in &diagnostic.lines {
+                    let _ = writeln!(summary, "    {}", line);
+                }
+                if times > 1 {
+                    let _ = writeln!(
+                        summary,
+                        "    {}",
+                        l10n::text("reported-times", &[("count", &times)])
+                    );
+                }
+            }
+        }
+    }
+    Some(summary)
+}
+
+#[test]
+fn groups_diagnostics() {
+    let diagnostic = |level, code: &str, message: &str, crate_name: &str| Diagnostic {
+        level,
+        code: Some(code.into()),
+        message: message.into(),
+        lines: vec![format!("{}: {}", level.as_str(), message)],
+        crate_name: Some(crate_name.into()),
+    };
+    let diagnostics = [
+        diagnostic(Level::Warning, "unused_variables", "unused `x`", "foo"),
+        diagnostic(Level::Error, "E0308", "mismatched types", "foo"),
+        diagnostic(Level::Warning, "unused_variables", "unused `x`", "foo"),
+        diagnostic(Level::Warning, "unused_variables", "unused `y`", "foo"),
+        diagnostic(Level::Warning, "dead_code", "unused `bar`", "bar"),
+    ];
+    assert_eq!(
+        render(&diagnostics).unwrap(),
+        "1 error and 4 warnings, by crate and lint:
+`bar`:
+  warning[dead_code] (1)
+    warning: unused `bar`
+`foo`:
+  error[E0308] (1)
+    error: mismatched types
+  warning[unused_variables] (3)
+    warning: unused `x`
+    (reported 2 times)
+    warning: unused `y`
+"
+    );
+    assert_eq!(render(&[]), None);
+}
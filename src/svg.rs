@@ -0,0 +1,117 @@
+//! A self-contained SVG timeline of the crate compilations.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::{format, timings::CrateTiming};
+
+/// Width of the timeline itself, in pixels.
+const TIMELINE_WIDTH: f64 = 1000.0;
+/// Room for the crate names on the left.
+const LABEL_WIDTH: f64 = 260.0;
+const ROW_HEIGHT: f64 = 20.0;
+/// Room for the time axis at the top.
+const HEADER_HEIGHT: f64 = 30.0;
+
+/// Escapes the text for XML.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the timings of a build that took `total` as a Gantt-style chart.
+pub fn timeline(timings: &[CrateTiming], total: Duration) -> String {
+    let total_secs = total.as_secs_f64().max(0.001);
+    let scale = TIMELINE_WIDTH / total_secs;
+    let width = LABEL_WIDTH + TIMELINE_WIDTH + 80.0;
+    let height = HEADER_HEIGHT + ROW_HEIGHT * timings.len() as f64 + 10.0;
+
+    let mut svg = String::new();
+    // Writing into a `String` never fails.
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="monospace" font-size="12">"#,
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(
+        svg,
+        r#"<text x="4" y="16">Total: {}, {} crates</text>"#,
+        format::duration(total),
+        timings.len()
+    );
+
+    // Vertical grid lines every "nice" interval.
+    let step = grid_step(total_secs);
+    let mut tick = 0.0;
+    while tick <= total_secs {
+        let x = LABEL_WIDTH + tick * scale;
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x:.1}" y1="{top}" x2="{x:.1}" y2="{height}" stroke="#ddd"/><text x="{x:.1}" y="{label_y}" fill="#888">{tick}s</text>"##,
+            top = HEADER_HEIGHT - 4.0,
+            label_y = HEADER_HEIGHT - 8.0,
+        );
+        tick += step;
+    }
+
+    for (row, timing) in timings.iter().enumerate() {
+        let y = HEADER_HEIGHT + row as f64 * ROW_HEIGHT;
+        let x = LABEL_WIDTH + timing.start.as_secs_f64() * scale;
+        let bar_width = (timing.duration.as_secs_f64() * scale).max(1.0);
+        let name = escape(&format!("{} v{}", timing.name, timing.version));
+        let _ = writeln!(
+            svg,
+            r##"<g><title>{name}: {duration}</title><text x="4" y="{text_y:.1}">{name}</text><rect x="{x:.1}" y="{bar_y:.1}" width="{bar_width:.1}" height="{bar_height}" fill="#4a90d9"/><text x="{after:.1}" y="{text_y:.1}" fill="#555">{duration}</text></g>"##,
+            duration = format::duration(timing.duration),
+            text_y = y + 14.0,
+            bar_y = y + 3.0,
+            bar_height = ROW_HEIGHT - 6.0,
+            after = x + bar_width + 4.0,
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Picks a grid interval (in seconds) giving about ten lines.
+fn grid_step(total_secs: f64) -> f64 {
+    const STEPS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+    STEPS
+        .iter()
+        .copied()
+        .find(|step| total_secs / step <= 10.0)
+        .unwrap_or(1200.0)
+}
+
+#[test]
+fn renders_timeline() {
+    let timings = [
+        CrateTiming {
+            name: "syn".into(),
+            version: "2.0.0".into(),
+            start: Duration::ZERO,
+            duration: Duration::from_secs(3),
+        },
+        CrateTiming {
+            name: "a<b>".into(),
+            version: "0.1.0".into(),
+            start: Duration::from_secs(3),
+            duration: Duration::from_secs(1),
+        },
+    ];
+    let svg = timeline(&timings, Duration::from_secs(4));
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(">syn v2.0.0<"));
+    assert!(svg.contains("a&lt;b&gt; v0.1.0"));
+    // The second crate starts at 3/4 of the timeline.
+    assert!(svg.contains(r#"<rect x="1010.0""#));
+}
+
+#[test]
+fn picks_grid_step() {
+    assert_eq!(grid_step(4.0), 1.0);
+    assert_eq!(grid_step(95.0), 10.0);
+    assert_eq!(grid_step(100_000.0), 1200.0);
+}
@@ -0,0 +1,200 @@
+//! `--tag NAME`: several instances sharing a terminal, like the builds of a
+//! few workspaces started at once from a script. Every line is prefixed with
+//! the tag, `[api]    Compiling foo v0.1.0`, and the instances take turns on
+//! the status line instead of garbling each other's.
+//!
+//! The instances on the same terminal coordinate through a lock file named
+//! after it in the runtime directory (`$XDG_RUNTIME_DIR`, or the temporary one
+//! if there's none): the output is written while holding the lock, and the
+//! file tells which instance has written last. The one writing after another
+//! clears the line first, as the other's status might still be dangling there.
+
+use std::io::{self, Write};
+
+/// The colors of the tags, picked by the name so that an instance keeps its
+/// color from one run to another.
+const COLORS: &[u8] = &[36, 35, 32, 33, 34, 31];
+
+/// The tag of the instance.
+#[derive(Debug)]
+pub struct Tag {
+    prefix: String,
+    lock: Option<imp::TerminalLock>,
+}
+
+impl Tag {
+    /// The tag for the `name`, styled if `colored`.
+    pub fn new(name: &str, colored: bool) -> Self {
+        let prefix = if colored {
+            let hash = name.bytes().fold(0usize, |hash, b| {
+                hash.wrapping_mul(31).wrapping_add(usize::from(b))
+            });
+            format!("\x1b[{}m[{}]\x1b[0m ", COLORS[hash % COLORS.len()], name)
+        } else {
+            format!("[{}] ", name)
+        };
+        Tag {
+            prefix,
+            lock: imp::TerminalLock::open(),
+        }
+    }
+
+    /// What the lines are prefixed with.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Prefixes every line of the output.
+    pub fn apply(&self, output: &[u8]) -> Vec<u8> {
+        let mut tagged = Vec::with_capacity(output.len() + self.prefix.len());
+        for line in output.split_inclusive(|&b| b == b'\n') {
+            tagged.extend_from_slice(self.prefix.as_bytes());
+            tagged.extend_from_slice(line);
+        }
+        tagged
+    }
+
+    /// Writes the output to stderr in turn with the other instances.
+    pub fn write(&self, output: &[u8]) -> io::Result<()> {
+        let mut stderr = io::stderr().lock();
+        let Some(lock) = &self.lock else {
+            return stderr.write_all(output).and_then(|()| stderr.flush());
+        };
+        let _guard = lock.acquire()?;
+        if lock.take_turn()? {
+            stderr.write_all(b"\r\x1b[K")?;
+        }
+        stderr.write_all(output).and_then(|()| stderr.flush())
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        ffi::CStr,
+        fs::{File, OpenOptions},
+        io::{self, Read, Seek, SeekFrom, Write},
+        os::unix::{
+            fs::{MetadataExt, OpenOptionsExt},
+            io::AsRawFd,
+        },
+        path::PathBuf,
+    };
+
+    /// The lock of the terminal stderr is.
+    #[derive(Debug)]
+    pub struct TerminalLock {
+        file: File,
+    }
+
+    /// Releases the lock once dropped.
+    pub struct Guard<'a>(&'a File);
+
+    impl Drop for Guard<'_> {
+        fn drop(&mut self) {
+            // SAFETY: `flock` has no memory safety implications.
+            unsafe { libc::flock(self.0.as_raw_fd(), libc::LOCK_UN) };
+        }
+    }
+
+    impl TerminalLock {
+        /// Opens the lock file of the terminal, if stderr is one.
+        pub fn open() -> Option<Self> {
+            // SAFETY: the returned string is copied right away, before
+            // anything else could call `ttyname`.
+            let name = unsafe {
+                let name = libc::ttyname(libc::STDERR_FILENO);
+                if name.is_null() {
+                    return None;
+                }
+                CStr::from_ptr(name).to_string_lossy().into_owned()
+            };
+            // The runtime directory is the user's own, while in the shared
+            // temporary one someone else might have planted a symlink (not
+            // followed) or the file itself (not used).
+            let dir = std::env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .unwrap_or_else(std::env::temp_dir);
+            let path = dir.join(format!("cargo-single-line{}.lock", name.replace('/', "-")));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .mode(0o600)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(path)
+                .ok()?;
+            let metadata = file.metadata().ok()?;
+            // SAFETY: `geteuid` has no memory safety implications.
+            let owned = metadata.uid() == unsafe { libc::geteuid() };
+            (owned && metadata.is_file()).then_some(TerminalLock { file })
+        }
+
+        pub fn acquire(&self) -> io::Result<Guard<'_>> {
+            // SAFETY: `flock` has no memory safety implications.
+            if unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Guard(&self.file))
+        }
+
+        /// Records this instance as the latest one to write, returns `true` if
+        /// it's been another one. Only to be called with the lock acquired.
+        pub fn take_turn(&self) -> io::Result<bool> {
+            let id = std::process::id().to_string();
+            let mut file = &self.file;
+            let mut latest = String::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_string(&mut latest)?;
+            if latest == id {
+                return Ok(false);
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(id.as_bytes())?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+
+    /// Nothing to lock, the instances are on their own.
+    #[derive(Debug)]
+    pub enum TerminalLock {}
+
+    pub struct Guard;
+
+    impl TerminalLock {
+        pub fn open() -> Option<Self> {
+            None
+        }
+
+        pub fn acquire(&self) -> io::Result<Guard> {
+            match *self {}
+        }
+
+        pub fn take_turn(&self) -> io::Result<bool> {
+            match *self {}
+        }
+    }
+}
+
+#[test]
+fn tags_lines() {
+    let tag = Tag::new("api", false);
+    assert_eq!(tag.prefix(), "[api] ");
+    assert_eq!(
+        tag.apply(b"warning: unused\n  |\n"),
+        b"[api] warning: unused\n[api]   |\n"
+    );
+    assert_eq!(
+        Tag::new("api", true).prefix(),
+        Tag::new("api", true).prefix()
+    );
+    assert!(Tag::new("web", true).prefix().ends_with("[web]\x1b[0m "));
+}
@@ -0,0 +1,210 @@
+//! The look of the collapsed `Compiling`/`Checking` lines, customized with
+//! `--format`, like `--format "{icon} {verb:bold,green} {crate} {version}
+//! ({elapsed})"`.
+//!
+//! The fields are `icon`, `verb`, `crate`, `version`, `path` (empty for the
+//! crates from a registry) and `elapsed` (since the start of the build). A
+//! field may be styled after a colon with a comma-separated list of `bold`,
+//! `dim`, `italic`, `underline` and the colors: `black`, `red`, `green`,
+//! `yellow`, `blue`, `magenta`, `cyan` and `white`. The braces themselves are
+//! written as `{{` and `}}`.
+
+use std::time::Duration;
+
+use crate::{format, unit::Unit};
+
+/// A field of the template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Icon,
+    Verb,
+    Crate,
+    Version,
+    Path,
+    Elapsed,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "icon" => Some(Field::Icon),
+            "verb" => Some(Field::Verb),
+            "crate" => Some(Field::Crate),
+            "version" => Some(Field::Version),
+            "path" => Some(Field::Path),
+            "elapsed" => Some(Field::Elapsed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// A field along with its SGR parameters, like `1;32`, if it's styled.
+    Field(Field, Option<String>),
+}
+
+/// The icons telling the verbs apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icons {
+    Unicode,
+    /// For the terminals (or the fonts) without the fancy symbols.
+    Ascii,
+}
+
+impl Icons {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unicode" => Some(Icons::Unicode),
+            "ascii" => Some(Icons::Ascii),
+            _ => None,
+        }
+    }
+
+    fn icon(self, verb: &str) -> &'static str {
+        match (self, verb) {
+            (Icons::Unicode, "Compiling") => "▶",
+            (Icons::Unicode, "Checking") => "▷",
+            (Icons::Unicode, _) => "•",
+            (Icons::Ascii, "Compiling") => ">",
+            (Icons::Ascii, "Checking") => "~",
+            (Icons::Ascii, _) => "-",
+        }
+    }
+}
+
+/// A parsed `--format` template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let (field, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| format!("unclosed {{ in {:?}", template))?;
+                    chars = rest.chars();
+                    let (name, style) = match field.split_once(':') {
+                        Some((name, style)) => (name, Some(sgr(style)?)),
+                        None => (field, None),
+                    };
+                    let field = Field::from_name(name.trim()).ok_or_else(|| {
+                        format!(
+                            "unknown field {:?}, expected icon, verb, crate, version, path or elapsed",
+                            name
+                        )
+                    })?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field, style));
+                }
+                '}' => return Err(format!("unmatched }} in {:?}", template)),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Renders the unit, `elapsed` since the start of the build. The styles
+    /// are only applied if `colored`.
+    pub fn render(&self, unit: &Unit, elapsed: Duration, icons: Icons, colored: bool) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            let (field, style) = match part {
+                Part::Text(text) => {
+                    rendered.push_str(text);
+                    continue;
+                }
+                Part::Field(field, style) => (field, style),
+            };
+            let value = match field {
+                Field::Icon => icons.icon(&unit.verb).to_owned(),
+                Field::Verb => unit.verb.clone(),
+                Field::Crate => unit.name.clone(),
+                Field::Version => unit.version.clone(),
+                Field::Path => unit.path.clone().unwrap_or_default(),
+                Field::Elapsed => format::approximate(elapsed),
+            };
+            match style.as_deref().filter(|_| colored && !value.is_empty()) {
+                Some(style) => rendered.push_str(&format!("\x1b[{}m{}\x1b[0m", style, value)),
+                None => rendered.push_str(&value),
+            }
+        }
+        rendered
+    }
+}
+
+/// Converts a style like `bold,green` to the SGR parameters, `1;32`.
+fn sgr(style: &str) -> Result<String, String> {
+    style
+        .split(',')
+        .map(|name| {
+            Ok(match name.trim() {
+                "bold" => "1",
+                "dim" => "2",
+                "italic" => "3",
+                "underline" => "4",
+                "black" => "30",
+                "red" => "31",
+                "green" => "32",
+                "yellow" => "33",
+                "blue" => "34",
+                "magenta" => "35",
+                "cyan" => "36",
+                "white" => "37",
+                name => return Err(format!("unknown style {:?}", name)),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|codes| codes.join(";"))
+}
+
+#[test]
+fn renders_templates() {
+    let unit = Unit::parse("   Compiling foo v0.1.0 (/work/foo)").unwrap();
+    let template =
+        Template::parse("{icon} {verb:bold,green} {crate}@{version} [{path}] {{{elapsed}}}")
+            .unwrap();
+    assert_eq!(
+        template.render(&unit, Duration::from_secs(72), Icons::Unicode, false),
+        "▶ Compiling foo@0.1.0 [/work/foo] {1m 12s}"
+    );
+    assert_eq!(
+        template.render(&unit, Duration::from_secs(3), Icons::Ascii, true),
+        "> \x1b[1;32mCompiling\x1b[0m foo@0.1.0 [/work/foo] {3s}"
+    );
+
+    let unit = Unit::parse("    Checking syn v2.0.48").unwrap();
+    assert_eq!(
+        Template::parse("{icon} {crate} {path:red}")
+            .unwrap()
+            .render(&unit, Duration::ZERO, Icons::Ascii, true),
+        "~ syn "
+    );
+
+    assert!(Template::parse("{name}").is_err());
+    assert!(Template::parse("{crate:sparkly}").is_err());
+    assert!(Template::parse("{crate").is_err());
+    assert!(Template::parse("crate}").is_err());
+}
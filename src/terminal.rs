@@ -0,0 +1,183 @@
+//! What we know about the terminal we're running in.
+
+/// Color support of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// `NO_COLOR` is set or the terminal is dumb.
+    None,
+    /// The basic 16 colors.
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses the color depth from the environment variables.
+    pub fn detect() -> Self {
+        Self::from_env(
+            std::env::var_os("NO_COLOR").is_some(),
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(no_color: bool, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color {
+            return ColorDepth::None;
+        }
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        match term {
+            Some("dumb") => ColorDepth::None,
+            Some(term) if term.contains("256color") => ColorDepth::Ansi256,
+            Some(term) if term.contains("direct") => ColorDepth::TrueColor,
+            // Windows consoles don't set `TERM`, but they do colors.
+            _ => ColorDepth::Basic,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ColorDepth::None => "no colors",
+            ColorDepth::Basic => "16 colors",
+            ColorDepth::Ansi256 => "256 colors",
+            ColorDepth::TrueColor => "true color",
+        }
+    }
+}
+
+/// The terminal multiplexer we're running under, if any.
+pub fn multiplexer() -> Option<&'static str> {
+    if std::env::var_os("TMUX").is_some() {
+        Some("tmux")
+    } else if std::env::var_os("ZELLIJ").is_some() {
+        Some("zellij")
+    } else if std::env::var_os("STY").is_some() {
+        Some("screen")
+    } else {
+        None
+    }
+}
+
+/// Size of the terminal as `(columns, rows)`, if stderr is a terminal.
+#[cfg(unix)]
+pub fn size() -> Option<(u16, u16)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: `TIOCGWINSZ` only writes into the provided `winsize`.
+    let result = unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col != 0 {
+        Some((size.ws_col, size.ws_row))
+    } else {
+        None
+    }
+}
+
+/// Size of the terminal as `(columns, rows)`, if stderr is a console.
+#[cfg(windows)]
+pub fn size() -> Option<(u16, u16)> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO, STD_ERROR_HANDLE,
+    };
+
+    // SAFETY: the handle is only passed to the console API, which validates it,
+    // and the API only writes into the provided structure.
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        if GetConsoleScreenBufferInfo(handle, &mut info) == 0 {
+            return None;
+        }
+        let columns = info.srWindow.Right - info.srWindow.Left + 1;
+        let rows = info.srWindow.Bottom - info.srWindow.Top + 1;
+        Some((columns.try_into().ok()?, rows.try_into().ok()?))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn size() -> Option<(u16, u16)> {
+    None
+}
+
+/// Whether the console processes the ANSI escape sequences. `None` when not
+/// applicable, i.e. everywhere but on Windows.
+#[cfg(windows)]
+pub fn virtual_terminal_enabled() -> Option<bool> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_ERROR_HANDLE,
+    };
+
+    // SAFETY: see `size`.
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return Some(false);
+        }
+        Some(mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0)
+    }
+}
+
+/// Whether the console processes the ANSI escape sequences. `None` when not
+/// applicable, i.e. everywhere but on Windows.
+#[cfg(not(windows))]
+pub fn virtual_terminal_enabled() -> Option<bool> {
+    None
+}
+
+/// Turns on the processing of the ANSI escape sequences by the console, so that
+/// the colors and the cursor movements work in `cmd.exe` and PowerShell too.
+/// Returns whether the console processes them now, `None` when not applicable,
+/// i.e. everywhere but on Windows.
+///
+/// The setting belongs to the console rather than to us, but it's left on:
+/// cargo and the programs it runs are fine with it.
+#[cfg(windows)]
+pub fn enable_virtual_terminal() -> Option<bool> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_ERROR_HANDLE,
+    };
+
+    // SAFETY: see `size`.
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return Some(false);
+        }
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+            return Some(true);
+        }
+        // Fails on the consoles older than Windows 10.
+        Some(SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0)
+    }
+}
+
+/// Turns on the processing of the ANSI escape sequences by the console. `None`
+/// when not applicable, i.e. everywhere but on Windows.
+#[cfg(not(windows))]
+pub fn enable_virtual_terminal() -> Option<bool> {
+    None
+}
+
+#[test]
+fn detects_color_depth() {
+    assert_eq!(
+        ColorDepth::from_env(true, Some("truecolor"), Some("xterm-256color")),
+        ColorDepth::None
+    );
+    assert_eq!(
+        ColorDepth::from_env(false, Some("truecolor"), Some("xterm")),
+        ColorDepth::TrueColor
+    );
+    assert_eq!(
+        ColorDepth::from_env(false, None, Some("screen-256color")),
+        ColorDepth::Ansi256
+    );
+    assert_eq!(
+        ColorDepth::from_env(false, None, Some("dumb")),
+        ColorDepth::None
+    );
+    assert_eq!(ColorDepth::from_env(false, None, None), ColorDepth::Basic);
+}
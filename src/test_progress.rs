@@ -0,0 +1,164 @@
+//! The output of the test harness of `cargo test`, folded into a single
+//! status line: `tests: 41 passed, 1 failed, 2 running`.
+//!
+//! The harness prints its lines to stdout, which is only read when the tests
+//! are collapsed. The failed tests and their output (the `failures:` section)
+//! are printed as is, along with whatever the harness doesn't recognize.
+
+use crate::{l10n, starts_with};
+
+/// The tally of the tests run so far.
+#[derive(Debug, Default)]
+pub struct TestProgress {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    /// The tests of the current test binary still to be reported.
+    running: usize,
+    /// The current test binary, like `unittests src/lib.rs`.
+    binary: Option<String>,
+    /// Whether a `running N tests` line has been seen at all, since there's
+    /// nothing to sum up otherwise.
+    started: bool,
+    /// Whether the `failures:` section of a binary is being printed.
+    within_failures: bool,
+}
+
+impl TestProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes note of the cargo's `Running ...`/`Doc-tests ...` lines which
+    /// start the test binaries. Returns whether the line is one of them.
+    pub fn observe_cargo(&mut self, line: &str) -> bool {
+        let plain = crate::strip_colors(line);
+        let plain = plain.trim();
+        let binary = if let Some(binary) = plain.strip_prefix("Running ") {
+            // `unittests src/lib.rs (target/debug/deps/foo-0123)`
+            binary.split(" (").next().unwrap_or(binary)
+        } else if plain.starts_with("Doc-tests ") {
+            plain
+        } else {
+            return false;
+        };
+        self.binary = Some(binary.to_owned());
+        true
+    }
+
+    /// Takes note of a line of the test harness. Returns whether the line is
+    /// folded into the [status](Self::status), otherwise it is to be printed.
+    pub fn observe(&mut self, line: &str) -> bool {
+        let plain = crate::strip_colors(line);
+        let plain = plain.trim_end();
+        if let Some(count) = plain
+            .strip_prefix("running ")
+            .and_then(|rest| rest.strip_suffix(" tests").or(rest.strip_suffix(" test")))
+            .and_then(|count| count.parse().ok())
+        {
+            self.running = count;
+            self.started = true;
+            self.within_failures = false;
+            return true;
+        }
+        if starts_with(plain, &["test result: ", "all doctests ran in "]) {
+            self.running = 0;
+            self.within_failures = false;
+            return true;
+        }
+        if self.within_failures {
+            return false;
+        }
+        if plain == "failures:" {
+            self.within_failures = true;
+            return false;
+        }
+        if plain.is_empty() {
+            return true;
+        }
+        let Some(outcome) = plain
+            .strip_prefix("test ")
+            .and_then(|test| test.rsplit_once(" ... "))
+            .map(|(_, outcome)| outcome)
+        else {
+            return false;
+        };
+        let folded = if outcome == "ok" || outcome.starts_with("bench:") {
+            self.passed += 1;
+            true
+        } else if outcome.starts_with("ignored") {
+            self.ignored += 1;
+            true
+        } else if outcome == "FAILED" {
+            self.failed += 1;
+            false
+        } else {
+            return false;
+        };
+        self.running = self.running.saturating_sub(1);
+        folded
+    }
+
+    /// The status line, with the current binary while its tests are running.
+    /// `None` until the first test binary reports its tests.
+    pub fn status(&self) -> Option<String> {
+        if !self.started {
+            return None;
+        }
+        let counts = [
+            ("tests-passed", self.passed),
+            ("tests-failed", self.failed),
+            ("tests-ignored", self.ignored),
+            ("tests-running", self.running),
+        ];
+        let counts = counts
+            .iter()
+            .filter(|&&(key, count)| count > 0 || key == "tests-passed")
+            .map(|&(key, count)| l10n::text(key, &[("count", &count)]))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = l10n::text("tests-progress", &[("counts", &counts)]);
+        Some(match self.binary.as_deref().filter(|_| self.running > 0) {
+            Some(binary) => format!("{} ({})", status, binary),
+            None => status,
+        })
+    }
+}
+
+#[test]
+fn folds_test_output() {
+    let mut tests = TestProgress::new();
+    assert!(!tests.observe_cargo("    Finished `test` profile in 1.2s\n"));
+    assert!(tests.observe_cargo("     Running unittests src/lib.rs (target/debug/deps/foo-0123)\n"));
+    assert!(!tests.observe("hello from a test\n"));
+    assert!(tests.observe("\n"));
+    assert_eq!(tests.status(), None);
+    assert!(tests.observe("running 3 tests\n"));
+    assert!(tests.observe("test a ... ok\n"));
+    assert_eq!(
+        tests.status().unwrap(),
+        "tests: 1 passed, 2 running (unittests src/lib.rs)"
+    );
+    assert!(!tests.observe("test b ... FAILED\n"));
+    assert!(tests.observe("test c ... ignored, slow\n"));
+    assert!(tests.observe("\n"));
+    assert!(!tests.observe("failures:\n"));
+    assert!(!tests.observe("\n"));
+    assert!(!tests.observe("---- b stdout ----\n"));
+    assert!(!tests.observe("test b ... ok\n"));
+    assert!(tests.observe(
+        "test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out\n"
+    ));
+    assert!(tests.observe("\n"));
+    assert_eq!(
+        tests.status().unwrap(),
+        "tests: 1 passed, 1 failed, 1 ignored"
+    );
+
+    assert!(tests.observe_cargo("   Doc-tests foo\n"));
+    assert!(tests.observe("running 1 test\n"));
+    assert_eq!(
+        tests.status().unwrap(),
+        "tests: 1 passed, 1 failed, 1 ignored, 1 running (Doc-tests foo)"
+    );
+}
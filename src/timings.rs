@@ -0,0 +1,157 @@
+//! Per-crate compilation times.
+//!
+//! Cargo doesn't tell when a crate is done compiling, so a crate is considered
+//! to be compiling until the next one starts (or the build is over). With
+//! parallel builds this is not exact, but good enough to spot the crates that
+//! dominate the build time.
+
+use std::time::{Duration, Instant};
+
+use crate::{format, unit::Unit};
+
+/// How many crates the `--timings-lite` table lists.
+const TOP: usize = 10;
+
+/// How long a crate took to compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateTiming {
+    pub name: String,
+    pub version: String,
+    /// When the compilation has started, relative to the start of the build.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Collects [`CrateTiming`]s from the cargo output.
+#[derive(Debug)]
+pub struct Timings {
+    /// When the build has started.
+    started: Instant,
+    current: Option<(Unit, Instant)>,
+    finished: Vec<CrateTiming>,
+}
+
+impl Timings {
+    pub fn new(started: Instant) -> Self {
+        Timings {
+            started,
+            current: None,
+            finished: Vec::new(),
+        }
+    }
+
+    /// Takes a note of a line printed by cargo at the moment `now`.
+    pub fn observe(&mut self, line: &str, now: Instant) {
+        if let Some(unit) = Unit::parse(line) {
+            self.finish_current(now);
+            self.current = Some((unit, now));
+        }
+    }
+
+    /// Finalizes the measurements and returns the timings in the order the
+    /// crates were compiled.
+    pub fn finish(mut self, now: Instant) -> Vec<CrateTiming> {
+        self.finish_current(now);
+        self.finished
+    }
+
+    fn finish_current(&mut self, now: Instant) {
+        if let Some((unit, started)) = self.current.take() {
+            self.finished.push(CrateTiming {
+                name: unit.name,
+                version: unit.version,
+                start: started.saturating_duration_since(self.started),
+                duration: now.saturating_duration_since(started),
+            });
+        }
+    }
+}
+
+/// The table of the slowest crates for `--timings-lite`, the units of the same
+/// crate (the build script, the library, the tests) added up. `None` if
+/// nothing was compiled.
+pub fn table(timings: &[CrateTiming], total: Duration) -> Option<String> {
+    let mut crates: Vec<(String, Duration)> = Vec::new();
+    for timing in timings {
+        let name = format!("{} v{}", timing.name, timing.version);
+        match crates.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, duration)) => *duration += timing.duration,
+            None => crates.push((name, timing.duration)),
+        }
+    }
+    if crates.is_empty() {
+        return None;
+    }
+    // The stable sort keeps the crates compiled first ahead of the ties.
+    crates.sort_by(|(_, a), (_, b)| b.cmp(a));
+    crates.truncate(TOP);
+    let name_width = crates
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(Some("crate".len()))
+        .max()
+        .unwrap_or_default();
+    let mut table = format!(
+        "{:<name_width$}  {:>8}  {:>5}\n",
+        "crate",
+        "time",
+        "share",
+        name_width = name_width
+    );
+    for (name, duration) in crates {
+        let share = duration.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON) * 100.0;
+        table.push_str(&format!(
+            "{:<name_width$}  {:>8}  {:>4.0}%\n",
+            name,
+            format::duration(duration),
+            share,
+            name_width = name_width
+        ));
+    }
+    Some(table)
+}
+
+#[test]
+fn measures_time_between_units() {
+    let start = Instant::now();
+    let mut timings = Timings::new(start);
+    timings.observe("   Compiling foo v0.1.0\n", start);
+    timings.observe("warning: unused variable\n", start + Duration::from_secs(1));
+    timings.observe("   Compiling bar v0.2.0\n", start + Duration::from_secs(3));
+    let timings = timings.finish(start + Duration::from_secs(4));
+    assert_eq!(
+        timings,
+        [
+            CrateTiming {
+                name: "foo".into(),
+                version: "0.1.0".into(),
+                start: Duration::ZERO,
+                duration: Duration::from_secs(3),
+            },
+            CrateTiming {
+                name: "bar".into(),
+                version: "0.2.0".into(),
+                start: Duration::from_secs(3),
+                duration: Duration::from_secs(1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn tabulates_slowest_crates() {
+    let timing = |name: &str, secs| CrateTiming {
+        name: name.into(),
+        version: "1.0.0".into(),
+        start: Duration::ZERO,
+        duration: Duration::from_secs(secs),
+    };
+    assert_eq!(table(&[], Duration::from_secs(1)), None);
+    let timings = [timing("syn", 3), timing("serde", 4), timing("syn", 2)];
+    assert_eq!(
+        table(&timings, Duration::from_secs(10)).unwrap(),
+        "crate             time  share\n\
+         syn v1.0.0        5.0s    50%\n\
+         serde v1.0.0      4.0s    40%\n"
+    );
+}
@@ -0,0 +1,103 @@
+//! The build status mirrored to the terminal's window (or tab) title with
+//! `--set-title`, like `cargo: 45/180 compiling tokio`, for the build running
+//! in a background tab.
+//!
+//! The title is set with the OSC 0 sequence. The one in place before is saved
+//! on the terminal's title stack (`CSI 22 t`) and restored from it after the
+//! build (`CSI 23 t`), or after a panic. The terminals without the stack are
+//! left with an empty title, which they replace with their default one.
+
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::unit::Unit;
+
+/// Whether the title has been changed and is to be restored.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the terminal displays a title, judging by `TERM`.
+fn supported_term(term: Option<&str>) -> bool {
+    // The Linux console has no title to show, and nothing is known about the
+    // dumb terminals. Windows consoles don't set `TERM`, but they do titles.
+    !matches!(term, Some("dumb" | "linux"))
+}
+
+/// Whether the title can be set: stderr is a terminal that displays one.
+pub fn supported() -> bool {
+    atty::is(atty::Stream::Stderr) && supported_term(std::env::var("TERM").ok().as_deref())
+}
+
+fn write(sequence: &str) {
+    let mut stderr = std::io::stderr().lock();
+    // A title is not worth reporting a failure.
+    let _ = stderr
+        .write_all(sequence.as_bytes())
+        .and_then(|()| stderr.flush());
+}
+
+/// The title for the unit being compiled, `done` out of the `total` crates,
+/// if the estimate makes sense.
+pub fn text(unit: &Unit, done: usize, total: Option<usize>) -> String {
+    let verb = unit.verb.to_lowercase();
+    match total.filter(|total| *total >= done && *total != 0) {
+        Some(total) => format!("cargo: {}/{} {} {}", done, total, verb, unit.name),
+        None => format!("cargo: {} {} {}", done, verb, unit.name),
+    }
+}
+
+/// The title of the terminal, restored once dropped.
+#[derive(Debug)]
+pub struct Title {
+    current: String,
+}
+
+impl Title {
+    /// Saves the current title, if the terminal is [supported](supported).
+    pub fn start() -> Option<Self> {
+        if !supported() {
+            return None;
+        }
+        write("\x1b[22;0t");
+        CHANGED.store(true, Ordering::SeqCst);
+        Some(Title {
+            current: String::new(),
+        })
+    }
+
+    pub fn set(&mut self, title: &str) {
+        if self.current != title {
+            let printable: String = title.chars().filter(|c| !c.is_control()).collect();
+            write(&format!("\x1b]0;{}\x07", printable));
+            self.current = title.to_owned();
+        }
+    }
+}
+
+impl Drop for Title {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Restores the title saved by [`Title::start`], if it's been changed. Also
+/// works when the [`Title`] is out of reach, like in a panic hook.
+pub fn restore() {
+    if CHANGED.swap(false, Ordering::SeqCst) {
+        write("\x1b]0;\x07\x1b[23;0t");
+    }
+}
+
+#[test]
+fn describes_build() {
+    let unit = Unit::parse("   Compiling tokio v1.37.0").unwrap();
+    assert_eq!(text(&unit, 45, Some(180)), "cargo: 45/180 compiling tokio");
+    assert_eq!(text(&unit, 45, None), "cargo: 45 compiling tokio");
+    assert_eq!(text(&unit, 45, Some(40)), "cargo: 45 compiling tokio");
+
+    assert!(supported_term(Some("xterm-256color")));
+    assert!(supported_term(None));
+    assert!(!supported_term(Some("dumb")));
+    assert!(!supported_term(Some("linux")));
+}
@@ -0,0 +1,258 @@
+//! Presets for the cargo subcommands of the third-party tools, which print
+//! their own progress along with cargo's.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::cargo_args::CargoArgs;
+
+/// A tool whose output we know how to compact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// `cargo miri`: the sysroot build, then the tests interpreted one by one.
+    Miri,
+    /// `cargo fuzz run`: libFuzzer reporting its stats over and over.
+    Fuzz,
+    /// `cargo embed`, `cargo flash`, `cargo espflash` or a flashing runner
+    /// like `probe-rs run`: the flashing progress, then the device output.
+    Embedded,
+    /// `cargo apk` or `cargo ndk`, possibly run by Gradle: the Gradle tasks,
+    /// the NDK toolchain and the packaging.
+    Android,
+}
+
+impl Tool {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "miri" => Some(Tool::Miri),
+            "fuzz" => Some(Tool::Fuzz),
+            "embedded" => Some(Tool::Embedded),
+            "android" => Some(Tool::Android),
+            _ => None,
+        }
+    }
+
+    /// Picks the tool by the cargo subcommand.
+    pub fn detect(cargo_args: &CargoArgs) -> Option<Self> {
+        match cargo_args.subcommand.as_deref()? {
+            "miri" => Some(Tool::Miri),
+            "fuzz" => Some(Tool::Fuzz),
+            "embed" | "flash" | "espflash" => Some(Tool::Embedded),
+            "apk" | "ndk" => Some(Tool::Android),
+            _ => None,
+        }
+    }
+
+    /// What to put on the status line for a progress line of the tool, if it
+    /// is one. Everything else is handled as usual, so the errors stay
+    /// visible.
+    pub fn status<'a>(&self, line: &'a str) -> Option<Cow<'a, str>> {
+        let plain = crate::strip_colors(line);
+        let plain = plain.trim();
+        match self {
+            Tool::Miri => miri_status(line, plain),
+            Tool::Fuzz => fuzz_status(plain).map(Cow::Owned),
+            Tool::Embedded => embedded_status(line, plain),
+            Tool::Android => android_status(line, plain),
+        }
+    }
+}
+
+/// The sysroot preparation, the test binaries being started and the tests
+/// that passed. The test results are printed to stdout, where they are folded
+/// like the ones of `cargo test`, so they only reach us with `--pty`. The
+/// failed tests and the undefined behavior reports are left alone.
+fn miri_status<'a>(line: &'a str, plain: &str) -> Option<Cow<'a, str>> {
+    const PREFIXES: &[&str] = &[
+        "Preparing a sysroot for Miri",
+        "A sysroot for Miri is now available",
+        "Running ",
+        "Doc-tests ",
+        "running ",
+    ];
+    let passed =
+        plain.starts_with("test ") && (plain.ends_with("... ok") || plain.ends_with("... ignored"));
+    (passed || PREFIXES.iter().any(|prefix| plain.starts_with(prefix)))
+        .then_some(Cow::Borrowed(line))
+}
+
+/// The periodic libFuzzer stats, like `#1048576 pulse cov: 14 ft: 15 corp:
+/// 2/3b lim: 4 exec/s: 349525 rss: 30Mb`, and its `INFO:` chatter. The new
+/// coverage (`NEW`) is printed as is, and so are the crash reports.
+fn fuzz_status(plain: &str) -> Option<String> {
+    if plain.starts_with("INFO: ") {
+        return Some(format!("{:>12} {}", "Fuzzing", plain));
+    }
+    let mut words = plain.split_whitespace();
+    let runs = words.next()?.strip_prefix('#')?;
+    if !matches!(words.next()?, "pulse" | "INITED" | "REDUCE" | "DONE") {
+        return None;
+    }
+    let (mut coverage, mut corpus, mut speed) = (None, None, None);
+    while let Some(word) = words.next() {
+        match word {
+            "cov:" => coverage = words.next(),
+            "corp:" => corpus = words.next().and_then(|corpus| corpus.split_once('/')),
+            "exec/s:" => speed = words.next(),
+            _ => {}
+        }
+    }
+    let (corpus, size) = corpus?;
+    Some(format!(
+        "{:>12} {}",
+        "Fuzzing",
+        crate::l10n::text(
+            "fuzzing",
+            &[
+                ("runs", &runs),
+                ("speed", &speed?),
+                ("corpus", &corpus),
+                ("size", &size),
+                ("coverage", &coverage?),
+            ]
+        )
+    ))
+}
+
+/// The flashing steps and their progress bars, like `Programming ✔
+/// [00:00:01] [####] 64.00 KiB/64.00 KiB` of probe-rs or `[00:00:02] [====]
+/// 13/13 0x0` of espflash. Whatever the device prints (RTT, the serial
+/// monitor) is left alone.
+fn embedded_status<'a>(line: &'a str, plain: &str) -> Option<Cow<'a, str>> {
+    /// The elapsed time of a progress bar.
+    static ELAPSED: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(^|\s)\[\d{2}:\d{2}:\d{2}\]\s").expect("Regex is well-formed"));
+    const PREFIXES: &[&str] = &[
+        "Erasing",
+        "Programming",
+        "Verifying",
+        "Flashing",
+        "Connecting...",
+        "Writing",
+        "Resetting",
+        "Chip type:",
+        "Crystal frequency:",
+        "Flash size:",
+        "Features:",
+        "MAC address:",
+        "App/part. size:",
+        "Serial port:",
+        "Using flash stub",
+    ];
+    (PREFIXES.iter().any(|prefix| plain.starts_with(prefix)) || ELAPSED.is_match(plain))
+        .then_some(Cow::Borrowed(line))
+}
+
+/// The Gradle tasks and progress, the ABIs being built by cargo-ndk, the
+/// packaging steps of cargo-apk and the harmless warnings of the NDK's clang
+/// (as is or relayed by a build script). The failures are left alone.
+fn android_status<'a>(line: &'a str, plain: &str) -> Option<Cow<'a, str>> {
+    const PREFIXES: &[&str] = &[
+        // Gradle.
+        "> Task ",
+        "> Configure project",
+        "> Transform ",
+        "BUILD SUCCESSFUL",
+        "Starting a Gradle Daemon",
+        // cargo-ndk.
+        "Building ",
+        "[INFO]",
+        // cargo-apk.
+        "Aligning ",
+        "Signing ",
+        "Packaging ",
+        "Using package ",
+    ];
+    // Like `<=====--------> 40% EXECUTING [12s]`.
+    let gradle_progress = plain.starts_with('<') && plain.contains("% ");
+    let clang_noise = plain.contains("clang: warning: argument unused during compilation");
+    let tasks_summary =
+        plain.ends_with("actionable tasks") || plain.contains(" actionable tasks: ");
+    (gradle_progress
+        || clang_noise
+        || tasks_summary
+        || PREFIXES.iter().any(|prefix| plain.starts_with(prefix)))
+    .then_some(Cow::Borrowed(line))
+}
+
+#[test]
+fn compacts_miri() {
+    let miri = Tool::Miri;
+    for line in [
+        "Preparing a sysroot for Miri (target: x86_64-unknown-linux-gnu)... done\n",
+        "     Running unittests src/lib.rs (target/miri/x86_64-unknown-linux-gnu/debug/deps/foo-1)\n",
+        "running 3 tests\n",
+        "test tests::works ... ok\n",
+    ] {
+        assert_eq!(miri.status(line).as_deref(), Some(line));
+    }
+    for line in [
+        "test tests::unsound ... FAILED\n",
+        "error: Undefined Behavior: using uninitialized data, but this operation requires initialized memory\n",
+        "  --> src/lib.rs:4:5\n",
+        "test result: FAILED. 2 passed; 1 failed; 0 ignored\n",
+    ] {
+        assert_eq!(miri.status(line), None);
+    }
+}
+
+#[test]
+fn compacts_fuzzing() {
+    let fuzz = Tool::Fuzz;
+    assert_eq!(
+        fuzz.status("#1048576\tpulse  cov: 14 ft: 15 corp: 2/3b lim: 4 exec/s: 349525 rss: 30Mb\n")
+            .as_deref(),
+        Some("     Fuzzing #1048576: 349525 exec/s, corpus 2 (3b), coverage 14")
+    );
+    assert!(fuzz.status("INFO: Seed: 1234\n").is_some());
+    for line in [
+        "#3\tNEW    cov: 14 ft: 15 corp: 2/3b lim: 4 exec/s: 0 rss: 30Mb L: 2/2 MS: 1 InsertByte-\n",
+        "==1234== ERROR: libFuzzer: deadly signal\n",
+        "artifact_prefix='artifacts/'; Test unit written to artifacts/crash-da39a3ee\n",
+    ] {
+        assert_eq!(fuzz.status(line), None);
+    }
+}
+
+#[test]
+fn compacts_flashing() {
+    let embedded = Tool::Embedded;
+    for line in [
+        "      Erasing ✔ [00:00:00] [####################] 128.00 KiB/128.00 KiB @ 245.12 KiB/s\n",
+        "  Programming ✔ [00:00:01] [####################] 64.00 KiB/64.00 KiB @ 38.02 KiB/s\n",
+        "[00:00:02] [========================================]      13/13      0x0\n",
+        "Chip type:         esp32c3 (revision v0.4)\n",
+    ] {
+        assert_eq!(embedded.status(line).as_deref(), Some(line));
+    }
+    for line in [
+        "INFO  Hello from the device [00:00:01]\n",
+        "0.000000 INFO  boot counter: 3\n",
+        "Error: Connecting to the chip was unsuccessful.\n",
+    ] {
+        assert_eq!(embedded.status(line), None);
+    }
+}
+
+#[test]
+fn compacts_android() {
+    let android = Tool::Android;
+    for line in [
+        "> Task :app:mergeDebugNativeLibs\n",
+        "<=============> 100% EXECUTING [12s]\n",
+        "Building arm64-v8a (aarch64-linux-android)\n",
+        "warning: ring@0.17.8: clang: warning: argument unused during compilation: '-mfpu=neon'\n",
+        "42 actionable tasks: 42 executed\n",
+    ] {
+        assert_eq!(android.status(line).as_deref(), Some(line));
+    }
+    for line in [
+        "BUILD FAILED in 3s\n",
+        "FAILURE: Build failed with an exception.\n",
+        "error: linker `aarch64-linux-android21-clang` not found\n",
+    ] {
+        assert_eq!(android.status(line), None);
+    }
+}
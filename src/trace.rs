@@ -0,0 +1,104 @@
+//! Internal tracing, for the bug reports.
+//!
+//! When `CARGO_SINGLE_LINE_LOG` is set to a path, what the plugin decides (what
+//! the terminal is capable of, how every line of the cargo output is
+//! classified, what's drawn, what happens to cargo) is appended to that file.
+//! Nothing is traced otherwise, and the [`trace!`] calls cost a single check.
+//!
+//! The records are [`tracing`] events, the category being the target, so
+//! `CARGO_SINGLE_LINE_LOG_FILTER` narrows them down with the directives of
+//! [`EnvFilter`], like `child,redraw` or `line=off`.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use tracing_subscriber::{fmt::time::Uptime, EnvFilter};
+
+/// The environment variable with the path to the trace file.
+pub const ENV: &str = "CARGO_SINGLE_LINE_LOG";
+
+/// The environment variable with the categories to trace, all of them by
+/// default.
+pub const FILTER_ENV: &str = "CARGO_SINGLE_LINE_LOG_FILTER";
+
+/// The trace file, the secrets redacted on the way.
+struct TraceFile(File);
+
+impl Write for TraceFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A record is written at once, so it's never redacted in pieces.
+        let record = String::from_utf8_lossy(buf);
+        self.0
+            .write_all(crate::redact::redact(&record).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Opens the trace file, if asked to. Only the first call matters.
+pub fn init() {
+    let Some(path) = std::env::var_os(ENV).filter(|path| !path.is_empty()) else {
+        return;
+    };
+    let file = match std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!(
+                "cargo-single-line: warning: unable to open the trace file {:?}: {}",
+                path, e
+            );
+            return;
+        }
+    };
+    let filter = match std::env::var(FILTER_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+            eprintln!(
+                "cargo-single-line: warning: invalid {} {:?}, tracing everything: {}",
+                FILTER_ENV, directives, e
+            );
+            EnvFilter::new("trace")
+        }),
+        Err(_) => EnvFilter::new("trace"),
+    };
+    let installed = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(TraceFile(file)))
+        .with_timer(Uptime::default())
+        .with_ansi(false)
+        .with_level(false)
+        .try_init()
+        .is_ok();
+    if installed {
+        trace!(
+            "start",
+            "{} {} (pid {}), arguments: {:?}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            std::process::id(),
+            std::env::args_os().skip(1).collect::<Vec<_>>()
+        );
+    }
+}
+
+/// Traces a message of a category, like `trace!("child", "exited: {}", status)`.
+macro_rules! trace {
+    ($category:literal, $($arg:tt)+) => {
+        // Formatted out here, as `tracing` brings its own `display` and
+        // `debug` into the scope of the arguments.
+        match format_args!($($arg)+) {
+            message => ::tracing::trace!(target: $category, "{}", message),
+        }
+    };
+}
+
+pub(crate) use trace;
@@ -0,0 +1,200 @@
+//! Transcripts of the cargo output, recorded with `--record` and fed through
+//! the filtering with `--dry-filter` instead of running cargo, to work on the
+//! `--collapse-pattern`s and the like without building anything.
+//!
+//! A transcript is a JSON object per line, the time since the start in
+//! milliseconds along with what's been printed to which stream, and cargo's
+//! exit code in the end:
+//!
+//! ```text
+//! {"ms":0,"stderr":"   Compiling foo v0.1.0 (/work/foo)\n"}
+//! {"ms":1480,"stdout":"test tests::it_works ... ok\n"}
+//! {"ms":1502,"exit":0}
+//! ```
+//!
+//! Anything else, like the stderr of cargo saved with `2> build.txt`, is taken
+//! for the stderr as is, with no timing.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Entry {
+    Stderr(String),
+    Stdout(String),
+    Exit(i32),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    ms: u64,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+/// Writes the transcript of a build as it goes.
+#[derive(Debug)]
+pub struct Recorder {
+    output: BufWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Creates (or truncates) the file, the time is counted from `started`.
+    pub fn create(path: &Path, started: Instant) -> io::Result<Self> {
+        Ok(Recorder {
+            output: BufWriter::new(File::create(path)?),
+            started,
+        })
+    }
+
+    fn write(&mut self, entry: Entry) -> io::Result<()> {
+        let record = Record {
+            ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            entry,
+        };
+        serde_json::to_writer(&mut self.output, &record)?;
+        self.output.write_all(b"\n")
+    }
+
+    /// Records the text printed to stderr.
+    pub fn stderr(&mut self, text: &str) -> io::Result<()> {
+        self.write(Entry::Stderr(text.to_owned()))
+    }
+
+    /// Records the text printed to stdout.
+    pub fn stdout(&mut self, text: &str) -> io::Result<()> {
+        self.write(Entry::Stdout(text.to_owned()))
+    }
+
+    /// Records the exit code of cargo, if it has one, and writes everything
+    /// out.
+    pub fn finish(mut self, code: Option<i32>) -> io::Result<()> {
+        if let Some(code) = code {
+            self.write(Entry::Exit(code))?;
+        }
+        self.output.flush()
+    }
+}
+
+/// The output of a stream, piece by piece along with when it's printed.
+type Pieces = Vec<(Duration, Vec<u8>)>;
+
+/// A recorded build.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    stderr: Pieces,
+    stdout: Pieces,
+    /// Cargo's exit code, if it's known.
+    pub exit: Option<i32>,
+}
+
+impl Transcript {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(std::fs::read(path)?))
+    }
+
+    fn parse(contents: Vec<u8>) -> Self {
+        let mut transcript = Transcript::default();
+        for line in contents.split(|&b| b == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let Ok(record) = serde_json::from_slice::<Record>(line) else {
+                // Not a transcript of ours, but the output itself.
+                return Transcript {
+                    stderr: vec![(Duration::ZERO, contents)],
+                    ..Transcript::default()
+                };
+            };
+            let at = Duration::from_millis(record.ms);
+            match record.entry {
+                Entry::Stderr(text) => transcript.stderr.push((at, text.into_bytes())),
+                Entry::Stdout(text) => transcript.stdout.push((at, text.into_bytes())),
+                Entry::Exit(code) => transcript.exit = Some(code),
+            }
+        }
+        transcript
+    }
+
+    /// The readers of the stderr and the stdout. With `realtime`, every
+    /// piece is held back until the time it's been printed at, counting from
+    /// the moment the readers are created.
+    pub fn play(self, realtime: bool) -> (Player, Player) {
+        let started = Instant::now();
+        let player = |pieces: Pieces| Player {
+            pieces: pieces.into_iter(),
+            current: io::Cursor::new(Vec::new()),
+            started: realtime.then_some(started),
+        };
+        (player(self.stderr), player(self.stdout))
+    }
+}
+
+/// Reads the recorded output of a stream.
+#[derive(Debug)]
+pub struct Player {
+    pieces: std::vec::IntoIter<(Duration, Vec<u8>)>,
+    current: io::Cursor<Vec<u8>>,
+    /// When the playing has started, unless the output is read as fast as
+    /// possible.
+    started: Option<Instant>,
+}
+
+impl Read for Player {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let Some((at, piece)) = self.pieces.next() else {
+                return Ok(0);
+            };
+            if let Some(started) = self.started {
+                thread::sleep(at.saturating_sub(started.elapsed()));
+            }
+            self.current = io::Cursor::new(piece);
+        }
+    }
+}
+
+#[test]
+fn replays_transcripts() {
+    let path = std::env::temp_dir().join(format!(
+        "cargo-single-line-transcript-{}.jsonl",
+        std::process::id()
+    ));
+    let mut recorder = Recorder::create(&path, Instant::now()).unwrap();
+    recorder.stderr("   Compiling foo v0.1.0\n").unwrap();
+    recorder.stdout("test it_works ... ok\n").unwrap();
+    recorder.stderr("   Building [=> ] 1/2\r").unwrap();
+    recorder.finish(Some(101)).unwrap();
+    let transcript = Transcript::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(transcript.exit, Some(101));
+
+    let (mut stderr, mut stdout) = transcript.play(true);
+    let mut text = String::new();
+    stderr.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "   Compiling foo v0.1.0\n   Building [=> ] 1/2\r");
+    text.clear();
+    stdout.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "test it_works ... ok\n");
+
+    let output = include_bytes!("../tests/fixtures/build.txt");
+    let transcript = Transcript::parse(output.to_vec());
+    assert_eq!(transcript.exit, None);
+    let (mut stderr, _) = transcript.play(false);
+    let mut played = Vec::new();
+    stderr.read_to_end(&mut played).unwrap();
+    assert_eq!(played, output);
+}
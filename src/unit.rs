@@ -0,0 +1,63 @@
+//! Parsing of the cargo's "unit" lines, like `Compiling foo v0.1.0 (/path)`.
+
+/// Cargo output that marks a start of a crate compilation.
+pub const UNIT_PREFIXES: &[&str] = &["Compiling", "Checking"];
+
+/// A crate mentioned on a cargo status line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    /// What cargo is doing, e.g. `Compiling`.
+    pub verb: String,
+    pub name: String,
+    /// The version without the leading `v`.
+    pub version: String,
+    /// Where the sources are, only given for the local crates.
+    pub path: Option<String>,
+}
+
+impl Unit {
+    /// Parses a `Compiling`/`Checking` line, color codes are fine.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = crate::strip_colors(line);
+        let mut words = line.split_whitespace();
+        let verb = words.next()?;
+        if !UNIT_PREFIXES.contains(&verb) {
+            return None;
+        }
+        let name = words.next()?;
+        let version = words.next()?.strip_prefix('v')?;
+        let rest = words.collect::<Vec<_>>().join(" ");
+        let path = rest
+            .strip_prefix('(')
+            .and_then(|path| path.strip_suffix(')'))
+            .map(str::to_owned);
+        Some(Unit {
+            verb: verb.to_owned(),
+            name: name.to_owned(),
+            version: version.to_owned(),
+            path,
+        })
+    }
+}
+
+#[test]
+fn parses_units() {
+    let unit = Unit::parse(
+        "\u{1b}[1m\u{1b}[32m   Compiling\u{1b}[0m cargo-single-line v0.1.6 (/root/crate)\n",
+    )
+    .unwrap();
+    assert_eq!(
+        unit,
+        Unit {
+            verb: "Compiling".into(),
+            name: "cargo-single-line".into(),
+            version: "0.1.6".into(),
+            path: Some("/root/crate".into()),
+        }
+    );
+    let unit = Unit::parse("    Checking syn v2.0.0").unwrap();
+    assert_eq!(unit.name, "syn");
+    assert_eq!(unit.path, None);
+    assert!(Unit::parse("    Updating crates.io index").is_none());
+    assert!(Unit::parse("   Compiling").is_none());
+}
@@ -0,0 +1,217 @@
+//! Checking for new releases of the plugin.
+//!
+//! We ask cargo (`cargo search`) rather than talking to crates.io ourselves, so
+//! the registry configuration, proxies and such are respected for free.
+
+use std::{
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    io,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::mpsc,
+    time::{Duration, SystemTime},
+};
+
+/// Name of the crate on crates.io.
+pub const CRATE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Version of the running binary.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Setting this environment variable disables the new version notification.
+pub const NO_CHECK_ENV: &str = "CARGO_SINGLE_LINE_NO_UPDATE_CHECK";
+
+/// How often crates.io is asked for a new version.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Compares two `major.minor.patch` versions. Pre-release versions are
+/// considered older than the corresponding releases; anything that doesn't
+/// parse compares as equal, i.e. "no update".
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parse(version: &str) -> Option<(Vec<u64>, bool)> {
+        let (release, pre) = match version.split_once('-') {
+            Some((release, _)) => (release, true),
+            None => (version.split('+').next()?, false),
+        };
+        let numbers = release
+            .split('.')
+            .map(|number| number.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some((numbers, pre))
+    }
+    match (parse(a), parse(b)) {
+        (Some((a, a_pre)), Some((b, b_pre))) => a.cmp(&b).then(b_pre.cmp(&a_pre)),
+        _ => Ordering::Equal,
+    }
+}
+
+/// Extracts the version of the crate from the `cargo search` output, which
+/// looks like `cargo-single-line = "0.1.6"    # A cargo plugin ...`.
+fn parse_search_output(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (name, rest) = line.split_once('=')?;
+        if name.trim() != CRATE_NAME {
+            return None;
+        }
+        let version = rest.trim_start().strip_prefix('"')?;
+        let (version, _) = version.split_once('"')?;
+        Some(version.to_owned())
+    })
+}
+
+/// Looks up the latest published version of the plugin.
+pub fn latest_version(cargo: &OsStr) -> io::Result<Option<String>> {
+    let output = Command::new(cargo)
+        .args(["search", "--limit", "1", "--color", "never", CRATE_NAME])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`cargo search` has failed: {}",
+            output.status
+        )));
+    }
+    Ok(parse_search_output(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Returns the latest version if it's newer than the running one.
+pub fn newer_version(cargo: &OsStr) -> io::Result<Option<String>> {
+    Ok(latest_version(cargo)?
+        .filter(|latest| compare_versions(latest, CURRENT_VERSION) == Ordering::Greater))
+}
+
+/// Runs the `self-update` subcommand. Returns the exit code.
+pub fn self_update(cargo: &OsStr, check_only: bool) -> io::Result<i32> {
+    let latest = match newer_version(cargo)? {
+        Some(latest) => latest,
+        None => {
+            println!("{} {} is up to date", CRATE_NAME, CURRENT_VERSION);
+            return Ok(0);
+        }
+    };
+    println!(
+        "{} {} is available (installed: {})",
+        CRATE_NAME, latest, CURRENT_VERSION
+    );
+    if check_only {
+        return Ok(1);
+    }
+    let status = Command::new(cargo)
+        .args(["install", "--version", &latest, CRATE_NAME])
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Where the result of the latest check is cached.
+fn cache_path() -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_dir.join(CRATE_NAME).join("latest-version"))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+/// Parses the cache file, which contains the timestamp of the check and the
+/// version found, separated by a space. A failed check (like when offline)
+/// leaves the timestamp alone.
+fn parse_cache(contents: &str) -> Option<(u64, Option<String>)> {
+    let contents = contents.trim();
+    let (timestamp, version) = match contents.split_once(' ') {
+        Some((timestamp, version)) => (timestamp, Some(version.to_owned())),
+        None => (contents, None),
+    };
+    Some((timestamp.parse().ok()?, version))
+}
+
+/// Finds out the latest version, asking crates.io at most once per
+/// [`CHECK_INTERVAL`], whether it succeeds or not.
+fn cached_latest_version(cargo: &OsStr) -> io::Result<Option<String>> {
+    let cache = cache_path();
+    let now = unix_now();
+    if let Some(cache) = &cache {
+        let cached = std::fs::read_to_string(cache)
+            .ok()
+            .and_then(|contents| parse_cache(&contents));
+        if let Some((timestamp, version)) = cached {
+            if now.saturating_sub(timestamp) < CHECK_INTERVAL.as_secs() {
+                return Ok(version);
+            }
+        }
+    }
+    let latest = latest_version(cargo);
+    if let Some(cache) = &cache {
+        if let Some(dir) = cache.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = match &latest {
+            Ok(Some(latest)) => format!("{} {}\n", now, latest),
+            _ => format!("{}\n", now),
+        };
+        std::fs::write(cache, contents)?;
+    }
+    latest
+}
+
+/// Starts looking for a newer version in background. The result is only sent
+/// if a newer version is found; the check is never waited for, so if it's not
+/// done by the end of the build, so be it.
+pub fn check_in_background(cargo: OsString) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        if let Ok(Some(latest)) = cached_latest_version(&cargo) {
+            if compare_versions(&latest, CURRENT_VERSION) == Ordering::Greater {
+                let _ = sender.send(latest);
+            }
+        }
+    });
+    receiver
+}
+
+/// The notice displayed after the build when a newer version is available.
+pub fn notice(latest: &str) -> String {
+    crate::l10n::text(
+        "update-available",
+        &[("name", &CRATE_NAME), ("version", &latest)],
+    )
+}
+
+#[test]
+fn compares_versions() {
+    assert_eq!(compare_versions("0.2.0", "0.1.6"), Ordering::Greater);
+    assert_eq!(compare_versions("0.1.10", "0.1.9"), Ordering::Greater);
+    assert_eq!(compare_versions("0.1.6", "0.1.6"), Ordering::Equal);
+    assert_eq!(compare_versions("0.2.0-rc.1", "0.2.0"), Ordering::Less);
+    assert_eq!(compare_versions("0.2.0-rc.1", "0.1.6"), Ordering::Greater);
+    assert_eq!(compare_versions("garbage", "0.1.6"), Ordering::Equal);
+}
+
+#[test]
+fn parses_search_output() {
+    let output = "cargo-single-line = \"0.1.6\"    # A cargo plugin to shrink cargo's output\n\
+                  ... and 2 crates more (use --limit N to see more)\n";
+    assert_eq!(parse_search_output(output).as_deref(), Some("0.1.6"));
+    assert_eq!(
+        parse_search_output("cargo-single-line-ng = \"1.0.0\"\n"),
+        None
+    );
+}
+
+#[test]
+fn parses_cache() {
+    assert_eq!(
+        parse_cache("1700000000 0.2.0\n"),
+        Some((1700000000, Some("0.2.0".to_owned())))
+    );
+    assert_eq!(parse_cache("1700000000\n"), Some((1700000000, None)));
+    assert_eq!(parse_cache("garbage"), None);
+}
@@ -0,0 +1,137 @@
+//! Cargo's verbose output (`cargo build -v`): a `Fresh` line for every crate
+//! that is up to date, hundreds of them in a big workspace, and the full
+//! `Running` rustc invocation of every crate compiled.
+//!
+//! A run of `Fresh` lines is counted on the status line like the downloads
+//! are, and a single `Fresh 142 crates` line is kept once it's over. With
+//! `--hide-invocations`, the invocations are held back, and only the one of a
+//! crate that fails to compile is printed, right before cargo reports it.
+
+use std::collections::HashMap;
+
+use crate::{downloads::Burst, l10n};
+
+/// Counts the `Fresh` lines.
+#[derive(Debug, Default)]
+pub struct FreshLines {
+    /// The `Fresh` of the first line of the run, colors included, along with
+    /// how many lines the run has had so far.
+    run: Option<(String, usize)>,
+}
+
+impl FreshLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a line printed by cargo, returns how it's to be displayed if it's
+    /// a part of (or ends) a run of `Fresh` lines.
+    pub fn observe(&mut self, line: &str) -> Option<Burst> {
+        if !crate::starts_with(line, &["Fresh"]) {
+            let (verb, count) = self.run.take()?;
+            return Some(Burst::Over {
+                summary: format!("{} {}", verb, count_crates(count)),
+                consumed: false,
+            });
+        }
+        let (verb, count) = self
+            .run
+            .get_or_insert_with(|| (verb_of(line).to_owned(), 0));
+        *count += 1;
+        Some(Burst::Progress(format!(
+            "{} {}…",
+            verb,
+            count_crates(*count)
+        )))
+    }
+}
+
+fn count_crates(count: usize) -> String {
+    l10n::text("fresh-crates", &[("count", &count)])
+}
+
+/// The line up to the end of `Fresh` (and the color code resetting it).
+fn verb_of(line: &str) -> &str {
+    let Some(end) = line.find("Fresh").map(|start| start + "Fresh".len()) else {
+        return line;
+    };
+    let end = if line[end..].starts_with("\x1b[0m") {
+        end + "\x1b[0m".len()
+    } else {
+        end
+    };
+    &line[..end]
+}
+
+/// Holds the rustc invocations back.
+#[derive(Debug, Default)]
+pub struct Invocations {
+    /// The latest invocation line of every crate (by its `--crate-name`).
+    held: HashMap<String, String>,
+}
+
+impl Invocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the line is a rustc invocation, which is held back.
+    pub fn hold(&mut self, line: &str) -> bool {
+        let plain = crate::strip_colors(line);
+        if !crate::starts_with(&plain, &["Running"]) {
+            return false;
+        }
+        let Some(name) = plain
+            .split_once(" --crate-name ")
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+        else {
+            return false;
+        };
+        self.held.insert(name.to_owned(), line.to_owned());
+        true
+    }
+
+    /// The invocation of the `crate_name` (as cargo names it in its messages,
+    /// with the dashes), if it's been held back.
+    pub fn take(&mut self, crate_name: &str) -> Option<String> {
+        self.held.remove(&crate_name.replace('-', "_"))
+    }
+}
+
+#[test]
+fn counts_fresh_lines() {
+    let mut fresh = FreshLines::new();
+    assert_eq!(fresh.observe("   Compiling foo v0.1.0\n"), None);
+    assert_eq!(
+        fresh.observe("\x1b[1m\x1b[32m       Fresh\x1b[0m unicode-ident v1.0.12\n"),
+        Some(Burst::Progress(
+            "\x1b[1m\x1b[32m       Fresh\x1b[0m 1 crate…".into()
+        ))
+    );
+    assert_eq!(
+        fresh.observe("\x1b[1m\x1b[32m       Fresh\x1b[0m proc-macro2 v1.0.86\n"),
+        Some(Burst::Progress(
+            "\x1b[1m\x1b[32m       Fresh\x1b[0m 2 crates…".into()
+        ))
+    );
+    assert_eq!(
+        fresh.observe("   Compiling foo v0.1.0\n"),
+        Some(Burst::Over {
+            summary: "\x1b[1m\x1b[32m       Fresh\x1b[0m 2 crates".into(),
+            consumed: false
+        })
+    );
+    assert_eq!(fresh.observe("   Compiling bar v0.1.0\n"), None);
+}
+
+#[test]
+fn holds_invocations() {
+    let mut invocations = Invocations::new();
+    let invocation = "     Running `/home/me/.rustup/toolchains/stable/bin/rustc --crate-name my_crate --edition=2021 src/lib.rs`\n";
+    assert!(invocations.hold(invocation));
+    assert!(!invocations.hold("     Running `target/debug/build/foo-1234/build-script-build`\n"));
+    assert!(!invocations.hold("     Running unittests src/lib.rs (target/debug/deps/foo-5678)\n"));
+    assert_eq!(invocations.take("other"), None);
+    assert_eq!(invocations.take("my-crate").as_deref(), Some(invocation));
+    assert_eq!(invocations.take("my-crate"), None);
+}
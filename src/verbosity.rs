@@ -0,0 +1,96 @@
+//! How much of the output is displayed: `-q` leaves nothing but the errors and
+//! the final status, `-v` prints the diagnostics right away even when they are
+//! summed up in the end, and `-vv` doesn't put anything on the status line.
+//!
+//! These are the plugin's options, cargo's own verbosity is set after the
+//! subcommand as usual (`cargo single-line build -v`).
+
+use crate::{diagnostics::Level, filter::Compaction};
+
+/// The verbosity level of the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    VeryVerbose,
+}
+
+/// What kind of line is printed (the lines put on the status line aside).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Error,
+    Warning,
+    /// A `note:` of cargo's outside of a diagnostic.
+    Note,
+    /// Anything else: the build scripts output, the test runner's lines...
+    Other,
+}
+
+impl Class {
+    /// Classifies a line, given the level of the diagnostic it's a part of.
+    pub fn of(line: &str, within: Option<Level>) -> Self {
+        match within {
+            Some(Level::Error) => Class::Error,
+            Some(Level::Warning) => Class::Warning,
+            None if crate::starts_with(line, &["note:"]) => Class::Note,
+            None => Class::Other,
+        }
+    }
+}
+
+impl Verbosity {
+    /// One level more verbose.
+    pub fn increase(self) -> Self {
+        match self {
+            Verbosity::Quiet => Verbosity::Normal,
+            Verbosity::Normal => Verbosity::Verbose,
+            Verbosity::Verbose | Verbosity::VeryVerbose => Verbosity::VeryVerbose,
+        }
+    }
+
+    /// Whether the lines of the class are printed.
+    pub fn prints(self, class: Class) -> bool {
+        self > Verbosity::Quiet || class == Class::Error
+    }
+
+    /// Whether the diagnostics may be held back until the end of the build.
+    pub fn holds_back(self) -> bool {
+        self <= Verbosity::Normal
+    }
+
+    /// The compaction to use instead of the one matching cargo's verbosity.
+    pub fn compaction(self, compaction: Compaction) -> Compaction {
+        if self == Verbosity::VeryVerbose {
+            Compaction::None
+        } else {
+            compaction
+        }
+    }
+}
+
+#[test]
+fn routes_lines() {
+    assert_eq!(Class::of("error: oops\n", Some(Level::Error)), Class::Error);
+    assert_eq!(Class::of("  |\n", Some(Level::Warning)), Class::Warning);
+    assert_eq!(
+        Class::of("note: to see what the problems were...\n", None),
+        Class::Note
+    );
+    assert_eq!(Class::of("hello from build.rs\n", None), Class::Other);
+
+    assert!(Verbosity::Quiet.prints(Class::Error));
+    assert!(!Verbosity::Quiet.prints(Class::Warning));
+    assert!(!Verbosity::Quiet.prints(Class::Other));
+    assert!(Verbosity::Normal.prints(Class::Note));
+    assert!(Verbosity::Normal.holds_back());
+    assert!(!Verbosity::Verbose.holds_back());
+    assert_eq!(
+        Verbosity::VeryVerbose.compaction(Compaction::Full),
+        Compaction::None
+    );
+    assert_eq!(
+        Verbosity::Normal.increase().increase(),
+        Verbosity::VeryVerbose
+    );
+}
@@ -0,0 +1,171 @@
+//! `--watch`: runs the command again whenever a file of the project changes,
+//! like `cargo watch -x 'single-line check'` would, with the runs counted and
+//! a compact `run #N` separator between them.
+//!
+//! The project is polled for the changes, which takes no dependencies and
+//! works the same everywhere; the `target` directory and the hidden ones
+//! (`.git` and the like) are skipped. Every run is a fresh instance of the
+//! plugin, so one's failure (or `process::exit`) doesn't take the watch down.
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{format, l10n};
+
+/// How often the project is checked for the changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the files have to stay the same before the next run, so that a
+/// "save all" in the editor triggers a single run.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// The files of the project along with their modification times and sizes.
+type Snapshot = Vec<(PathBuf, SystemTime, u64)>;
+
+/// Collects the files under the `dir`, skipping the build output and the
+/// hidden directories.
+fn snapshot(dir: &Path, files: &mut Snapshot) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                snapshot(&entry.path(), files);
+            }
+        } else if let Ok(modified) = metadata.modified() {
+            files.push((entry.path(), modified, metadata.len()));
+        }
+    }
+}
+
+fn take_snapshot(root: &Path) -> Snapshot {
+    let mut files = Vec::new();
+    snapshot(root, &mut files);
+    files.sort();
+    files
+}
+
+/// The tally of the runs so far.
+#[derive(Debug, Default)]
+struct Runs {
+    succeeded: usize,
+    failed: usize,
+    /// How long the runs have taken altogether.
+    total: Duration,
+}
+
+impl Runs {
+    /// The separator printed before the next run.
+    fn separator(&self) -> String {
+        let run = self.succeeded + self.failed + 1;
+        if run == 1 {
+            return l10n::text("watch-first-run", &[]);
+        }
+        l10n::text(
+            "watch-run",
+            &[
+                ("run", &run),
+                ("succeeded", &self.succeeded),
+                ("failed", &self.failed),
+                ("total", &format::duration(self.total)),
+            ],
+        )
+    }
+}
+
+/// Runs the plugin with the `args` (the `--watch` left out) over and over in
+/// the project at the `root`, until interrupted. The separators are dimmed if
+/// the output is `colored`.
+pub fn run(args: &[OsString], root: &Path, colored: bool) -> ! {
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("cargo-single-line: unable to locate itself: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let styled = |text: String| {
+        if colored {
+            format!("\x1b[2m{}\x1b[0m", text)
+        } else {
+            text
+        }
+    };
+    let mut runs = Runs::default();
+    loop {
+        eprintln!("{}", styled(format!("── {} ──", runs.separator())));
+        let files = take_snapshot(root);
+        let started = Instant::now();
+        match Command::new(&program).args(args).status() {
+            Ok(status) if status.success() => runs.succeeded += 1,
+            Ok(_) => runs.failed += 1,
+            Err(e) => {
+                eprintln!("cargo-single-line: unable to run itself: {}", e);
+                std::process::exit(1);
+            }
+        }
+        runs.total += started.elapsed();
+        eprintln!(
+            "{}",
+            styled(l10n::text("watch-waiting", &[("path", &root.display())]))
+        );
+        // The files changed while the command was running count as well.
+        let mut latest = take_snapshot(root);
+        while latest == files {
+            thread::sleep(POLL_INTERVAL);
+            latest = take_snapshot(root);
+        }
+        loop {
+            thread::sleep(SETTLE);
+            let settled = take_snapshot(root);
+            if settled == latest {
+                break;
+            }
+            latest = settled;
+        }
+    }
+}
+
+#[test]
+fn tracks_changes() {
+    let root = std::env::temp_dir().join(format!("cargo-single-line-watch-{}", std::process::id()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::write(root.join("src").join("lib.rs"), "").unwrap();
+    let before = take_snapshot(&root);
+    assert_eq!(before.len(), 1);
+
+    fs::write(root.join("target").join("out"), "").unwrap();
+    fs::write(root.join(".git").join("index"), "").unwrap();
+    assert_eq!(take_snapshot(&root), before);
+
+    fs::write(root.join("src").join("lib.rs"), "fn main() {}").unwrap();
+    assert_ne!(take_snapshot(&root), before);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn separates_runs() {
+    let mut runs = Runs::default();
+    assert_eq!(runs.separator(), "run #1");
+    runs.succeeded = 2;
+    runs.failed = 1;
+    runs.total = Duration::from_millis(12_400);
+    assert_eq!(
+        runs.separator(),
+        "run #4 (2 succeeded, 1 failed, 12.4s in total)"
+    );
+}
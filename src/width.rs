@@ -0,0 +1,148 @@
+//! Display width of the text on the terminal.
+//!
+//! The escape sequences (the colors, mostly) take no room, the CJK characters
+//! and most emoji take two columns, and the combining marks take none. That's
+//! a rough approximation of the Unicode East Asian Width, but it's enough for
+//! cargo's output.
+
+use std::borrow::Cow;
+
+/// The characters taking two columns.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE30, 0xFE4F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x1F900, 0x1F9FF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+/// The characters taking no room.
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x200B, 0x200F),
+    (0x20D0, 0x20FF),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+];
+
+/// How many columns the character takes.
+fn char_width(c: char) -> usize {
+    let in_ranges = |ranges: &[(u32, u32)]| {
+        ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&(c as u32)))
+    };
+    if c.is_control() || in_ranges(ZERO) {
+        0
+    } else if in_ranges(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// A piece of the text: an escape sequence, or a printable character along with
+/// its width.
+enum Piece<'a> {
+    Escape(&'a str),
+    Char(&'a str, usize),
+}
+
+/// Splits the text into the escape sequences and the printable characters.
+fn pieces(text: &str) -> impl Iterator<Item = Piece<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let length = match rest.strip_prefix("\x1b[") {
+            // A CSI sequence ends with a byte in the `@`..`~` range.
+            Some(sequence) => {
+                2 + sequence
+                    .find(|c| ('@'..='~').contains(&c))
+                    .map_or(sequence.len(), |end| end + 1)
+            }
+            None => c.len_utf8(),
+        };
+        let (piece, tail) = rest.split_at(length);
+        rest = tail;
+        Some(if length == c.len_utf8() && c != '\x1b' {
+            Piece::Char(piece, char_width(c))
+        } else {
+            Piece::Escape(piece)
+        })
+    })
+}
+
+/// How many columns the text takes on the terminal.
+pub fn width(text: &str) -> usize {
+    pieces(text)
+        .map(|piece| match piece {
+            Piece::Escape(_) => 0,
+            Piece::Char(_, width) => width,
+        })
+        .sum()
+}
+
+/// Cuts the text down to the given amount of columns, replacing the end of it
+/// with an ellipsis. The escape sequences are kept, and the style is reset
+/// after the ellipsis if there were any.
+pub fn truncate(text: &str, columns: usize) -> Cow<'_, str> {
+    if width(text) <= columns {
+        return Cow::Borrowed(text);
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    let mut styled = false;
+    for piece in pieces(text) {
+        match piece {
+            Piece::Escape(sequence) => {
+                styled = true;
+                truncated.push_str(sequence);
+            }
+            // Room for the ellipsis is reserved.
+            Piece::Char(c, width) if used + width < columns => {
+                used += width;
+                truncated.push_str(c);
+            }
+            Piece::Char(..) => break,
+        }
+    }
+    if columns > 0 {
+        truncated.push('…');
+    }
+    if styled {
+        truncated.push_str("\x1b[0m");
+    }
+    Cow::Owned(truncated)
+}
+
+#[test]
+fn measures_display_width() {
+    assert_eq!(width("Compiling foo"), 13);
+    assert_eq!(width("\x1b[1;32m   Compiling\x1b[0m foo"), 16);
+    assert_eq!(width("syn 🐢"), 6);
+    assert_eq!(width("日本語"), 6);
+    assert_eq!(width("e\u{301}"), 1);
+}
+
+#[test]
+fn truncates_to_columns() {
+    assert_eq!(truncate("Compiling foo", 13), "Compiling foo");
+    assert_eq!(truncate("Compiling foo", 10), "Compiling…");
+    assert_eq!(
+        truncate("\x1b[32mCompiling\x1b[0m foo", 5),
+        "\x1b[32mComp…\x1b[0m"
+    );
+    // A wide character doesn't fit in the last column left.
+    assert_eq!(truncate("ab日本", 4), "ab…");
+}
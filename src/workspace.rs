@@ -0,0 +1,183 @@
+//! The workspace members, as told by `cargo metadata`, so that the lines of
+//! one's own crates stand out among the dependencies: their names are
+//! highlighted on the status line, or with `--echo-workspace-members` the
+//! lines are printed for good.
+//!
+//! The lookup never holds the build up: until `cargo metadata` is done, the
+//! crates are taken for dependencies. A package outside of any workspace is
+//! its only member, read from its manifest without running cargo at all.
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+}
+
+/// Extracts the names of the members from the `cargo metadata --no-deps`
+/// output, where the only packages are the members.
+fn parse(output: &[u8]) -> Option<HashSet<String>> {
+    let metadata: Metadata = serde_json::from_slice(output).ok()?;
+    Some(
+        metadata
+            .packages
+            .into_iter()
+            .map(|package| package.name)
+            .collect(),
+    )
+}
+
+/// The manifests the package in the `dir` might belong to: its own and those
+/// of the directories above.
+fn manifests(dir: &Path) -> impl Iterator<Item = String> + '_ {
+    dir.ancestors()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("Cargo.toml")).ok())
+}
+
+/// Whether the manifest defines a workspace.
+fn is_workspace(manifest: &str) -> bool {
+    manifest
+        .lines()
+        .map(str::trim)
+        .any(|line| line == "[workspace]" || line.starts_with("[workspace."))
+}
+
+/// The name of the package defined in the manifest.
+fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if in_package && key.trim() == "name" {
+            return Some(value.trim().trim_matches('"').to_owned());
+        }
+    }
+    None
+}
+
+/// The workspace members, looked up in background.
+#[derive(Debug)]
+pub struct Members {
+    receiver: mpsc::Receiver<HashSet<String>>,
+    /// `None` until `cargo metadata` is done.
+    names: Option<HashSet<String>>,
+}
+
+impl Members {
+    /// Starts `cargo metadata` for the manifest, if it's given, or the current
+    /// directory (the `manifest_dir` either way). It neither resolves the
+    /// dependencies nor goes online, so it takes a fraction of the build's
+    /// startup.
+    pub fn look_up(cargo: OsString, manifest_path: Option<PathBuf>, manifest_dir: &Path) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let members = Members {
+            receiver,
+            names: None,
+        };
+        if !manifests(manifest_dir).any(|manifest| is_workspace(&manifest)) {
+            let names = manifests(manifest_dir)
+                .next()
+                .and_then(|manifest| package_name(&manifest));
+            let _ = sender.send(names.into_iter().collect());
+            return members;
+        }
+        std::thread::spawn(move || {
+            let mut cmd = Command::new(cargo);
+            cmd.args([
+                "metadata",
+                "--format-version",
+                "1",
+                "--no-deps",
+                "--offline",
+            ]);
+            if let Some(manifest_path) = manifest_path {
+                cmd.arg("--manifest-path").arg(manifest_path);
+            }
+            let output = cmd.stdin(Stdio::null()).stderr(Stdio::null()).output();
+            if let Some(names) = output
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| parse(&output.stdout))
+            {
+                let _ = sender.send(names);
+            }
+        });
+        members
+    }
+
+    /// Whether the crate is a member of the workspace. The crates are
+    /// considered dependencies while `cargo metadata` is still running.
+    pub fn contains(&mut self, name: &str) -> bool {
+        if self.names.is_none() {
+            match self.receiver.try_recv() {
+                Ok(names) => self.names = Some(names),
+                Err(mpsc::TryRecvError::Empty) => return false,
+                // The lookup has failed, there's nothing to wait for.
+                Err(mpsc::TryRecvError::Disconnected) => self.names = Some(HashSet::new()),
+            }
+        }
+        self.names
+            .as_ref()
+            .is_some_and(|names| names.contains(name))
+    }
+}
+
+/// Makes the name of the crate on its cargo's line bold magenta.
+pub fn highlight(line: &str, name: &str) -> String {
+    line.replacen(
+        &format!(" {} v", name),
+        &format!(" \x1b[1;35m{}\x1b[0m v", name),
+        1,
+    )
+}
+
+#[test]
+fn parses_members() {
+    let output = br#"{"packages":[{"name":"foo","version":"0.1.0"},{"name":"foo-macros"}],"workspace_members":[],"version":1}"#;
+    let names = parse(output).unwrap();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains("foo") && names.contains("foo-macros"));
+    assert!(parse(b"error").is_none());
+}
+
+#[test]
+fn highlights_members() {
+    assert_eq!(
+        highlight(
+            "\x1b[1m\x1b[32m   Compiling\x1b[0m foo v0.1.0 (/work/foo)\n",
+            "foo"
+        ),
+        "\x1b[1m\x1b[32m   Compiling\x1b[0m \x1b[1;35mfoo\x1b[0m v0.1.0 (/work/foo)\n"
+    );
+}
+
+#[test]
+fn reads_manifests() {
+    let manifest =
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n\n[dependencies]\nname = \"bar\"\n";
+    assert_eq!(package_name(manifest).as_deref(), Some("foo"));
+    assert!(!is_workspace(manifest));
+    assert!(is_workspace("[workspace]\nmembers = [\"foo\"]\n"));
+    assert!(is_workspace(
+        "[package]\nname = \"foo\"\n[workspace.dependencies]\n"
+    ));
+    assert_eq!(package_name("[workspace]\nmembers = []\n"), None);
+}
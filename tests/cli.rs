@@ -0,0 +1,233 @@
+//! Runs the plugin against small projects generated in a temporary directory.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// Writes a project of the `files` to a directory of its own, out of the
+/// reach of the repository's `.cargo/config`.
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir()
+        .join("cargo-single-line-tests")
+        .join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        name
+    );
+    for (path, contents) in [("Cargo.toml", manifest.as_str())]
+        .into_iter()
+        .chain(files.iter().copied())
+    {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    root
+}
+
+/// Runs the plugin in the `project` with the output piped.
+fn single_line(project: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cargo-single-line"))
+        .arg("single-line")
+        .args(args)
+        .current_dir(project)
+        .env_remove("CARGO_SINGLE_LINE_DEPTH")
+        .env_remove("CARGO_SINGLE_LINE_PRESET")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn keeps_test_results_in_redirected_stdout() {
+    let project = project(
+        "redirected-tests",
+        &[(
+            "src/lib.rs",
+            "#[test]\nfn first() {}\n\n#[test]\nfn second() {}\n\n#[test]\n#[ignore]\nfn third() {}\n",
+        )],
+    );
+    let output = single_line(&project, &["test", "--lib"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in [
+        "running 3 tests",
+        "test first ... ok",
+        "test second ... ok",
+        "test third ... ignored",
+        "test result: ok. 2 passed; 0 failed; 1 ignored;",
+    ] {
+        assert!(
+            stdout.contains(line),
+            "{:?} missing from {:?}",
+            line,
+            stdout
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_keeps_redirected_stdout() {
+    let output = single_line(
+        Path::new(env!("CARGO_TARGET_TMPDIR")),
+        &["exec", "sh", "-c", "echo out; echo err >&2; kill -TERM $$"],
+    );
+    assert_eq!(output.stdout, b"out\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("err"));
+    assert_eq!(output.status.code(), Some(128 + 15));
+}
+
+#[test]
+fn propagates_exit_code_of_failed_build() {
+    let project = project(
+        "failing-build",
+        &[(
+            "src/lib.rs",
+            "pub fn broken() -> u32 { \"not a number\" }\n",
+        )],
+    );
+    let output = single_line(&project, &["build"]);
+    // Cargo's own code for a failed compilation.
+    assert_eq!(output.status.code(), Some(101), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error[E0308]"), "{}", stderr);
+
+    let output = single_line(&project, &["build", "--no-such-option"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+}
+
+#[test]
+fn stops_alias_loops() {
+    let project = project(
+        "alias-loop",
+        &[
+            (
+                ".cargo/config.toml",
+                "[alias]\nloop = \"single-line loop\"\n",
+            ),
+            ("src/lib.rs", ""),
+        ],
+    );
+    // Cargo finds the plugin in the `PATH`.
+    let plugin_dir = Path::new(env!("CARGO_BIN_EXE_cargo-single-line"))
+        .parent()
+        .unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(plugin_dir.to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-single-line"))
+        .args(["single-line", "loop"])
+        .current_dir(&project)
+        .env("PATH", path)
+        .env_remove("CARGO_SINGLE_LINE_DEPTH")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("giving up").count(), 1, "{}", stderr);
+    assert!(!stderr.contains("recursively"), "{}", stderr);
+}
+
+#[test]
+fn wraps_up_stalled_builds() {
+    let project = project(
+        "stalled",
+        &[(
+            "transcript.json",
+            "{\"ms\":0,\"stderr\":\"    Updating crates.io index\\n\"}\n\
+             {\"ms\":60000,\"stderr\":\"    Finished `dev` profile\\n\"}\n\
+             {\"ms\":60000,\"exit\":0}\n",
+        )],
+    );
+    let log = project.join("build.log");
+    let output = single_line(
+        &project,
+        &[
+            "--dry-filter",
+            "transcript.json",
+            "--realtime",
+            "--stall-timeout",
+            "1",
+            "--exit-on-stall",
+            "--log-file",
+            log.to_str().unwrap(),
+            "build",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(75), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("stalled"));
+    // The log file is flushed once the build is over, so it's wrapped up.
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.contains("Updating crates.io index"), "{:?}", log);
+}
+
+/// Replays a `cargo miri test` run through the plugin in a terminal, as the
+/// test results are only folded when stdout is one.
+#[cfg(target_os = "linux")]
+#[test]
+fn folds_miri_tests() {
+    let project = project(
+        "miri-tests",
+        &[(
+            "transcript.json",
+            r#"{"ms":0,"stderr":"Preparing a sysroot for Miri (target: x86_64-unknown-linux-gnu)... done\n"}
+{"ms":1,"stderr":"     Running unittests src/lib.rs (target/miri/x86_64-unknown-linux-gnu/debug/deps/foo-1)\n"}
+{"ms":2,"stdout":"\n"}
+{"ms":3,"stdout":"running 2 tests\n"}
+{"ms":4,"stdout":"test first ... ok\n"}
+{"ms":5,"stdout":"test second ... ok\n"}
+{"ms":6,"stdout":"\n"}
+{"ms":7,"stdout":"test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n"}
+{"ms":8,"stdout":"\n"}
+{"ms":9,"exit":0}
+"#,
+        )],
+    );
+    let command = format!(
+        "{} single-line --no-keys --dry-filter transcript.json miri test",
+        env!("CARGO_BIN_EXE_cargo-single-line")
+    );
+    // `script` from util-linux runs the command in a pseudo terminal.
+    let Ok(output) = Command::new("script")
+        .args(["-qec", &command, "/dev/null"])
+        .current_dir(&project)
+        .env_remove("CARGO_SINGLE_LINE_DEPTH")
+        .env_remove("CARGO_SINGLE_LINE_PRESET")
+        .output()
+    else {
+        return;
+    };
+    assert!(output.status.success(), "{:?}", output);
+    let terminal = String::from_utf8_lossy(&output.stdout);
+    assert!(terminal.contains("tests: 2 passed"), "{:?}", terminal);
+    assert!(!terminal.contains("test first ... ok"), "{:?}", terminal);
+}
+
+#[test]
+fn records_builds_compiling_nothing() {
+    let project = project("history", &[("src/lib.rs", "")]);
+    let builds = || -> Vec<serde_json::Value> {
+        let history = project.join(".cargo-single-line").join("history.json");
+        serde_json::from_slice(&std::fs::read(history).unwrap()).unwrap()
+    };
+    assert!(single_line(&project, &["check"]).status.success());
+    // Nothing to compile the second time.
+    assert!(single_line(&project, &["check"]).status.success());
+    assert_eq!(builds().len(), 2);
+    assert_eq!(builds()[1]["crates"], serde_json::json!([]));
+    // Failing before anything is compiled.
+    let manifest = project.join("Cargo.toml");
+    let contents = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        contents + "\n[dependencies]\nmissing = { path = \"missing\" }\n",
+    )
+    .unwrap();
+    assert!(!single_line(&project, &["check"]).status.success());
+    assert_eq!(builds().len(), 3);
+    assert_eq!(builds()[2]["success"], false);
+}